@@ -1,7 +1,7 @@
 mod projects;
 
 use crate::projects::{create_project, delete_project, get_project, get_projects, update_project};
-use axum::extract::{DefaultBodyLimit, Multipart, State};
+use axum::extract::{DefaultBodyLimit, Multipart, Query, State};
 use axum::http::{HeaderValue, StatusCode};
 use axum::routing::{get, post};
 use axum::{Json, Router};
@@ -133,12 +133,31 @@ async fn query_symbols_by_slug(
     State(pg_pool): State<PgPool>,
     axum::extract::Path(slug): axum::extract::Path<String>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
-    let sym = get_sym_for_slug(pg_pool, &slug).await?;
+    let sym = get_sym_for_slug(pg_pool.clone(), &slug).await?;
+    let search_space = count_symbols_for_platform(pg_pool, sym.platform).await?;
 
-    Ok((
-        StatusCode::OK,
-        json!(SymbolMetadata::from_db_symbol(&sym)).to_string(),
-    ))
+    let metadata = SymbolMetadata {
+        significance: sym.significance(search_space),
+        ..SymbolMetadata::from_db_symbol(&sym)
+    };
+
+    Ok((StatusCode::OK, json!(metadata).to_string()))
+}
+
+/// Number of symbols a symbol of this platform is matched against
+async fn count_symbols_for_platform(
+    pg_pool: PgPool,
+    platform: i32,
+) -> Result<i64, (StatusCode, String)> {
+    coddog_db::opcodes::count_symbols(pg_pool, platform)
+        .await
+        .map_err(|e| {
+            eprintln!("Error counting symbols: {e}");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({"success": false, "message": e.to_string()}).to_string(),
+            )
+        })
 }
 
 fn get_asm_for_symbol(
@@ -172,11 +191,19 @@ struct SymbolMatchResult {
     symbol: SymbolMetadata,
 }
 
+#[derive(Deserialize)]
+struct GetSymbolMatchesParams {
+    /// Leave out matches expected to happen by chance more than this many times
+    max_evalue: Option<f64>,
+}
+
 async fn get_symbol_matches(
     State(pg_pool): State<PgPool>,
     axum::extract::Path(slug): axum::extract::Path<String>,
+    Query(params): Query<GetSymbolMatchesParams>,
 ) -> Result<(StatusCode, String), (StatusCode, String)> {
     let query_sym = get_sym_for_slug(pg_pool.clone(), &slug).await?;
+    let search_space = count_symbols_for_platform(pg_pool.clone(), query_sym.platform).await?;
 
     let mut found_stuff = HashSet::new();
 
@@ -191,6 +218,19 @@ async fn get_symbol_matches(
         })?;
     found_stuff.extend(exact_matches.iter().map(|m| m.id));
 
+    let mut semantic_matches =
+        coddog_db::symbols::query_by_semantic_hash(pg_pool.clone(), &query_sym)
+            .await
+            .map_err(|e| {
+                eprintln!("Error getting semantic matches: {e}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({"success": false, "message": e.to_string()}).to_string(),
+                )
+            })?;
+    semantic_matches.retain(|m| !found_stuff.contains(&m.id));
+    found_stuff.extend(semantic_matches.iter().map(|m| m.id));
+
     let mut equivalent_matches =
         coddog_db::symbols::query_by_equiv_hash(pg_pool.clone(), &query_sym)
             .await
@@ -204,6 +244,19 @@ async fn get_symbol_matches(
     equivalent_matches.retain(|m| !found_stuff.contains(&m.id));
     found_stuff.extend(equivalent_matches.iter().map(|m| m.id));
 
+    let mut regalloc_matches =
+        coddog_db::symbols::query_by_regalloc_hash(pg_pool.clone(), &query_sym)
+            .await
+            .map_err(|e| {
+                eprintln!("Error getting regalloc matches: {e}");
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    json!({"success": false, "message": e.to_string()}).to_string(),
+                )
+            })?;
+    regalloc_matches.retain(|m| !found_stuff.contains(&m.id));
+    found_stuff.extend(regalloc_matches.iter().map(|m| m.id));
+
     let mut opcode_matches = coddog_db::symbols::query_by_opcode_hash(pg_pool.clone(), &query_sym)
         .await
         .map_err(|e| {
@@ -215,31 +268,40 @@ async fn get_symbol_matches(
         })?;
     opcode_matches.retain(|m| !found_stuff.contains(&m.id));
 
-    let exact_matches: Vec<SymbolMatchResult> = exact_matches
-        .iter()
-        .map(|s| SymbolMatchResult {
-            subtype: "exact".to_string(),
-            symbol: SymbolMetadata::from_db_symbol(s),
-        })
-        .collect();
-    let equivalent_matches: Vec<SymbolMatchResult> = equivalent_matches
-        .iter()
-        .map(|s| SymbolMatchResult {
-            subtype: "equivalent".to_string(),
-            symbol: SymbolMetadata::from_db_symbol(s),
-        })
-        .collect();
-    let opcode_matches: Vec<SymbolMatchResult> = opcode_matches
-        .iter()
-        .map(|s| SymbolMatchResult {
-            subtype: "opcode".to_string(),
-            symbol: SymbolMetadata::from_db_symbol(s),
-        })
-        .collect();
+    // Within each level, the matches least likely to be coincidences come first
+    let to_results = |matches: &[DBSymbol], subtype: &str| -> Vec<SymbolMatchResult> {
+        let mut results: Vec<SymbolMatchResult> = matches
+            .iter()
+            .map(|s| SymbolMatchResult {
+                subtype: subtype.to_string(),
+                symbol: SymbolMetadata {
+                    significance: s.significance(search_space),
+                    ..SymbolMetadata::from_db_symbol(s)
+                },
+            })
+            .filter(|r| match (params.max_evalue, r.symbol.significance) {
+                (Some(max_evalue), Some(significance)) => significance.evalue <= max_evalue,
+                _ => true,
+            })
+            .collect();
+
+        let evalue =
+            |r: &SymbolMatchResult| r.symbol.significance.map_or(f64::INFINITY, |s| s.evalue);
+        results.sort_by(|a, b| evalue(a).total_cmp(&evalue(b)));
+        results
+    };
+
+    let exact_matches = to_results(&exact_matches, "exact");
+    let semantic_matches = to_results(&semantic_matches, "semantic");
+    let equivalent_matches = to_results(&equivalent_matches, "equivalent");
+    let regalloc_matches = to_results(&regalloc_matches, "regalloc");
+    let opcode_matches = to_results(&opcode_matches, "opcode");
 
     let all_matches: Vec<SymbolMatchResult> = exact_matches
         .iter()
+        .chain(semantic_matches.iter())
         .chain(equivalent_matches.iter())
+        .chain(regalloc_matches.iter())
         .chain(opcode_matches.iter())
         .cloned()
         .collect();
@@ -263,6 +325,8 @@ struct GetSubmatchesRequest {
     pub sort_by: SubmatchResultOrder,
     #[validate(custom(function = "validate_sort_dir"))]
     pub sort_dir: SortDirection,
+    #[validate(range(max = 16))]
+    pub max_edits: Option<usize>,
 }
 
 fn validate_window_size(input: i64) -> Result<(), ValidationError> {
@@ -337,15 +401,15 @@ async fn upload_object(
                 println!("Length of `{}` is {} bytes", name, data.len());
 
                 // TODO remove hard-coded platform
+                let platform = coddog_core::Platform::N64;
                 let symbols =
-                    coddog_core::ingest::read_elf(coddog_core::Platform::N64, &None, &data)
-                        .map_err(|e| {
-                            eprintln!("Error reading ELF: {e}");
-                            (
-                                StatusCode::BAD_REQUEST,
-                                json!({"success": false, "message": e.to_string()}).to_string(),
-                            )
-                        })?;
+                    coddog_core::ingest::read_elf(platform, &None, &data).map_err(|e| {
+                        eprintln!("Error reading ELF: {e}");
+                        (
+                            StatusCode::BAD_REQUEST,
+                            json!({"success": false, "message": e.to_string()}).to_string(),
+                        )
+                    })?;
 
                 if symbols.is_empty() {
                     return Err((
@@ -385,8 +449,41 @@ async fn upload_object(
                     )
                 })?;
 
+                coddog_db::opcodes::set_source_counts(&mut tx, source_id, &symbols)
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Error counting opcodes: {e}");
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            json!({"success": false, "message": e.to_string()}).to_string(),
+                        )
+                    })?;
+                let background = coddog_db::opcodes::query_background(&mut tx, platform as i32)
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Error getting opcode background: {e}");
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            json!({"success": false, "message": e.to_string()}).to_string(),
+                        )
+                    })?;
+
                 let symbol_ids =
                     coddog_db::symbols::create_many(&mut tx, source_id, &symbols).await;
+                let symbol_opcodes: Vec<(i64, Vec<u16>)> = symbol_ids
+                    .iter()
+                    .zip(&symbols)
+                    .map(|(id, symbol)| (*id, symbol.opcodes.clone()))
+                    .collect();
+                coddog_db::symbols::set_opcode_bits(&mut tx, &symbol_opcodes, &background)
+                    .await
+                    .map_err(|e| {
+                        eprintln!("Error measuring opcode bits: {e}");
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            json!({"success": false, "message": e.to_string()}).to_string(),
+                        )
+                    })?;
 
                 for (symbol, id) in symbols.iter().zip(symbol_ids) {
                     let opcode_hashes = symbol.get_opcode_hashes(db_window_size as usize);
@@ -474,6 +571,7 @@ async fn get_symbol_submatches(
             page: req.page_num,
             sort_by: req.sort_by,
             sort_direction: req.sort_dir,
+            max_edits: req.max_edits,
         },
     )
     .await
@@ -1,20 +1,32 @@
-use coddog_core::diff_symbols;
+use coddog_core::{
+    SimilarityMetric, SimilarityReport, diff_symbols, significance::OpcodeBackground,
+};
 
 use crate::*;
 
 #[derive(Debug)]
 pub struct Cluster<'a> {
-    pub syms: Vec<&'a Symbol>,
+    /// The first symbol found, which every other one was compared against
+    pub head: &'a Symbol,
+    /// The other symbols, with how similar each is to the head
+    pub members: Vec<(&'a Symbol, SimilarityReport)>,
 }
 
 impl Cluster<'_> {
     pub fn size(&self) -> usize {
-        self.syms.len()
+        self.members.len() + 1
     }
 }
 
-pub fn get_clusters(symbols: &[Symbol], threshold: f32, min_len: usize) -> Vec<Cluster<'_>> {
+pub fn get_clusters(
+    symbols: &[Symbol],
+    metric: SimilarityMetric,
+    threshold: f32,
+    min_len: usize,
+    max_evalue: Option<f64>,
+) -> Vec<Cluster<'_>> {
     let mut clusters: Vec<Cluster> = Vec::new();
+    let background = OpcodeBackground::new(symbols);
 
     symbols
         .iter()
@@ -23,17 +35,25 @@ pub fn get_clusters(symbols: &[Symbol], threshold: f32, min_len: usize) -> Vec<C
             let mut cluster_match = false;
 
             for cluster in &mut clusters {
-                let cluster_score = diff_symbols(symbol, cluster.syms[0], threshold);
-                if cluster_score > threshold {
+                if let Some(mut report) = diff_symbols(symbol, cluster.head, metric, threshold) {
+                    report.significance =
+                        Some(background.significance(symbol, cluster.head, symbols.len()));
+                    if !is_significant(&report, max_evalue) {
+                        continue;
+                    }
+
                     cluster_match = true;
-                    cluster.syms.push(symbol);
+                    cluster.members.push((symbol, report));
                     break;
                 }
             }
 
             // Add this symbol to a new cluster if it didn't match any existing clusters
             if !cluster_match {
-                clusters.push(Cluster { syms: vec![symbol] });
+                clusters.push(Cluster {
+                    head: symbol,
+                    members: Vec::new(),
+                });
             }
         });
 
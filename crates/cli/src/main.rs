@@ -3,14 +3,21 @@ mod cluster;
 use anyhow::{Result, anyhow};
 use clap::{Parser, Subcommand, ValueEnum};
 use coddog_core::{
-    self as core, Binary, Platform, Symbol, get_submatches,
-    ingest::{read_elf, read_map},
+    self as core, Binary, Platform, SimilarityMetric, SimilarityReport, Symbol,
+    align::{AlignmentKind, align_symbols},
+    arch::get_insn_offsets_raw,
+    callgraph::propagate_matches,
+    executable::{self, Executable},
+    ingest::{read_archive, read_object, read_target},
+    significance::OpcodeBackground,
+    submatch::{GapScoring, SubmatchIndex},
 };
 
 use colored::*;
 use decomp_settings::{config::Version, read_config, scan_for_config};
 use glob::glob;
 use inquire::Select;
+use serde::Serialize;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::{
@@ -48,6 +55,41 @@ enum Commands {
         /// Similarity threshold
         #[arg(short, long, default_value = "0.985")]
         threshold: f32,
+
+        /// Which similarity score to rank and filter matches by
+        #[arg(long, value_enum, default_value_t)]
+        score_by: ScoreBy,
+
+        /// Skip functions whose control flow graphs are less similar than this, before aligning them
+        #[arg(long)]
+        min_structural: Option<f32>,
+
+        /// Method for sorting matches
+        #[arg(long, value_enum, default_value = "similarity")]
+        sort_by: CompareSort,
+
+        /// Only show matches expected to happen by chance at most this many times
+        #[arg(long)]
+        max_evalue: Option<f64>,
+
+        /// Treat functions as equal when they only differ in the order of independent instructions
+        /// or in what fills their delay slots (MIPS only)
+        #[arg(long)]
+        tolerate_scheduling: bool,
+
+        /// Print matches as JSON, with every similarity score
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show where two functions differ, instruction by instruction
+    /// Uses project in the current directory
+    Diff {
+        /// Name of the first function
+        query1: String,
+
+        /// Name of the second function
+        query2: String,
     },
 
     /// Cluster functions by similarity, showing possible duplicates
@@ -60,6 +102,24 @@ enum Commands {
         /// Minimum length of functions (in number of instructions) to consider
         #[arg(short, long, default_value = "5")]
         min_len: usize,
+
+        /// Which similarity score to cluster by
+        #[arg(long, value_enum, default_value_t)]
+        score_by: ScoreBy,
+
+        /// Only cluster functions whose similarity is expected to happen by chance at most this
+        /// many times
+        #[arg(long)]
+        max_evalue: Option<f64>,
+
+        /// Treat functions as equal when they only differ in the order of independent instructions
+        /// or in what fills their delay slots (MIPS only)
+        #[arg(long)]
+        tolerate_scheduling: bool,
+
+        /// Print clusters as JSON, with every similarity score
+        #[arg(long)]
+        json: bool,
     },
 
     /// Find chunks of code similar to those in the query function
@@ -70,6 +130,18 @@ enum Commands {
 
         /// Window size (smaller values will find more matches but take longer)
         window_size: usize,
+
+        /// Other projects' decomp.yaml files to search as well, in every version
+        #[arg(long, num_args = 1..)]
+        others: Vec<PathBuf>,
+
+        /// Extend matches across instructions that differ, rather than only finding exact runs
+        #[arg(long)]
+        gapped: bool,
+
+        /// Most mismatching or inserted instructions a gapped match can span
+        #[arg(long, default_value = "4")]
+        max_edits: usize,
     },
 
     /// Compare two binaries, showing the functions in common between them
@@ -97,6 +169,32 @@ enum Commands {
         /// Method for sorting output symbols
         #[arg(long, value_enum)]
         sort_by: CompareSort,
+
+        /// Which similarity score to rank and filter matches by
+        #[arg(long, value_enum, default_value_t)]
+        score_by: ScoreBy,
+
+        /// Skip functions whose control flow graphs are less similar than this, before aligning them
+        #[arg(long)]
+        min_structural: Option<f32>,
+
+        /// Also match functions scoring above this lower threshold, if they call or are called by
+        /// functions that matched
+        #[arg(long)]
+        propagate: Option<f32>,
+
+        /// Only show matches expected to happen by chance at most this many times
+        #[arg(long)]
+        max_evalue: Option<f64>,
+
+        /// Treat functions as equal when they only differ in the order of independent instructions
+        /// or in what fills their delay slots (MIPS only)
+        #[arg(long)]
+        tolerate_scheduling: bool,
+
+        /// Print matches as JSON, with every similarity score
+        #[arg(long)]
+        json: bool,
     },
 
     /// Compare a binary in one project to one or more others, showing the functions in common between them
@@ -113,11 +211,56 @@ enum Commands {
         /// Method for sorting output symbols
         #[arg(long, value_enum)]
         sort_by: CompareSort,
+
+        /// Which similarity score to rank and filter matches by
+        #[arg(long, value_enum, default_value_t)]
+        score_by: ScoreBy,
+
+        /// Only show matches expected to happen by chance at most this many times
+        #[arg(long)]
+        max_evalue: Option<f64>,
+    },
+
+    /// Compare a static library (e.g. an SDK's) to a project's binary, showing the library's
+    /// functions the binary contains
+    CompareLib {
+        /// Path to the library (.a or .lib)
+        lib: PathBuf,
+
+        /// Path to the project's decomp.yaml
+        yaml: PathBuf,
+
+        /// Version of the project to compare
+        version: String,
+
+        /// Similarity threshold
+        #[arg(short, long, default_value = "0.985")]
+        threshold: f32,
+
+        /// Minimum length of functions (in number of instructions) to consider
+        #[arg(short, long, default_value = "5")]
+        min_len: usize,
+
+        /// Method for sorting output symbols
+        #[arg(long, value_enum, default_value_t)]
+        sort_by: CompareSort,
+
+        /// Which similarity score to rank and filter matches by
+        #[arg(long, value_enum, default_value_t)]
+        score_by: ScoreBy,
+
+        /// Only show matches expected to happen by chance at most this many times
+        #[arg(long)]
+        max_evalue: Option<f64>,
+
+        /// Print matches as JSON, with every similarity score
+        #[arg(long)]
+        json: bool,
     },
 
     /// Compare one raw binary to one or more projects' binaries, showing the functions in common between them
     CompareRaw {
-        /// Path to the main binary
+        /// Path to the main binary: raw code, or a PS-X EXE, PS2 ELF or PSP PRX
         query_bin: PathBuf,
 
         /// Path to other projects' decomp.yaml files
@@ -131,16 +274,79 @@ enum MatchType {
     Opcode,
     /// Opcodes and some operands are compared
     Equivalent,
+    /// Like equivalent, but registers are compared by the order they're first used in (MIPS and
+    /// PPC only)
+    Regalloc,
+    /// Like equivalent, but the names of well-known symbols referred to are compared too
+    Semantic,
     /// Exact bytes are compared
     Exact,
 }
 
+impl MatchType {
+    /// The strictest level at which the two symbols' hashes are the same, if any
+    fn between(sym1: &Symbol, sym2: &Symbol) -> Option<MatchType> {
+        if sym1.exact_hash == sym2.exact_hash {
+            Some(MatchType::Exact)
+        } else if sym1.semantic_hash.is_some() && sym1.semantic_hash == sym2.semantic_hash {
+            Some(MatchType::Semantic)
+        } else if sym1.equiv_hash == sym2.equiv_hash {
+            Some(MatchType::Equivalent)
+        } else if sym1.regalloc_hash.is_some() && sym1.regalloc_hash == sym2.regalloc_hash {
+            Some(MatchType::Regalloc)
+        } else if sym1.opcode_hash == sym2.opcode_hash {
+            Some(MatchType::Opcode)
+        } else {
+            None
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            MatchType::Opcode => "opcode",
+            MatchType::Equivalent => "equivalent",
+            MatchType::Regalloc => "regalloc",
+            MatchType::Semantic => "semantic",
+            MatchType::Exact => "exact",
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
+enum ScoreBy {
+    /// Only opcodes are compared
+    #[default]
+    Opcode,
+    /// Opcodes and some operands are compared
+    Equivalent,
+    /// Only the functions' lengths are compared
+    LengthRatio,
+    /// The functions' control flow graphs are compared, regardless of block order
+    Structural,
+    /// How much of the shorter function is found within the longer one, e.g. when inlined
+    Containment,
+}
+
+impl ScoreBy {
+    fn metric(self) -> SimilarityMetric {
+        match self {
+            ScoreBy::Opcode => SimilarityMetric::Opcode,
+            ScoreBy::Equivalent => SimilarityMetric::Equivalent,
+            ScoreBy::LengthRatio => SimilarityMetric::LengthRatio,
+            ScoreBy::Structural => SimilarityMetric::Structural,
+            ScoreBy::Containment => SimilarityMetric::Containment,
+        }
+    }
+}
+
 #[derive(ValueEnum, Clone, Copy, Default, PartialEq, Eq)]
 enum CompareSort {
     Name,
     #[default]
     VramAddr,
     Similarity,
+    /// Least likely to be a coincidence first
+    Significance,
 }
 
 fn cli_fullname(sym: &Symbol) -> String {
@@ -156,72 +362,437 @@ fn cli_fullname(sym: &Symbol) -> String {
 }
 
 fn cli_name_colored(sym: &Symbol, color: Color) -> String {
-    format!("{}", sym.name.clone().color(color))
+    match &sym.library {
+        Some(library) => format!("{} ({})", sym.name.clone().color(color), library.member),
+        None => format!("{}", sym.name.clone().color(color)),
+    }
+}
+
+/// Order reports by the given score, breaking ties by whether the bytes match exactly, then by
+/// structural similarity, then by how much information the match carries
+fn compare_reports(
+    a: &SimilarityReport,
+    b: &SimilarityReport,
+    metric: SimilarityMetric,
+) -> Ordering {
+    a.score(metric)
+        .partial_cmp(&b.score(metric))
+        .unwrap_or(Ordering::Equal)
+        .then(a.exact.cmp(&b.exact))
+        .then(
+            a.structural
+                .partial_cmp(&b.structural)
+                .unwrap_or(Ordering::Equal),
+        )
+        .then(compare_significance(a, b).reverse())
+}
+
+/// Order reports from the least to the most likely to be a coincidence
+fn compare_significance(a: &SimilarityReport, b: &SimilarityReport) -> Ordering {
+    let evalue = |r: &SimilarityReport| r.significance.map_or(f64::INFINITY, |s| s.evalue);
+    evalue(a).partial_cmp(&evalue(b)).unwrap_or(Ordering::Equal)
 }
 
-fn do_match(query: &str, symbols: &[Symbol], threshold: f32) {
+/// Whether a match is expected to happen by chance at most `max_evalue` times
+fn is_significant(report: &SimilarityReport, max_evalue: Option<f64>) -> bool {
+    match (max_evalue, report.significance) {
+        (Some(max_evalue), Some(significance)) => significance.evalue <= max_evalue,
+        _ => true,
+    }
+}
+
+/// Compare two symbols, skipping the alignment if their control flow graphs are less similar
+/// than `min_structural`
+fn diff_prefiltered(
+    sym1: &Symbol,
+    sym2: &Symbol,
+    metric: SimilarityMetric,
+    threshold: f32,
+    min_structural: Option<f32>,
+) -> Option<SimilarityReport> {
+    if let Some(min_structural) = min_structural
+        && core::cfg::structural_similarity(sym1, sym2) < min_structural
+    {
+        return None;
+    }
+    core::diff_symbols(sym1, sym2, metric, threshold)
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+#[derive(Serialize)]
+struct JsonSymbol<'a> {
+    name: &'a str,
+    vram: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member: Option<&'a str>,
+    is_decompiled: bool,
+}
+
+impl<'a> From<&'a Symbol> for JsonSymbol<'a> {
+    fn from(sym: &'a Symbol) -> Self {
+        Self {
+            name: &sym.name,
+            vram: sym.vram,
+            segment: sym.segment.as_deref(),
+            archive: sym.library.as_ref().map(|l| l.archive.as_str()),
+            member: sym.library.as_ref().map(|l| l.member.as_str()),
+            is_decompiled: sym.is_decompiled,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn do_match(
+    query: &str,
+    symbols: &[Symbol],
+    threshold: f32,
+    score_by: ScoreBy,
+    min_structural: Option<f32>,
+    sort_by: CompareSort,
+    max_evalue: Option<f64>,
+    tolerate_scheduling: bool,
+    json: bool,
+) {
+    #[derive(Serialize)]
     struct FunctionMatch<'a> {
-        symbol: &'a Symbol,
-        score: f32,
+        #[serde(flatten)]
+        symbol: JsonSymbol<'a>,
+        level: Option<&'static str>,
+        similarity: SimilarityReport,
     }
 
-    let Some(query_sym) = symbols.iter().find(|s| s.name == query) else {
+    let Some(query_idx) = symbols.iter().position(|s| s.name == query) else {
         println!("Symbol {query:} not found");
         return;
     };
 
-    let mut matches: Vec<FunctionMatch> = symbols
+    // Rescheduled symbols are compared, but differences are reported at the query's own insns
+    let rescheduled: Vec<Symbol>;
+    let (symbols, query_insns): (&[Symbol], Vec<usize>) = if tolerate_scheduling {
+        rescheduled = symbols.iter().map(Symbol::rescheduled).collect();
+        (&rescheduled[..], symbols[query_idx].canonical_order.clone())
+    } else {
+        (symbols, (0..symbols[query_idx].opcodes.len()).collect())
+    };
+    let query_sym = &symbols[query_idx];
+
+    let metric = score_by.metric();
+    let background = OpcodeBackground::new(symbols);
+
+    let mut matches: Vec<(&Symbol, SimilarityReport)> = symbols
         .iter()
         .filter(|s| s.name != query_sym.name)
-        .map(|s| FunctionMatch {
-            symbol: s,
-            score: core::diff_symbols(query_sym, s, threshold),
+        .filter_map(|s| {
+            let mut report = diff_prefiltered(query_sym, s, metric, threshold, min_structural)?;
+            report.significance = Some(background.significance(query_sym, s, symbols.len()));
+            is_significant(&report, max_evalue).then_some((s, report))
         })
-        .filter(|m| m.score > threshold)
         .collect();
 
-    // sort by score descending
-    matches.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+    matches.sort_by(|a, b| match sort_by {
+        CompareSort::Name => a.0.name.cmp(&b.0.name),
+        CompareSort::VramAddr => a.0.vram.cmp(&b.0.vram),
+        CompareSort::Similarity => compare_reports(&b.1, &a.1, metric),
+        CompareSort::Significance => compare_significance(&a.1, &b.1),
+    });
+
+    if json {
+        let matches: Vec<FunctionMatch> = matches
+            .iter()
+            .map(|(sym, report)| FunctionMatch {
+                symbol: JsonSymbol::from(*sym),
+                level: MatchType::between(query_sym, sym).map(|t| t.name()),
+                similarity: *report,
+            })
+            .collect();
+        print_json(&matches);
+        return;
+    }
+
+    for (sym, report) in matches {
+        let score = report.score(metric) * 100.0;
+        match MatchType::between(query_sym, sym) {
+            Some(match_type) => println!(
+                "{score:.2}% - {} ({})",
+                cli_fullname(sym),
+                match_type.name()
+            ),
+            None => println!("{score:.2}% - {}", cli_fullname(sym)),
+        }
+
+        let alignment = align_symbols(query_sym, sym);
+        let mut differing: Vec<usize> = alignment
+            .rows
+            .iter()
+            .filter(|r| r.differs())
+            .filter_map(|r| r.left)
+            .map(|i| query_insns[i])
+            .collect();
+        differing.sort();
+        let inserted = alignment
+            .rows
+            .iter()
+            .filter(|r| r.kind == AlignmentKind::Insertion)
+            .count();
+
+        if !differing.is_empty() {
+            println!("\tdiffers at query insns {}", format_ranges(&differing));
+        }
+        if inserted > 0 {
+            println!("\t{inserted} insns not in query");
+        }
+        if report.reloc_diffs > 0 {
+            println!(
+                "\t{} insns relocated against different targets",
+                report.reloc_diffs
+            );
+        }
+        if let Some(significance) = report.significance {
+            println!(
+                "\tE-value {:.2e} ({:.1} bits)",
+                significance.evalue, significance.bits
+            );
+        }
+        if let Some(containment) = report.containment {
+            if containment.first_in_second {
+                println!(
+                    "\tquery is contained in {} at insns {}-{}",
+                    sym.name, containment.start, containment.end
+                );
+            } else {
+                println!(
+                    "\t{} is contained in query at insns {}-{}",
+                    sym.name, containment.start, containment.end
+                );
+            }
+        }
+    }
+}
 
-    for m in matches {
-        println!("{:.2}% - {}", m.score * 100.0, cli_fullname(m.symbol));
+/// Put symbols' instructions in their canonical order if scheduling differences are tolerated
+fn reschedule(symbols: Vec<Symbol>, tolerate_scheduling: bool) -> Vec<Symbol> {
+    if tolerate_scheduling {
+        symbols.iter().map(Symbol::rescheduled).collect()
+    } else {
+        symbols
     }
 }
 
-fn do_submatch(query: &str, symbols: &[Symbol], window_size: usize) {
-    let Some(query_sym) = symbols.iter().find(|s| s.name == query) else {
-        println!("Symbol {query:} not found");
+/// Format sorted indices as a list of ranges, e.g. `3, 17-19`
+fn format_ranges(indices: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &i in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == i => *end = i,
+            _ => ranges.push((i, i)),
+        }
+    }
+
+    ranges
+        .iter()
+        .map(|(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{start}-{end}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Split a symbol's bytes into its instructions, if they can be found. The symbol's instruction
+/// set isn't stored, so it's the first of the platform's that splits it into as many instructions
+/// as it has opcodes.
+fn get_insn_bytes<'a>(sym: &'a Symbol, platform: Platform) -> Option<Vec<&'a [u8]>> {
+    let offsets = platform
+        .archs()
+        .iter()
+        .map(|arch| get_insn_offsets_raw(&sym.bytes, platform, *arch))
+        .find(|offsets| offsets.len() == sym.opcodes.len())?;
+
+    Some(
+        offsets
+            .iter()
+            .enumerate()
+            .map(|(i, start)| {
+                let end = offsets.get(i + 1).copied().unwrap_or(sym.bytes.len());
+                &sym.bytes[*start..end]
+            })
+            .collect(),
+    )
+}
+
+fn do_diff(query1: &str, query2: &str, symbols: &[Symbol], platform: Platform) {
+    let Some(sym1) = symbols.iter().find(|s| s.name == query1) else {
+        println!("Symbol {query1:} not found");
+        return;
+    };
+    let Some(sym2) = symbols.iter().find(|s| s.name == query2) else {
+        println!("Symbol {query2:} not found");
         return;
     };
 
-    let query_hashes = query_sym.get_opcode_hashes(window_size);
+    let alignment = align_symbols(sym1, sym2);
+
+    println!(
+        "{} vs {}: {:.2}% ({} insns differ, {} with different operands)",
+        cli_fullname(sym1),
+        cli_fullname(sym2),
+        alignment.similarity() * 100.0,
+        alignment.edits,
+        alignment.operand_mismatches()
+    );
+
+    println!(
+        "{:.2}% structurally similar ({} vs {} blocks)",
+        core::cfg::structural_similarity(sym1, sym2) * 100.0,
+        sym1.cfg.blocks.len(),
+        sym2.cfg.blocks.len()
+    );
+    for m in core::cfg::match_blocks(sym1, sym2) {
+        let block1 = &sym1.cfg.blocks[m.block1];
+        let block2 = &sym2.cfg.blocks[m.block2];
+        println!(
+            "\tblock {} [{}-{}] ~ block {} [{}-{}] ({:.2}%)",
+            m.block1,
+            block1.start,
+            block1.end,
+            m.block2,
+            block2.start,
+            block2.end,
+            m.similarity * 100.0
+        );
+    }
 
-    for s in symbols {
-        if s == query_sym {
-            continue;
+    let insns1 = get_insn_bytes(sym1, platform);
+    let insns2 = get_insn_bytes(sym2, platform);
+    let hex_width = insns1
+        .iter()
+        .chain(insns2.iter())
+        .flatten()
+        .map(|b| b.len() * 2)
+        .max()
+        .unwrap_or(0);
+
+    let column = |insns: &Option<Vec<&[u8]>>, idx: Option<usize>| match idx {
+        Some(idx) => {
+            let hex = insns
+                .as_ref()
+                .map(|insns| insns[idx].iter().map(|b| format!("{b:02x}")).collect())
+                .unwrap_or_default();
+            format!("{idx:>5} {hex:<hex_width$}")
         }
+        None => " ".repeat(6 + hex_width),
+    };
 
-        if query_sym.opcodes == s.opcodes {
+    for row in &alignment.rows {
+        let marker = match row.kind {
+            AlignmentKind::Match if row.operands_differ => "~",
+            AlignmentKind::Match => " ",
+            AlignmentKind::Substitution => "|",
+            AlignmentKind::Insertion => ">",
+            AlignmentKind::Deletion => "<",
+        };
+        let line = format!(
+            "{} {} {}",
+            column(&insns1, row.left),
+            marker,
+            column(&insns2, row.right)
+        );
+
+        if !row.differs() {
+            println!("{line}");
+        } else if row.kind == AlignmentKind::Match {
+            println!("{}", line.yellow());
+        } else {
+            println!("{}", line.red());
+        }
+    }
+}
+
+fn do_submatch(query: &str, binaries: &[Binary], window_size: usize, scoring: Option<GapScoring>) {
+    let Some(query_sym) = binaries[0].symbols.iter().find(|s| s.name == query) else {
+        println!("Symbol {query:} not found");
+        return;
+    };
+
+    let show_binary = binaries.len() > 1;
+    let display_name = |binary: &Binary, sym: &Symbol| {
+        if show_binary {
+            format!(
+                "{} {}",
+                binary.name.color(BINARY_COLORS[1]),
+                cli_fullname(sym)
+            )
+        } else {
+            cli_fullname(sym)
+        }
+    };
+
+    for binary in binaries {
+        for s in binary
+            .symbols
+            .iter()
+            .filter(|s| !std::ptr::eq(*s, query_sym) && s.opcodes == query_sym.opcodes)
+        {
             let match_pct = if query_sym.exact_hash == s.exact_hash {
                 "100%"
             } else {
                 "99%"
             };
-            println!("{} matches {}", cli_fullname(s), match_pct);
-            continue;
+            println!("{} matches {}", display_name(binary, s), match_pct);
         }
+    }
 
-        let hashes = s.get_opcode_hashes(window_size);
+    let mut index = SubmatchIndex::new(window_size);
+    for binary in binaries {
+        index.add_binary(binary);
+    }
 
-        let pair_matches = get_submatches(&query_hashes, &hashes, window_size);
+    let is_other = |s: &Symbol| !std::ptr::eq(s, query_sym) && s.opcodes != query_sym.opcodes;
 
-        if pair_matches.is_empty() {
+    if let Some(scoring) = scoring {
+        for submatches in index.query_gapped(query_sym, &scoring) {
+            let s = submatches.symbol;
+            if !is_other(s) {
+                continue;
+            }
+
+            println!("{}:", display_name(submatches.binary, s));
+
+            for m in submatches.matches {
+                let query_str = format!("query [{}-{}]", m.offset1, m.offset1 + m.length1);
+                let target_str = format!(
+                    "{} [insn {}-{}] ({:.2}% identical, {} insns differ)",
+                    s.name,
+                    m.offset2,
+                    m.offset2 + m.length2,
+                    m.identity() * 100.0,
+                    m.edits
+                );
+                println!("\t{query_str} matches {target_str}");
+            }
+        }
+        return;
+    }
+
+    for submatches in index.query(query_sym) {
+        let s = submatches.symbol;
+        if !is_other(s) {
             continue;
         }
 
-        println!("{}:", cli_fullname(s));
+        println!("{}:", display_name(submatches.binary, s));
 
-        for m in pair_matches {
+        for m in submatches.matches {
             let query_str = format!("query [{}-{}]", m.offset1, m.offset1 + m.length);
             let target_str = format!(
                 "{} [insn {}-{}] ({} total)",
@@ -235,14 +806,55 @@ fn do_submatch(query: &str, symbols: &[Symbol], window_size: usize) {
     }
 }
 
-pub fn do_cluster(symbols: &[Symbol], threshold: f32, min_len: usize) {
-    let clusters = get_clusters(symbols, threshold, min_len);
+pub fn do_cluster(
+    symbols: &[Symbol],
+    threshold: f32,
+    min_len: usize,
+    score_by: ScoreBy,
+    max_evalue: Option<f64>,
+    json: bool,
+) {
+    #[derive(Serialize)]
+    struct ClusterMember<'a> {
+        #[serde(flatten)]
+        symbol: JsonSymbol<'a>,
+        similarity: SimilarityReport,
+    }
+
+    #[derive(Serialize)]
+    struct JsonCluster<'a> {
+        #[serde(flatten)]
+        head: JsonSymbol<'a>,
+        members: Vec<ClusterMember<'a>>,
+    }
+
+    let clusters = get_clusters(symbols, score_by.metric(), threshold, min_len, max_evalue);
+
+    if json {
+        let clusters: Vec<JsonCluster> = clusters
+            .iter()
+            .filter(|c| c.size() > 1)
+            .map(|c| JsonCluster {
+                head: JsonSymbol::from(c.head),
+                members: c
+                    .members
+                    .iter()
+                    .map(|(sym, report)| ClusterMember {
+                        symbol: JsonSymbol::from(*sym),
+                        similarity: *report,
+                    })
+                    .collect(),
+            })
+            .collect();
+        print_json(&clusters);
+        return;
+    }
 
     // Print clusters
     for cluster in clusters.iter().filter(|c| c.size() > 1) {
         println!(
             "Cluster {} has {} symbols",
-            cluster.syms[0].name,
+            cluster.head.name,
             cluster.size()
         );
     }
@@ -285,92 +897,206 @@ fn collect_symbols(config: &Version, base_dir: &Path, platform: &str) -> Result<
                 e
             )
         })?;
-        return read_elf(platform, &unmatched_funcs, &elf_data);
+        return read_object(platform, &unmatched_funcs, &elf_data);
     }
 
-    if let (Some(target), Some(map_path)) = (
-        get_full_path(base_dir, Some(config.paths.target.clone())),
-        get_full_path(base_dir, Some(config.paths.map.clone())),
-    ) {
+    // PlayStation executables can be read without a map
+    if let Some(target) = get_full_path(base_dir, Some(config.paths.target.clone()))
+        && target.exists()
+    {
         let target_bytes = fs::read(target)?;
-        let map_str = fs::read_to_string(map_path)?;
-        return read_map(platform, unmatched_funcs, target_bytes, &map_str);
+        let map_str = get_full_path(base_dir, Some(config.paths.map.clone()))
+            .filter(|p| p.exists())
+            .map(fs::read_to_string)
+            .transpose()?;
+        return read_target(platform, unmatched_funcs, target_bytes, map_str.as_deref());
     }
 
-    Err(anyhow!("No elf or mapfile found"))
+    Err(anyhow!("No elf or target found"))
 }
 
 fn compare_match_sort(
     sort_by: CompareSort,
-    a: &(&Symbol, &Symbol, f32),
-    b: &(&Symbol, &Symbol, f32),
+    metric: SimilarityMetric,
+    a: &(&Symbol, &Symbol, SimilarityReport),
+    b: &(&Symbol, &Symbol, SimilarityReport),
 ) -> Ordering {
     match sort_by {
         CompareSort::Name => a.0.name.cmp(&b.0.name).then(a.1.name.cmp(&b.1.name)),
         CompareSort::VramAddr => a.0.vram.cmp(&b.0.vram).then(a.1.vram.cmp(&b.1.vram)),
-        CompareSort::Similarity => {
-            b.2.partial_cmp(&a.2)
-                .unwrap_or(Ordering::Equal)
-                .then(a.0.name.cmp(&b.0.name))
-                .then(a.1.name.cmp(&b.1.name))
-        }
+        CompareSort::Similarity => compare_reports(&b.2, &a.2, metric)
+            .then(a.0.name.cmp(&b.0.name))
+            .then(a.1.name.cmp(&b.1.name)),
+        CompareSort::Significance => compare_significance(&a.2, &b.2)
+            .then(a.0.name.cmp(&b.0.name))
+            .then(a.1.name.cmp(&b.1.name)),
     }
 }
 
+fn print_binary_match(
+    sym1: &Symbol,
+    sym2: &Symbol,
+    report: &SimilarityReport,
+    metric: SimilarityMetric,
+) {
+    let score = report.score(metric) * 100.0;
+    match report.significance {
+        Some(significance) => println!(
+            "{} - {} ({score:.2}%, E-value {:.2e})",
+            cli_name_colored(sym1, BINARY_COLORS[0]),
+            cli_name_colored(sym2, BINARY_COLORS[1]),
+            significance.evalue
+        ),
+        None => println!(
+            "{} - {} ({score:.2}%)",
+            cli_name_colored(sym1, BINARY_COLORS[0]),
+            cli_name_colored(sym2, BINARY_COLORS[1]),
+        ),
+    }
+
+    if let Some(containment) = report.containment {
+        let (inner, outer) = if containment.first_in_second {
+            (
+                sym1.name.color(BINARY_COLORS[0]),
+                sym2.name.color(BINARY_COLORS[1]),
+            )
+        } else {
+            (
+                sym2.name.color(BINARY_COLORS[1]),
+                sym1.name.color(BINARY_COLORS[0]),
+            )
+        };
+        println!(
+            "\t{inner} is contained in {outer} at insns {}-{}",
+            containment.start, containment.end
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn do_compare_binaries(
     bin1: &Binary,
     bin2: &Binary,
     threshold: f32,
     min_len: usize,
     sort_by: CompareSort,
+    score_by: ScoreBy,
+    min_structural: Option<f32>,
+    propagate: Option<f32>,
+    max_evalue: Option<f64>,
+    json: bool,
 ) {
-    let mut matched_syms: Vec<(&Symbol, &Symbol, f32)> = Vec::new();
+    #[derive(Serialize)]
+    struct BinaryMatch<'a> {
+        symbol1: JsonSymbol<'a>,
+        symbol2: JsonSymbol<'a>,
+        similarity: SimilarityReport,
+        propagated: bool,
+    }
+
+    let metric = score_by.metric();
+    let background = OpcodeBackground::new(bin1.symbols.iter().chain(&bin2.symbols));
+    let search_space = bin2.symbols.len();
+    let mut matched_syms: Vec<(&Symbol, &Symbol, SimilarityReport)> = Vec::new();
+    let mut anchors: Vec<(usize, usize)> = Vec::new();
 
     bin1.symbols
         .iter()
-        .filter(|s| s.opcodes.len() >= min_len)
-        .for_each(|sym| {
-            let mut best_match: Option<(&Symbol, f32)> = None;
-
-            for sym2 in bin2.symbols.iter().filter(|s| s.opcodes.len() >= min_len) {
-                let score = core::diff_symbols(sym, sym2, threshold);
-                if score > threshold {
-                    if let Some((_, best_score)) = best_match {
-                        if score > best_score {
-                            best_match = Some((sym2, score));
+        .enumerate()
+        .filter(|(_, s)| s.opcodes.len() >= min_len)
+        .for_each(|(i, sym)| {
+            let mut best_match: Option<(usize, &Symbol, SimilarityReport)> = None;
+
+            for (j, sym2) in bin2
+                .symbols
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| s.opcodes.len() >= min_len)
+            {
+                if let Some(mut report) =
+                    diff_prefiltered(sym, sym2, metric, threshold, min_structural)
+                {
+                    report.significance = Some(background.significance(sym, sym2, search_space));
+                    if !is_significant(&report, max_evalue) {
+                        continue;
+                    }
+
+                    if let Some((_, _, best_report)) = best_match {
+                        if compare_reports(&report, &best_report, metric) == Ordering::Greater {
+                            best_match = Some((j, sym2, report));
                         }
                     } else {
-                        best_match = Some((sym2, score));
+                        best_match = Some((j, sym2, report));
                     }
                 }
             }
 
-            if let Some((best_sym, score)) = best_match {
-                matched_syms.push((sym, best_sym, score));
+            if let Some((j, best_sym, report)) = best_match {
+                matched_syms.push((sym, best_sym, report));
+                anchors.push((i, j));
             }
         });
 
-    matched_syms.sort_by(|a, b| compare_match_sort(sort_by, a, b));
+    matched_syms.sort_by(|a, b| compare_match_sort(sort_by, metric, a, b));
+
+    let mut propagated_syms: Vec<(&Symbol, &Symbol, SimilarityReport)> = match propagate {
+        Some(propagate_threshold) => propagate_matches(
+            bin1,
+            &bin1.call_graph(),
+            bin2,
+            &bin2.call_graph(),
+            &anchors,
+            metric,
+            propagate_threshold,
+        )
+        .into_iter()
+        .map(|m| {
+            let (sym1, sym2) = (&bin1.symbols[m.symbol1], &bin2.symbols[m.symbol2]);
+            let mut report = m.report;
+            report.significance = Some(background.significance(sym1, sym2, search_space));
+            (sym1, sym2, report)
+        })
+        .filter(|(_, _, report)| is_significant(report, max_evalue))
+        .collect(),
+        None => vec![],
+    };
+    propagated_syms.sort_by(|a, b| compare_match_sort(sort_by, metric, a, b));
+
+    if json {
+        let matches: Vec<BinaryMatch> = matched_syms
+            .iter()
+            .map(|m| (m, false))
+            .chain(propagated_syms.iter().map(|m| (m, true)))
+            .map(|((sym1, sym2, report), propagated)| BinaryMatch {
+                symbol1: JsonSymbol::from(*sym1),
+                symbol2: JsonSymbol::from(*sym2),
+                similarity: *report,
+                propagated,
+            })
+            .collect();
+        print_json(&matches);
+        return;
+    }
 
     match matched_syms.len() {
         0 => {
             println!("No matches found");
         }
         _ => {
-            let mut both_decompiled: Vec<(&Symbol, &Symbol, f32)> = vec![];
-            let mut only1_decompiled: Vec<(&Symbol, &Symbol, f32)> = vec![];
-            let mut only2_decompiled: Vec<(&Symbol, &Symbol, f32)> = vec![];
-            let mut both_undecompiled: Vec<(&Symbol, &Symbol, f32)> = vec![];
+            let mut both_decompiled: Vec<(&Symbol, &Symbol, SimilarityReport)> = vec![];
+            let mut only1_decompiled: Vec<(&Symbol, &Symbol, SimilarityReport)> = vec![];
+            let mut only2_decompiled: Vec<(&Symbol, &Symbol, SimilarityReport)> = vec![];
+            let mut both_undecompiled: Vec<(&Symbol, &Symbol, SimilarityReport)> = vec![];
 
-            for (sym1, sym2, score) in matched_syms {
+            for (sym1, sym2, report) in matched_syms {
                 if sym1.is_decompiled && sym2.is_decompiled {
-                    both_decompiled.push((sym1, sym2, score));
+                    both_decompiled.push((sym1, sym2, report));
                 } else if sym1.is_decompiled {
-                    only1_decompiled.push((sym1, sym2, score));
+                    only1_decompiled.push((sym1, sym2, report));
                 } else if sym2.is_decompiled {
-                    only2_decompiled.push((sym1, sym2, score));
+                    only2_decompiled.push((sym1, sym2, report));
                 } else {
-                    both_undecompiled.push((sym1, sym2, score));
+                    both_undecompiled.push((sym1, sym2, report));
                 }
             }
 
@@ -380,13 +1106,8 @@ fn do_compare_binaries(
                     bin1.name.color(BINARY_COLORS[0]),
                     bin2.name.color(BINARY_COLORS[1])
                 );
-                for (sym1, sym2, score) in both_decompiled {
-                    println!(
-                        "{} - {} ({:.2}%)",
-                        cli_name_colored(sym1, BINARY_COLORS[0]),
-                        cli_name_colored(sym2, BINARY_COLORS[1]),
-                        score * 100.0
-                    );
+                for (sym1, sym2, report) in both_decompiled {
+                    print_binary_match(sym1, sym2, &report, metric);
                 }
             }
 
@@ -395,13 +1116,8 @@ fn do_compare_binaries(
                     "\nOnly decompiled in {}:",
                     bin1.name.color(BINARY_COLORS[0])
                 );
-                for (sym1, sym2, score) in only1_decompiled {
-                    println!(
-                        "{} - {} ({:.2}%)",
-                        cli_name_colored(sym1, BINARY_COLORS[0]),
-                        cli_name_colored(sym2, BINARY_COLORS[1]),
-                        score * 100.0
-                    );
+                for (sym1, sym2, report) in only1_decompiled {
+                    print_binary_match(sym1, sym2, &report, metric);
                 }
             }
 
@@ -410,32 +1126,29 @@ fn do_compare_binaries(
                     "\nOnly decompiled in {}:",
                     bin2.name.color(BINARY_COLORS[1])
                 );
-                for (sym1, sym2, score) in only2_decompiled {
-                    println!(
-                        "{} - {} ({:.2}%)",
-                        cli_name_colored(sym1, BINARY_COLORS[0]),
-                        cli_name_colored(sym2, BINARY_COLORS[1]),
-                        score * 100.0
-                    );
+                for (sym1, sym2, report) in only2_decompiled {
+                    print_binary_match(sym1, sym2, &report, metric);
                 }
             }
 
             if !both_undecompiled.is_empty() {
                 println!("\nDecompiled in neither:");
-                for (sym1, sym2, score) in both_undecompiled {
-                    println!(
-                        "{} - {} ({:.2}%)",
-                        cli_name_colored(sym1, BINARY_COLORS[0]),
-                        cli_name_colored(sym2, BINARY_COLORS[1]),
-                        score * 100.0
-                    );
+                for (sym1, sym2, report) in both_undecompiled {
+                    print_binary_match(sym1, sym2, &report, metric);
                 }
             }
         }
     }
+
+    if !propagated_syms.is_empty() {
+        println!("\nFound through the call graph:");
+        for (sym1, sym2, report) in &propagated_syms {
+            print_binary_match(sym1, sym2, report, metric);
+        }
+    }
 }
 
-fn get_cwd_symbols() -> Result<Vec<Symbol>> {
+fn get_cwd_binary() -> Result<Binary> {
     let config = scan_for_config()?;
 
     let version = if config.versions.len() > 1 {
@@ -445,7 +1158,14 @@ fn get_cwd_symbols() -> Result<Vec<Symbol>> {
         config.versions.first().unwrap().clone()
     };
 
-    collect_symbols(&version, &std::env::current_dir()?, &config.platform)
+    Ok(Binary {
+        name: config.name,
+        symbols: collect_symbols(&version, &std::env::current_dir()?, &config.platform)?,
+    })
+}
+
+fn get_cwd_symbols() -> Result<Vec<Symbol>> {
+    Ok(get_cwd_binary()?.symbols)
 }
 
 #[tokio::main]
@@ -453,17 +1173,83 @@ async fn main() -> Result<()> {
     let cli: Cli = Cli::parse();
 
     match &cli.command {
-        Commands::Match { query, threshold } => {
+        Commands::Match {
+            query,
+            threshold,
+            score_by,
+            min_structural,
+            sort_by,
+            max_evalue,
+            tolerate_scheduling,
+            json,
+        } => {
             let symbols = get_cwd_symbols()?;
-            do_match(query, &symbols, *threshold);
+            do_match(
+                query,
+                &symbols,
+                *threshold,
+                *score_by,
+                *min_structural,
+                *sort_by,
+                *max_evalue,
+                *tolerate_scheduling,
+                *json,
+            );
         }
-        Commands::Submatch { query, window_size } => {
-            let symbols = get_cwd_symbols()?;
-            do_submatch(query, &symbols, *window_size);
+        Commands::Submatch {
+            query,
+            window_size,
+            others,
+            gapped,
+            max_edits,
+        } => {
+            let mut binaries = vec![get_cwd_binary()?];
+
+            for other_yaml in others {
+                let other_config = read_config(other_yaml.clone())?;
+
+                for other_version in &other_config.versions {
+                    binaries.push(Binary {
+                        name: format!("{} {}", other_config.name, other_version.fullname),
+                        symbols: collect_symbols(
+                            other_version,
+                            other_yaml.parent().unwrap(),
+                            &other_config.platform,
+                        )?,
+                    });
+                }
+            }
+
+            let scoring = gapped.then(|| GapScoring {
+                max_edits: *max_edits,
+                ..Default::default()
+            });
+            do_submatch(query, &binaries, *window_size, scoring);
         }
-        Commands::Cluster { threshold, min_len } => {
+        Commands::Diff { query1, query2 } => {
+            let config = scan_for_config()?;
+            let platform = Platform::from_name(&config.platform)
+                .ok_or_else(|| anyhow!("Invalid platform: {}", config.platform))?;
             let symbols = get_cwd_symbols()?;
-            do_cluster(&symbols, *threshold, *min_len);
+            do_diff(query1, query2, &symbols, platform);
+        }
+        Commands::Cluster {
+            threshold,
+            min_len,
+            score_by,
+            max_evalue,
+            tolerate_scheduling,
+            json,
+        } => {
+            let symbols = reschedule(get_cwd_symbols()?, *tolerate_scheduling);
+            do_cluster(
+                &symbols,
+                *threshold,
+                *min_len,
+                *score_by,
+                *max_evalue,
+                *json,
+            );
         }
         Commands::Compare2 {
             yaml1,
@@ -473,6 +1259,12 @@ async fn main() -> Result<()> {
             threshold,
             min_len,
             sort_by,
+            score_by,
+            min_structural,
+            propagate,
+            max_evalue,
+            tolerate_scheduling,
+            json,
         } => {
             let config1 = read_config(yaml1.clone())?;
             let config2 = read_config(yaml2.clone())?;
@@ -485,21 +1277,34 @@ async fn main() -> Result<()> {
 
             let bin1 = Binary {
                 name: config1.name,
-                symbols: symbols1,
+                symbols: reschedule(symbols1, *tolerate_scheduling),
             };
 
             let bin2 = Binary {
                 name: config2.name,
-                symbols: symbols2,
+                symbols: reschedule(symbols2, *tolerate_scheduling),
             };
 
-            do_compare_binaries(&bin1, &bin2, *threshold, *min_len, *sort_by);
+            do_compare_binaries(
+                &bin1,
+                &bin2,
+                *threshold,
+                *min_len,
+                *sort_by,
+                *score_by,
+                *min_structural,
+                *propagate,
+                *max_evalue,
+                *json,
+            );
         }
         Commands::CompareN {
             main_yaml,
             main_version,
             other_yamls,
             sort_by,
+            score_by,
+            max_evalue,
         } => {
             let main_config = read_config(main_yaml.clone())?;
             let main_version = main_config.get_version_by_name(main_version).unwrap();
@@ -537,11 +1342,61 @@ async fn main() -> Result<()> {
                         other_version.fullname.color(BINARY_COLORS[1])
                     );
 
-                    do_compare_binaries(&main_bin, &other_bin, 0.99, 5, *sort_by);
+                    do_compare_binaries(
+                        &main_bin,
+                        &other_bin,
+                        0.99,
+                        5,
+                        *sort_by,
+                        *score_by,
+                        None,
+                        None,
+                        *max_evalue,
+                        false,
+                    );
                     println!();
                 }
             }
         }
+        Commands::CompareLib {
+            lib,
+            yaml,
+            version,
+            threshold,
+            min_len,
+            sort_by,
+            score_by,
+            max_evalue,
+            json,
+        } => {
+            let config = read_config(yaml.clone())?;
+            let version = config.get_version_by_name(version).unwrap();
+            let platform = Platform::from_name(&config.platform)
+                .ok_or_else(|| anyhow!("Invalid platform: {}", config.platform))?;
+
+            let lib_name = lib.file_name().unwrap().to_string_lossy().to_string();
+            let lib_bin = Binary {
+                symbols: read_archive(platform, &lib_name, &fs::read(lib)?)?,
+                name: lib_name,
+            };
+            let bin = Binary {
+                name: config.name.clone(),
+                symbols: collect_symbols(&version, yaml.parent().unwrap(), &config.platform)?,
+            };
+
+            do_compare_binaries(
+                &lib_bin,
+                &bin,
+                *threshold,
+                *min_len,
+                *sort_by,
+                *score_by,
+                None,
+                None,
+                *max_evalue,
+                *json,
+            );
+        }
         Commands::CompareRaw { query_bin, yamls } => {
             let query_bin_data = fs::read(query_bin)?;
             let mut symbol_hashes = HashMap::new();
@@ -580,18 +1435,35 @@ async fn main() -> Result<()> {
             let platform =
                 platform.ok_or_else(|| anyhow!("No platform found in provided configs"))?;
 
-            let opcodes = core::arch::get_opcodes_raw(&query_bin_data, platform);
-            for (i, hash) in core::get_hashes(&opcodes, window_size).iter().enumerate() {
-                if let Some((project_name, version_name, symbol)) = symbol_hashes.get(hash)
-                    && opcodes[i..i + symbol.opcodes.len()] == symbol.opcodes
-                {
-                    println!(
-                        "0x{:X} - {} {}: {}",
-                        i * platform.arch().standard_insn_length(),
-                        project_name.color(BINARY_COLORS[0]),
-                        version_name.color(BINARY_COLORS[0]),
-                        cli_fullname(symbol)
-                    );
+            // Executables are searched by the addresses their code is loaded at
+            let code: Vec<(usize, Vec<u8>)> = if executable::is_executable(&query_bin_data) {
+                Executable::parse(&query_bin_data)?
+                    .text_segments()
+                    .map(|s| (s.address as usize, s.data.clone()))
+                    .collect()
+            } else {
+                vec![(0, query_bin_data)]
+            };
+
+            // Code may be in any of the platform's instruction sets, e.g. ARM or Thumb
+            let code_archs = code.iter().flat_map(|(address, data)| {
+                platform.archs().iter().map(move |a| (address, data, *a))
+            });
+            for (address, data, arch) in code_archs {
+                let opcodes = core::arch::get_opcodes_raw(data, platform, arch);
+                let insn_offsets = core::arch::get_insn_offsets_raw(data, platform, arch);
+                for (i, hash) in core::get_hashes(&opcodes, window_size).iter().enumerate() {
+                    if let Some((project_name, version_name, symbol)) = symbol_hashes.get(hash)
+                        && opcodes.get(i..i + symbol.opcodes.len()) == Some(&symbol.opcodes[..])
+                    {
+                        println!(
+                            "0x{:X} - {} {}: {}",
+                            address + insn_offsets[i],
+                            project_name.color(BINARY_COLORS[0]),
+                            version_name.color(BINARY_COLORS[0]),
+                            cli_fullname(symbol)
+                        );
+                    }
                 }
             }
         }
@@ -0,0 +1,235 @@
+//! Instruction-level alignment of two symbols.
+//!
+//! Symbols are aligned by their opcodes with a Levenshtein edit script, where substituting,
+//! inserting or deleting an instruction each cost one edit. Instructions with the same opcode are
+//! additionally compared at the equivalent level, to point out where the operands differ.
+
+use serde::Serialize;
+
+use crate::Symbol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlignmentKind {
+    /// Both symbols have an instruction with the same opcode
+    Match,
+    /// Both symbols have an instruction, with different opcodes
+    Substitution,
+    /// Only the second symbol has an instruction
+    Insertion,
+    /// Only the first symbol has an instruction
+    Deletion,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct AlignmentRow {
+    pub kind: AlignmentKind,
+    /// Index of the instruction in the first symbol, if it has one in this row
+    pub left: Option<usize>,
+    /// Index of the instruction in the second symbol, if it has one in this row
+    pub right: Option<usize>,
+    /// For matching opcodes, whether the instructions differ at the equivalent level
+    pub operands_differ: bool,
+}
+
+impl AlignmentRow {
+    /// Whether the row is anything other than a match at the equivalent level
+    pub fn differs(&self) -> bool {
+        self.kind != AlignmentKind::Match || self.operands_differ
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alignment {
+    pub rows: Vec<AlignmentRow>,
+    /// Number of substituted, inserted and deleted instructions
+    pub edits: usize,
+}
+
+impl Alignment {
+    /// Similarity of the opcodes, from 0 (nothing in common) to 1 (identical). Edits are counted
+    /// against the lengths of both symbols together.
+    pub fn similarity(&self) -> f32 {
+        let total: usize = self
+            .rows
+            .iter()
+            .map(|r| r.left.is_some() as usize + r.right.is_some() as usize)
+            .sum();
+
+        if total == 0 {
+            return 1.0;
+        }
+        1.0 - self.edits as f32 / total as f32
+    }
+
+    /// Number of instructions with matching opcodes but different operands
+    pub fn operand_mismatches(&self) -> usize {
+        self.rows.iter().filter(|r| r.operands_differ).count()
+    }
+}
+
+/// Align the instructions of two symbols
+pub fn align_symbols(sym1: &Symbol, sym2: &Symbol) -> Alignment {
+    let max_edits = sym1.opcodes.len().max(sym2.opcodes.len());
+    align_symbols_bounded(sym1, sym2, max_edits).unwrap()
+}
+
+/// Align the instructions of two symbols, giving up if it takes more than `max_edits` edits.
+/// Only the band of the edit matrix within `max_edits` of the diagonal is filled in, so this is
+/// much cheaper than a full alignment when few edits are allowed.
+pub fn align_symbols_bounded(sym1: &Symbol, sym2: &Symbol, max_edits: usize) -> Option<Alignment> {
+    let a = &sym1.opcodes;
+    let b = &sym2.opcodes;
+    let (n, m) = (a.len(), b.len());
+
+    if n.abs_diff(m) > max_edits {
+        return None;
+    }
+
+    // dist[i][j] is the edit distance between a[..i] and b[..j], for |i - j| <= k
+    let k = max_edits.min(n.max(m));
+    let width = 2 * k + 1;
+    let idx = |i: usize, j: usize| i * width + (j + k - i);
+    let in_band = |i: usize, j: usize| i.abs_diff(j) <= k;
+
+    const UNREACHED: usize = usize::MAX / 2;
+    let mut dist = vec![UNREACHED; (n + 1) * width];
+
+    for i in 0..=n {
+        let j_start = i.saturating_sub(k);
+        let j_end = (i + k).min(m);
+        let mut row_min = UNREACHED;
+
+        for j in j_start..=j_end {
+            let d = if i == 0 {
+                j
+            } else if j == 0 {
+                i
+            } else {
+                let sub = dist[idx(i - 1, j - 1)] + usize::from(a[i - 1] != b[j - 1]);
+                let del = if in_band(i - 1, j) {
+                    dist[idx(i - 1, j)] + 1
+                } else {
+                    UNREACHED
+                };
+                let ins = if in_band(i, j - 1) {
+                    dist[idx(i, j - 1)] + 1
+                } else {
+                    UNREACHED
+                };
+                sub.min(del).min(ins)
+            };
+            dist[idx(i, j)] = d;
+            row_min = row_min.min(d);
+        }
+
+        // Distances never decrease from one row to the next, so stop once all are too large
+        if row_min > max_edits {
+            return None;
+        }
+    }
+
+    let edits = dist[idx(n, m)];
+    if edits > max_edits {
+        return None;
+    }
+
+    // Walk back from the end, preferring to pair instructions up over inserting or deleting
+    let mut rows = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        let d = dist[idx(i, j)];
+
+        if i > 0 && j > 0 {
+            let same_opcode = a[i - 1] == b[j - 1];
+            if dist[idx(i - 1, j - 1)] + usize::from(!same_opcode) == d {
+                rows.push(AlignmentRow {
+                    kind: if same_opcode {
+                        AlignmentKind::Match
+                    } else {
+                        AlignmentKind::Substitution
+                    },
+                    left: Some(i - 1),
+                    right: Some(j - 1),
+                    operands_differ: same_opcode
+                        && sym1.insn_equiv_hashes.get(i - 1) != sym2.insn_equiv_hashes.get(j - 1),
+                });
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+
+        if i > 0 && in_band(i - 1, j) && dist[idx(i - 1, j)] + 1 == d {
+            rows.push(AlignmentRow {
+                kind: AlignmentKind::Deletion,
+                left: Some(i - 1),
+                right: None,
+                operands_differ: false,
+            });
+            i -= 1;
+        } else {
+            rows.push(AlignmentRow {
+                kind: AlignmentKind::Insertion,
+                left: None,
+                right: Some(j - 1),
+                operands_differ: false,
+            });
+            j -= 1;
+        }
+    }
+    rows.reverse();
+
+    Some(Alignment { rows, edits })
+}
+
+/// Find where `needle` aligns best within `haystack`, both sequences of opcodes. Instructions of
+/// `haystack` before and after the aligned region are free to skip, so this is the edit distance
+/// between `needle` and the closest region of `haystack`. Returns the region's start and end in
+/// `haystack`, and the number of edits, or `None` if every region takes more than `max_edits`.
+pub fn align_within(
+    needle: &[u16],
+    haystack: &[u16],
+    max_edits: usize,
+) -> Option<(usize, usize, usize)> {
+    let m = haystack.len();
+
+    // dist[j] is the edit distance between the needle so far and the closest region ending at
+    // haystack[..j], and start[j] is where that region starts
+    let mut dist: Vec<usize> = vec![0; m + 1];
+    let mut start: Vec<usize> = (0..=m).collect();
+    let mut next_dist = vec![0; m + 1];
+    let mut next_start = vec![0; m + 1];
+
+    for (i, opcode) in needle.iter().enumerate() {
+        next_dist[0] = i + 1;
+        next_start[0] = 0;
+
+        for j in 1..=m {
+            let sub = dist[j - 1] + usize::from(*opcode != haystack[j - 1]);
+            let del = dist[j] + 1;
+            let ins = next_dist[j - 1] + 1;
+
+            (next_dist[j], next_start[j]) = if sub <= del && sub <= ins {
+                (sub, start[j - 1])
+            } else if del <= ins {
+                (del, start[j])
+            } else {
+                (ins, next_start[j - 1])
+            };
+        }
+
+        std::mem::swap(&mut dist, &mut next_dist);
+        std::mem::swap(&mut start, &mut next_start);
+
+        // Distances never decrease from one row to the next, so stop once all are too large
+        if dist.iter().all(|&d| d > max_edits) {
+            return None;
+        }
+    }
+
+    // The earliest region with the fewest edits
+    let (end, edits) = dist.iter().copied().enumerate().min_by_key(|&(_, d)| d)?;
+
+    (edits <= max_edits).then_some((start[end], end, edits))
+}
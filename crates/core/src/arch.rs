@@ -1,17 +1,31 @@
+use crate::callgraph::CallTarget;
+use crate::cfg::{CfgInsn, InsnFlow};
+use crate::hash::{StableHasher, stable_hash};
+use crate::pseudo_reloc::{PseudoReloc, PseudoRelocKind, find_pseudo_relocs, known_or_recovered};
+use crate::schedule::DefUse;
 use crate::{Arch, Platform};
-use objdiff_core::obj::{InstructionRef, Section};
+use iced_x86::{ConstantOffsets, DecoderOptions, FlowControl, Mnemonic, OpKind};
+use objdiff_core::obj::{InstructionRef, Object, RelocationFlags, Section, SymbolKind};
 use object::Endian;
 use rabbitizer::IsaExtension::{R3000GTE, R4000ALLEGREX, R5900EE};
 use rabbitizer::IsaVersion::MIPS_III;
 use rabbitizer::operands::ValuedOperand;
 use std::collections::HashMap;
-use std::hash::{DefaultHasher, Hash, Hasher};
-use unarm::parse_thumb;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use std::sync::LazyLock;
+use unarm::{parse_arm, parse_thumb};
+use yaxpeax_arch::{Decoder, U8Reader};
+use yaxpeax_arm::armv8::a64::InstDecoder;
 
 enum Insn {
-    Mips(rabbitizer::Instruction),
+    Mips(rabbitizer::Instruction, u32),
     Ppc(powerpc::Ins),
-    Thumb(unarm::Ins),
+    Thumb(unarm::Ins, u32),
+    Arm(unarm::Ins, u32),
+    Aarch64(u32),
+    SuperH(u16),
+    X86(iced_x86::Instruction, ConstantOffsets),
 }
 
 fn get_rabbitizer_instruction(word: u32, vram: u32, platform: Platform) -> rabbitizer::Instruction {
@@ -28,11 +42,856 @@ fn get_rabbitizer_instruction(word: u32, vram: u32, platform: Platform) -> rabbi
     )
 }
 
-// Given raw bytes, attempt to get opcodes for the bytes
-pub fn get_opcodes_raw(bytes: &[u8], platform: Platform) -> Vec<u16> {
-    let insn_length = platform.arch().standard_insn_length();
+/// Whether the MIPS instruction is a jump or branch, and so is followed by a delay slot
+pub(crate) fn mips_has_delay_slot(word: u32) -> bool {
+    let op = word >> 26;
+    let rs = (word >> 21) & 0x1F;
+    match op {
+        // jr, jalr
+        0x00 => matches!(word & 0x3F, 0x08 | 0x09),
+        // bltz, bgez, bltzal, bgezal and their likely variants, but not the trap immediates
+        0x01 => matches!((word >> 16) & 0x1F, 0x00..=0x03 | 0x10..=0x13),
+        // j, jal, beq, bne, blez, bgtz and their likely variants
+        0x02..=0x07 | 0x14..=0x17 => true,
+        // bc1f, bc1t
+        0x11 => rs == 0x08,
+        _ => false,
+    }
+}
+
+/// yaxpeax-arm's AArch64 opcodes by name. objdiff's AArch64 opcode numbering is internal to
+/// objdiff, so an opcode's id is its index here instead, which keeps opcodes from objects and raw
+/// binaries comparable. Ids end up in the database, so new names must only be appended. Opcodes
+/// that carry a field (e.g. the condition of `Bcc`) are listed by their bare name.
+const AARCH64_OPCODES: &[&str] = &[
+    "UDF",
+    "MOVN",
+    "MOVK",
+    "MOVZ",
+    "ADC",
+    "ADCS",
+    "SBC",
+    "SBCS",
+    "AND",
+    "ORR",
+    "ORN",
+    "EOR",
+    "EON",
+    "BIC",
+    "BICS",
+    "ANDS",
+    "ADDS",
+    "ADD",
+    "SUBS",
+    "SUB",
+    "BFM",
+    "UBFM",
+    "SBFM",
+    "ADR",
+    "ADRP",
+    "EXTR",
+    "LDAR",
+    "LDLAR",
+    "LDARB",
+    "LDLARB",
+    "LDAXRB",
+    "LDARH",
+    "LDLARH",
+    "LDAXP",
+    "LDAXR",
+    "LDAXRH",
+    "LDP",
+    "LDPSW",
+    "LDR",
+    "LDRB",
+    "LDRSB",
+    "LDRSW",
+    "LDRSH",
+    "LDRH",
+    "LDTR",
+    "LDTRB",
+    "LDTRH",
+    "LDTRSB",
+    "LDTRSH",
+    "LDTRSW",
+    "LDUR",
+    "LDURB",
+    "LDURSB",
+    "LDURSW",
+    "LDURSH",
+    "LDURH",
+    "LDXP",
+    "LDXR",
+    "LDXRB",
+    "LDXRH",
+    "STLR",
+    "STLLR",
+    "STLRB",
+    "STLLRB",
+    "STLRH",
+    "STLLRH",
+    "STLXP",
+    "STLXR",
+    "STLXRB",
+    "STLXRH",
+    "STP",
+    "STR",
+    "STTR",
+    "STTRB",
+    "STTRH",
+    "STRB",
+    "STRH",
+    "STRW",
+    "STUR",
+    "STURB",
+    "STURH",
+    "STXP",
+    "STXR",
+    "STXRB",
+    "STXRH",
+    "TBZ",
+    "TBNZ",
+    "CBZ",
+    "CBNZ",
+    "B",
+    "BR",
+    "Bcc",
+    "BL",
+    "BLR",
+    "SVC",
+    "HVC",
+    "SMC",
+    "BRK",
+    "HLT",
+    "DCPS1",
+    "DCPS2",
+    "DCPS3",
+    "RET",
+    "ERET",
+    "DRPS",
+    "MSR",
+    "MRS",
+    "SYS",
+    "SYSL",
+    "ISB",
+    "DSB",
+    "DMB",
+    "SB",
+    "SSSB",
+    "HINT",
+    "CLREX",
+    "CSEL",
+    "CSNEG",
+    "CSINC",
+    "CSINV",
+    "PACIA",
+    "PACIZA",
+    "CCMN",
+    "CCMP",
+    "RBIT",
+    "REV16",
+    "REV",
+    "REV32",
+    "CLZ",
+    "CLS",
+    "MADD",
+    "MSUB",
+    "SMADDL",
+    "SMSUBL",
+    "SMULH",
+    "UMADDL",
+    "UMSUBL",
+    "UMULH",
+    "UDIV",
+    "SDIV",
+    "LSLV",
+    "LSRV",
+    "ASRV",
+    "RORV",
+    "CRC32B",
+    "CRC32H",
+    "CRC32W",
+    "CRC32X",
+    "CRC32CB",
+    "CRC32CH",
+    "CRC32CW",
+    "CRC32CX",
+    "STNP",
+    "LDNP",
+    "ST1",
+    "ST2",
+    "ST3",
+    "ST4",
+    "LD1R",
+    "LD2R",
+    "LD3R",
+    "LD4R",
+    "LD1",
+    "LD2",
+    "LD3",
+    "LD4",
+    "FMADD",
+    "FMSUB",
+    "FNMADD",
+    "FNMSUB",
+    "SCVTF",
+    "UCVTF",
+    "FCVTZS",
+    "FCVTZU",
+    "FMOV",
+    "FABS",
+    "FNEG",
+    "FSQRT",
+    "FRINTN",
+    "FRINTP",
+    "FRINTM",
+    "FRINTZ",
+    "FRINTA",
+    "FRINTX",
+    "FRINTI",
+    "FRINT32Z",
+    "FRINT32X",
+    "FRINT64Z",
+    "FRINT64X",
+    "BFCVT",
+    "FCVT",
+    "FCMP",
+    "FCMPE",
+    "FMUL",
+    "FDIV",
+    "FADD",
+    "FSUB",
+    "FMAX",
+    "FMIN",
+    "FMAXNM",
+    "FMINNM",
+    "FNMUL",
+    "FCSEL",
+    "FCCMP",
+    "FCCMPE",
+    "FMULX",
+    "FMLSL",
+    "FMLAL",
+    "SQRDMLSH",
+    "UDOT",
+    "SQRDMLAH",
+    "UMULL",
+    "UMULL2",
+    "UMLSL",
+    "UMLSL2",
+    "MLS",
+    "UMLAL",
+    "UMLAL2",
+    "MLA",
+    "SDOT",
+    "SQDMULL2",
+    "SQDMULL",
+    "SQDMLSL2",
+    "SQDMLSL",
+    "SMULL2",
+    "SMULL",
+    "SMLSL2",
+    "SMLSL",
+    "SQDMLAL2",
+    "SQDMLAL",
+    "SMLAL2",
+    "SMLAL",
+    "SQRDMULH",
+    "SQDMULH",
+    "MUL",
+    "USHR",
+    "USRA",
+    "URSHR",
+    "URSRA",
+    "SRI",
+    "SLI",
+    "SQSHLU",
+    "UQSHL",
+    "SQSHRUN",
+    "SQRSHRUN",
+    "UQSHRN",
+    "UQRSHRN",
+    "USHLL",
+    "SSHR",
+    "SSRA",
+    "SRSHR",
+    "SRSRA",
+    "SHL",
+    "SQSHL",
+    "SHRN",
+    "RSHRN",
+    "SQSHRN",
+    "SQRSHRN",
+    "SSHLL",
+    "MOVI",
+    "MVNI",
+    "SHADD",
+    "SQADD",
+    "SRHADD",
+    "SHSUB",
+    "SQSUB",
+    "CMGT",
+    "CMGE",
+    "SSHL",
+    "SRSHL",
+    "SQRSHL",
+    "SMAX",
+    "SMIN",
+    "SABD",
+    "SABA",
+    "CMTST",
+    "SMAXP",
+    "SMINP",
+    "ADDP",
+    "UHADD",
+    "UQADD",
+    "URHADD",
+    "UHSUB",
+    "UQSUB",
+    "CMHI",
+    "CMHS",
+    "USHL",
+    "URSHL",
+    "UQRSHL",
+    "UMAX",
+    "UMIN",
+    "UABD",
+    "UABA",
+    "CMEQ",
+    "PMUL",
+    "UMAXP",
+    "UMINP",
+    "FMLA",
+    "FCMEQ",
+    "FRECPS",
+    "BSL",
+    "BIT",
+    "BIF",
+    "FMAXNMP",
+    "FADDP",
+    "FCMGE",
+    "FACGE",
+    "FMAXP",
+    "SADDL",
+    "SADDL2",
+    "SADDW",
+    "SADDW2",
+    "SSUBL",
+    "SSUBL2",
+    "SSUBW",
+    "SSUBW2",
+    "ADDHN",
+    "ADDHN2",
+    "SABAL",
+    "SABAL2",
+    "SUBHN",
+    "SUBHN2",
+    "SABDL",
+    "SABDL2",
+    "PMULL",
+    "PMULL2",
+    "UADDL",
+    "UADDL2",
+    "UADDW",
+    "UADDW2",
+    "USUBL",
+    "USUBL2",
+    "USUBW",
+    "USUBW2",
+    "RADDHN",
+    "RADDHN2",
+    "RSUBHN",
+    "RSUBHN2",
+    "UABAL",
+    "UABAL2",
+    "UABDL",
+    "UABDL2",
+    "REV64",
+    "SADDLP",
+    "SUQADD",
+    "CNT",
+    "SADALP",
+    "SQABS",
+    "CMLT",
+    "ABS",
+    "XTN",
+    "SQXTN",
+    "FCVTN",
+    "FCMGT",
+    "FCVTL",
+    "FCVTNS",
+    "FCVTPS",
+    "FCVTMS",
+    "FCVTAS",
+    "URECPE",
+    "FRECPE",
+    "UADDLP",
+    "USQADD",
+    "UADALP",
+    "SQNEG",
+    "CMLE",
+    "NEG",
+    "SQXTUN",
+    "SHLL",
+    "UQXTN",
+    "FCVTXN",
+    "FCVTNU",
+    "FCVTMU",
+    "FCVTAU",
+    "INS",
+    "EXT",
+    "DUP",
+    "UZP1",
+    "TRN1",
+    "ZIP1",
+    "UZP2",
+    "TRN2",
+    "ZIP2",
+    "SMOV",
+    "UMOV",
+    "SQSHRN2",
+    "SQRSHRN2",
+    "SQSHRUN2",
+    "SQRSHRUN2",
+    "UQSHRN2",
+    "UQRSHRN2",
+    "FMLS",
+    "FRECPX",
+    "FRSQRTE",
+    "FCVTPU",
+    "FCMLT",
+    "FCMLE",
+    "FMAXNMV",
+    "FMINNMV",
+    "FMAXV",
+    "FMINV",
+    "UADDLV",
+    "SADDLV",
+    "UMAXV",
+    "SMAXV",
+    "UMINV",
+    "SMINV",
+    "ADDV",
+    "FRSQRTS",
+    "FMINNMP",
+    "FMLAL2",
+    "FMLSL2",
+    "FABD",
+    "FACGT",
+    "FMINP",
+    "FJCVTZS",
+    "URSQRTE",
+    "PRFM",
+    "PRFUM",
+    "AESE",
+    "AESD",
+    "AESMC",
+    "AESIMC",
+    "SHA1H",
+    "SHA1SU1",
+    "SHA256SU0",
+    "SM3TT1A",
+    "SM3TT1B",
+    "SM3TT2A",
+    "SM3TT2B",
+    "SHA512H",
+    "SHA512H2",
+    "SHA512SU1",
+    "RAX1",
+    "SM3PARTW1",
+    "SM3PARTW2",
+    "SM4EKEY",
+    "BCAX",
+    "SM3SSI",
+    "SHA512SU0",
+    "SM4E",
+    "EOR3",
+    "XAR",
+    "LDRAA",
+    "LDRAB",
+    "LDAPR",
+    "LDAPRH",
+    "LDAPRB",
+    "SWP",
+    "SWPA",
+    "SWPAL",
+    "SWPL",
+    "SWPB",
+    "SWPAB",
+    "SWPALB",
+    "SWPLB",
+    "SWPH",
+    "SWPAH",
+    "SWPALH",
+    "SWPLH",
+    "CAS",
+    "CASA",
+    "CASAL",
+    "CASL",
+    "CASB",
+    "CASAB",
+    "CASALB",
+    "CASLB",
+    "CASH",
+    "CASAH",
+    "CASALH",
+    "CASLH",
+    "CASP",
+    "CASPA",
+    "CASPAL",
+    "CASPL",
+    "LDADD",
+    "LDADDA",
+    "LDADDAL",
+    "LDADDL",
+    "LDADDB",
+    "LDADDAB",
+    "LDADDALB",
+    "LDADDLB",
+    "LDADDH",
+    "LDADDAH",
+    "LDADDALH",
+    "LDADDLH",
+    "LDCLR",
+    "LDCLRA",
+    "LDCLRAL",
+    "LDCLRL",
+    "LDCLRB",
+    "LDCLRAB",
+    "LDCLRALB",
+    "LDCLRLB",
+    "LDCLRH",
+    "LDCLRAH",
+    "LDCLRALH",
+    "LDCLRLH",
+    "LDEOR",
+    "LDEORA",
+    "LDEORAL",
+    "LDEORL",
+    "LDEORB",
+    "LDEORAB",
+    "LDEORALB",
+    "LDEORLB",
+    "LDEORH",
+    "LDEORAH",
+    "LDEORALH",
+    "LDEORLH",
+    "LDSET",
+    "LDSETA",
+    "LDSETAL",
+    "LDSETL",
+    "LDSETB",
+    "LDSETAB",
+    "LDSETALB",
+    "LDSETLB",
+    "LDSETH",
+    "LDSETAH",
+    "LDSETALH",
+    "LDSETLH",
+    "LDSMAX",
+    "LDSMAXA",
+    "LDSMAXAL",
+    "LDSMAXL",
+    "LDSMIN",
+    "LDSMINA",
+    "LDSMINAL",
+    "LDSMINL",
+    "LDUMAX",
+    "LDUMAXA",
+    "LDUMAXAL",
+    "LDUMAXL",
+    "LDUMIN",
+    "LDUMINA",
+    "LDUMINAL",
+    "LDUMINL",
+    "TBL",
+    "TBX",
+    "FCADD",
+    "FCMLA",
+    "SHA1C",
+    "SHA1P",
+    "SHA1M",
+    "SHA1SU0",
+    "SHA256H",
+    "SHA256H2",
+    "SHA256SU1",
+    "BLRAA",
+    "BLRAAZ",
+    "BLRAB",
+    "BLRABZ",
+    "BRAA",
+    "BRAAZ",
+    "BRAB",
+    "BRABZ",
+    "ERETAA",
+    "ERETAB",
+    "RETAA",
+    "RETAB",
+    "PACIB",
+    "PACIZB",
+    "PACDA",
+    "PACDZA",
+    "PACDB",
+    "PACDZB",
+    "AUTIA",
+    "AUTIZA",
+    "AUTIB",
+    "AUTIZB",
+    "AUTDA",
+    "AUTDZA",
+    "AUTDB",
+    "AUTDZB",
+    "XPACI",
+    "XPACD",
+    "PACGA",
+    "GMI",
+    "IRG",
+    "SUBP",
+    "SUBPS",
+    "ADDG",
+    "SUBG",
+    "STG",
+    "STZG",
+    "ST2G",
+    "STZ2G",
+    "LDG",
+    "STGP",
+    "LDGM",
+    "STGM",
+    "STZGM",
+    "SETF8",
+    "SETF16",
+    "RMIF",
+    "CFINV",
+    "XAFLAG",
+    "AXFLAG",
+    "WFE",
+    "WFI",
+    "WFET",
+    "WFIT",
+    "YIELD",
+    "SEV",
+    "SEVL",
+    "NOP",
+    "ESB",
+    "PSB",
+    "TSB",
+    "CSDB",
+    "BTI",
+    "DGH",
+    "STLUR",
+    "STLURB",
+    "STLURH",
+    "LDAPUR",
+    "LDAPURB",
+    "LDAPURH",
+    "LDAPURSB",
+    "LDAPURSH",
+    "LDAPURSW",
+    "TCANCEL",
+    "TSTART",
+    "TCOMMIT",
+    "TTEST",
+];
+
+/// Opcodes missing from `AARCH64_OPCODES` all share this id
+const AARCH64_UNLISTED_OPCODE: u16 = u16::MAX - 2;
+
+static AARCH64_OPCODE_IDS: LazyLock<HashMap<&'static str, u16>> = LazyLock::new(|| {
+    AARCH64_OPCODES
+        .iter()
+        .enumerate()
+        .map(|(id, name)| (*name, id as u16))
+        .collect()
+});
+
+fn get_aarch64_opcode(word: u32) -> u16 {
+    let decoder = InstDecoder::default();
+    match decoder.decode(&mut U8Reader::new(&word.to_le_bytes())) {
+        Ok(ins) => {
+            let name = format!("{:?}", ins.opcode);
+            let name = name.split('(').next().unwrap_or_default();
+            AARCH64_OPCODE_IDS
+                .get(name)
+                .copied()
+                .unwrap_or(AARCH64_UNLISTED_OPCODE)
+        }
+        Err(_) => u16::MAX,
+    }
+}
+
+/// Decode a run of x86 code, along with where each instruction's constants live
+fn decode_x86(
+    bytes: &[u8],
+    address: u64,
+    platform: Platform,
+) -> Vec<(iced_x86::Instruction, ConstantOffsets)> {
+    let mut decoder =
+        iced_x86::Decoder::with_ip(platform.x86_bitness(), bytes, address, DecoderOptions::NONE);
+    let mut ret = Vec::new();
+
+    while decoder.can_decode() {
+        let ins = decoder.decode();
+        let offsets = decoder.get_constant_offsets(&ins);
+        ret.push((ins, offsets));
+    }
+    ret
+}
+
+/// Get the length of x86 code without the `int3`/`nop` padding after its last instruction.
+/// Whole instructions are dropped, so e.g. a trailing immediate that happens to be 0xCC is kept.
+pub(crate) fn trim_x86_padding(bytes: &[u8], platform: Platform) -> usize {
+    decode_x86(bytes, 0, platform)
+        .iter()
+        .rev()
+        .find(|(ins, _)| !matches!(ins.mnemonic(), Mnemonic::Int3 | Mnemonic::Nop))
+        .map_or(0, |(ins, _)| ins.next_ip() as usize)
+}
+
+// Matches the opcode numbering objdiff uses for x86, so opcodes from objects and raw binaries
+// are comparable
+fn get_x86_opcode(ins: &iced_x86::Instruction) -> u16 {
+    if ins.is_invalid() {
+        u16::MAX
+    } else {
+        ins.mnemonic() as u16
+    }
+}
+
+/// The instruction set in use across a function, for architectures that can switch between
+/// several (ARM and Thumb)
+#[derive(Debug, Clone)]
+pub(crate) struct InsnModes {
+    default: Arch,
+    /// (address, arch) pairs where the instruction set changes, sorted by address. `None` starts
+    /// data, like a literal pool.
+    changes: Vec<(u64, Option<Arch>)>,
+}
+
+impl InsnModes {
+    pub(crate) fn new(default: Arch, changes: Vec<(u64, Option<Arch>)>) -> Self {
+        Self { default, changes }
+    }
+
+    pub(crate) fn uniform(arch: Arch) -> Self {
+        Self::new(arch, vec![])
+    }
+
+    /// Get the instruction set in use at an address, or `None` if it holds data
+    pub(crate) fn arch_at(&self, address: u64) -> Option<Arch> {
+        self.changes
+            .iter()
+            .rev()
+            .find(|(change_address, _)| *change_address <= address)
+            .map_or(Some(self.default), |(_, arch)| *arch)
+    }
+
+    /// Get the instruction set a function starting at an address is in
+    pub(crate) fn function_arch(&self, address: u64) -> Arch {
+        self.arch_at(address).unwrap_or(self.default)
+    }
+}
+
+fn arm_options(platform: Platform) -> unarm::Options {
+    unarm::Options {
+        version: platform.arm_version(),
+        ..Default::default()
+    }
+}
+
+/// Before Thumb-2, BL and BLX are the only 32-bit Thumb instructions, encoded as two halves
+fn is_thumb_bl_pair(first: u16, second: u16) -> bool {
+    first & 0xF800 == 0xF000 && second & 0xE800 == 0xE800
+}
+
+/// Read a 2-byte Thumb instruction or a 4-byte BL/BLX pair
+fn read_thumb_code(insn_bytes: &[u8], platform: Platform) -> Result<u32, anyhow::Error> {
+    match insn_bytes.len() {
+        2 => Ok(platform
+            .endianness()
+            .read_u16_bytes(insn_bytes.try_into().unwrap()) as u32),
+        4 => Ok(platform
+            .endianness()
+            .read_u32_bytes(insn_bytes.try_into().unwrap())),
+        size => Err(anyhow::anyhow!(
+            "Unexpected instruction size {} for Thumb",
+            size
+        )),
+    }
+}
+
+/// Split raw bytes into (offset, length) pairs, one per instruction
+fn get_insn_spans_raw(bytes: &[u8], platform: Platform, arch: Arch) -> Vec<(usize, usize)> {
+    match arch {
+        Arch::X86 => decode_x86(bytes, 0, platform)
+            .iter()
+            .map(|(ins, _)| (ins.ip() as usize, ins.len()))
+            .collect(),
+        Arch::Thumb => {
+            let halves: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|c| platform.endianness().read_u16_bytes(c.try_into().unwrap()))
+                .collect();
+
+            let mut spans = Vec::new();
+            let mut i = 0;
+            while i < halves.len() {
+                if i + 1 < halves.len() && is_thumb_bl_pair(halves[i], halves[i + 1]) {
+                    spans.push((i * 2, 4));
+                    i += 2;
+                } else {
+                    spans.push((i * 2, 2));
+                    i += 1;
+                }
+            }
+            spans
+        }
+        arch => (0..bytes.len() / arch.standard_insn_length())
+            .map(|i| (i * arch.standard_insn_length(), arch.standard_insn_length()))
+            .collect(),
+    }
+}
+
+/// Get the offset of each instruction within the given raw bytes, which are in the given
+/// instruction set of the platform (see [`Platform::archs`])
+pub fn get_insn_offsets_raw(bytes: &[u8], platform: Platform, arch: Arch) -> Vec<usize> {
+    get_insn_spans_raw(bytes, platform, arch)
+        .iter()
+        .map(|(offset, _)| *offset)
+        .collect()
+}
+
+/// Get the opcode of each instruction objdiff found. AArch64 and SuperH opcodes are numbered the
+/// way [`get_opcodes_raw`] numbers them so objects and raw code agree, but they're still read
+/// per instruction so they line up with the symbol's other per-instruction data.
+pub(crate) fn get_opcodes(
+    bytes: &[u8],
+    platform: Platform,
+    insn_refs: &[InstructionRef],
+) -> Vec<u16> {
+    let start_address = insn_refs.first().map(|r| r.address as usize).unwrap_or(0);
 
     match platform.arch() {
+        arch @ (Arch::Aarch64 | Arch::SuperH) => insn_refs
+            .iter()
+            .map(|insn_ref| {
+                let offset = insn_ref.address as usize - start_address;
+                let insn_bytes = &bytes[offset..offset + insn_ref.size as usize];
+                get_opcodes_raw(insn_bytes, platform, arch)
+                    .first()
+                    .copied()
+                    .unwrap_or(u16::MAX)
+            })
+            .collect(),
+        _ => insn_refs.iter().map(|r| r.opcode).collect(),
+    }
+}
+
+// Given raw bytes in one of the platform's instruction sets, attempt to get opcodes for the bytes
+pub fn get_opcodes_raw(bytes: &[u8], platform: Platform, arch: Arch) -> Vec<u16> {
+    let insn_length = arch.standard_insn_length();
+
+    match arch {
         Arch::Mips => bytes
             .chunks_exact(insn_length)
             .map(|chunk| {
@@ -52,36 +911,69 @@ pub fn get_opcodes_raw(bytes: &[u8], platform: Platform) -> Vec<u16> {
                 ) as u16
             })
             .collect(),
-        Arch::Thumb => bytes
+        Arch::Thumb => get_insn_spans_raw(bytes, platform, arch)
+            .iter()
+            .map(|(offset, length)| {
+                let code = read_thumb_code(&bytes[*offset..*offset + *length], platform).unwrap();
+
+                let (ins, _) = parse_thumb(code, 0, &arm_options(platform));
+                ins.discriminant()
+            })
+            .collect(),
+        Arch::Arm => bytes
             .chunks_exact(insn_length)
             .map(|chunk| {
                 let code = platform
                     .endianness()
-                    .read_u16_bytes(chunk.try_into().unwrap());
+                    .read_u32_bytes(chunk.try_into().unwrap());
 
-                let (ins, _) = parse_thumb(code.into(), 0, &unarm::Options::default());
+                let (ins, _) = parse_arm(code, 0, &arm_options(platform));
                 ins.discriminant()
             })
             .collect(),
+        Arch::Aarch64 => bytes
+            .chunks_exact(insn_length)
+            .map(|chunk| {
+                get_aarch64_opcode(
+                    platform
+                        .endianness()
+                        .read_u32_bytes(chunk.try_into().unwrap()),
+                )
+            })
+            .collect(),
+        Arch::SuperH => bytes
+            .chunks_exact(insn_length)
+            .map(|chunk| {
+                get_superh_opcode(
+                    platform
+                        .endianness()
+                        .read_u16_bytes(chunk.try_into().unwrap()),
+                )
+            })
+            .collect(),
+        Arch::X86 => decode_x86(bytes, 0, platform)
+            .iter()
+            .map(|(ins, _)| get_x86_opcode(ins))
+            .collect(),
     }
 }
 
 fn decode_instruction(
     insn_bytes: &[u8],
     platform: Platform,
+    arch: Arch,
     insn_ref: &InstructionRef,
 ) -> Result<Insn, anyhow::Error> {
-    match platform.arch() {
+    match arch {
         Arch::Mips => {
             let code = platform
                 .endianness()
                 .read_u32_bytes(insn_bytes.try_into().unwrap());
 
-            Ok(Insn::Mips(get_rabbitizer_instruction(
+            Ok(Insn::Mips(
+                get_rabbitizer_instruction(code, insn_ref.address as u32, platform),
                 code,
-                insn_ref.address as u32,
-                platform,
-            )))
+            ))
         }
         Arch::Ppc => Ok(Insn::Ppc(powerpc::Ins::new(
             platform
@@ -89,108 +981,545 @@ fn decode_instruction(
                 .read_u32_bytes(insn_bytes.try_into().unwrap()),
             powerpc::Extensions::gekko_broadway(),
         ))),
-        Arch::Thumb => match insn_ref.size {
-            2 => {
-                let ins = platform
-                    .endianness()
-                    .read_u16_bytes(insn_bytes.try_into().unwrap())
-                    as u32;
-                let (i, _) = parse_thumb(
-                    ins,
-                    0,
-                    &unarm::Options {
-                        version: platform.arm_version(),
-                        ..Default::default()
-                    },
-                );
-                Ok(Insn::Thumb(i))
-            }
-            4 => {
-                let ins = platform
-                    .endianness()
-                    .read_u32_bytes(insn_bytes.try_into().unwrap());
-                let (i, _) = parse_thumb(
-                    ins,
-                    0,
-                    &unarm::Options {
-                        version: platform.arm_version(),
-                        ..Default::default()
-                    },
-                );
-                Ok(Insn::Thumb(i))
+        Arch::Thumb => {
+            let code = read_thumb_code(insn_bytes, platform)?;
+            let (ins, _) = parse_thumb(code, 0, &arm_options(platform));
+            Ok(Insn::Thumb(ins, code))
+        }
+        // A 2-byte instruction can only be Thumb, regardless of what the mapping symbols say
+        Arch::Arm if insn_bytes.len() == 2 => {
+            decode_instruction(insn_bytes, platform, Arch::Thumb, insn_ref)
+        }
+        Arch::Arm => {
+            let code = platform
+                .endianness()
+                .read_u32_bytes(insn_bytes.try_into().unwrap());
+            let (ins, _) = parse_arm(code, 0, &arm_options(platform));
+            Ok(Insn::Arm(ins, code))
+        }
+        Arch::Aarch64 => Ok(Insn::Aarch64(
+            platform
+                .endianness()
+                .read_u32_bytes(insn_bytes.try_into().unwrap()),
+        )),
+        Arch::SuperH => Ok(Insn::SuperH(
+            platform
+                .endianness()
+                .read_u16_bytes(insn_bytes.try_into().unwrap()),
+        )),
+        Arch::X86 => {
+            let mut decoder = iced_x86::Decoder::with_ip(
+                platform.x86_bitness(),
+                insn_bytes,
+                insn_ref.address,
+                DecoderOptions::NONE,
+            );
+            let ins = decoder.decode();
+            if ins.is_invalid() {
+                return Err(anyhow::anyhow!("Invalid x86 instruction"));
             }
-            _ => Err(anyhow::anyhow!(
-                "Unexpected instruction size {} for Thumb",
-                insn_ref.size
-            )),
-        },
+            let offsets = decoder.get_constant_offsets(&ins);
+            Ok(Insn::X86(ins, offsets))
+        }
+    }
+}
+
+/// For instructions that load from a PC-relative literal pool, get the address and size of the
+/// literal being loaded
+fn get_pc_relative_literal(insn: &Insn, address: u64) -> Option<(u64, u8)> {
+    match insn {
+        Insn::SuperH(insn) => get_superh_literal(*insn, address),
+        Insn::Thumb(_, code) => get_thumb_literal(*code, address),
+        Insn::Arm(_, code) => get_arm_literal(*code, address),
+        _ => None,
     }
 }
 
-pub(crate) fn get_equivalence_hash(
+/// Get the literal pool entries raw code loads, as (address, size) pairs
+pub(crate) fn get_literals_raw(
+    bytes: &[u8],
+    vram: u64,
+    platform: Platform,
+    arch: Arch,
+) -> Vec<(u64, u8)> {
+    get_insn_spans_raw(bytes, platform, arch)
+        .iter()
+        .filter_map(|(offset, length)| {
+            let address = vram + *offset as u64;
+            let insn_ref = InstructionRef {
+                address,
+                size: *length as u8,
+                opcode: 0,
+                branch_dest: None,
+            };
+            let insn =
+                decode_instruction(&bytes[*offset..offset + length], platform, arch, &insn_ref)
+                    .ok()?;
+            get_pc_relative_literal(&insn, address)
+        })
+        .collect()
+}
+
+fn read_literal(
     bytes: &[u8],
+    start_address: u64,
+    literal_address: u64,
+    literal_size: u8,
     platform: Platform,
+) -> Option<u32> {
+    let offset = literal_address.checked_sub(start_address)? as usize;
+    let literal_bytes = bytes.get(offset..offset + literal_size as usize)?;
+
+    match literal_size {
+        2 => Some(
+            platform
+                .endianness()
+                .read_u16_bytes(literal_bytes.try_into().unwrap()) as u32,
+        ),
+        4 => Some(
+            platform
+                .endianness()
+                .read_u32_bytes(literal_bytes.try_into().unwrap()),
+        ),
+        _ => None,
+    }
+}
+
+/// What a relocation's unique id is assigned by, so that instructions referring to the same
+/// thing hash the same
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RelocKey {
+    Object(usize, i64, RelocationFlags),
+    Pseudo(PseudoReloc),
+}
+
+fn get_reloc_id(reloc_ids: &mut HashMap<RelocKey, usize>, key: RelocKey) -> usize {
+    let next_id = reloc_ids.len();
+    *reloc_ids.entry(key).or_insert(next_id)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RegisterKind {
+    Gpr,
+    Fpr,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum RenamedRegister {
+    /// A register whose role is fixed by the ABI, kept as-is
+    Fixed(RegisterKind, u32),
+    /// Any other register, numbered by the order it's first used in within the function
+    Allocated(RegisterKind, usize),
+}
+
+/// Renames registers by the order they're first used in, so that functions that only differ
+/// in which registers the compiler picked hash the same
+struct RegisterRenamer {
+    fixed_gprs: &'static [u32],
+    renamed: HashMap<(RegisterKind, u32), usize>,
+}
+
+impl RegisterRenamer {
+    /// Whether registers are renamed for the architecture. Elsewhere there's no regalloc hash.
+    fn supports(arch: Arch) -> bool {
+        matches!(arch, Arch::Mips | Arch::Ppc)
+    }
+
+    fn new(arch: Arch) -> Self {
+        let fixed_gprs: &'static [u32] = match arch {
+            // $zero, $gp, $sp, $ra
+            Arch::Mips => &[0, 28, 29, 31],
+            // r0 reads as zero when used as a base, r1 is the stack pointer, r2 and r13 are the
+            // small data anchors
+            Arch::Ppc => &[0, 1, 2, 13],
+            _ => &[],
+        };
+        Self {
+            fixed_gprs,
+            renamed: HashMap::new(),
+        }
+    }
+
+    fn rename(&mut self, kind: RegisterKind, reg: u32) -> RenamedRegister {
+        if kind == RegisterKind::Gpr && self.fixed_gprs.contains(&reg) {
+            return RenamedRegister::Fixed(kind, reg);
+        }
+        let next_id = self.renamed.iter().filter(|((k, _), _)| *k == kind).count();
+        RenamedRegister::Allocated(kind, *self.renamed.entry((kind, reg)).or_insert(next_id))
+    }
+}
+
+/// Hash a register operand, renamed by first use if register allocation is being ignored
+fn hash_register<T: Hash>(
+    operand: T,
+    kind: RegisterKind,
+    reg: u32,
+    regs: Option<&mut RegisterRenamer>,
+    hasher: &mut StableHasher,
+) {
+    match regs {
+        Some(regs) => regs.rename(kind, reg).hash(hasher),
+        None => operand.hash(hasher),
+    }
+}
+
+/// Get the equivalence hash of each instruction. The symbol's equivalence hash is the hash of
+/// these.
+pub(crate) fn get_equivalence_hashes(
+    bytes: &[u8],
+    platform: Platform,
+    obj: &Object,
+    section: &Section,
+    insn_refs: &Vec<InstructionRef>,
+    modes: &InsnModes,
+) -> Vec<u64> {
+    hash_instructions(bytes, platform, obj, section, insn_refs, modes, false)
+}
+
+/// Like the equivalence hash, but general purpose and floating point registers are numbered by
+/// first use. Only MIPS and PPC registers are renamed, so there's none for other architectures.
+pub(crate) fn get_regalloc_hash(
+    bytes: &[u8],
+    platform: Platform,
+    obj: &Object,
     section: &Section,
     insn_refs: &Vec<InstructionRef>,
-) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    modes: &InsnModes,
+) -> Option<u64> {
+    RegisterRenamer::supports(platform.arch()).then(|| {
+        stable_hash(&hash_instructions(
+            bytes, platform, obj, section, insn_refs, modes, true,
+        ))
+    })
+}
+
+/// Where a linked object's sections are loaded. Sections at address 0 are left out, as those of
+/// relocatable objects all start there and small constants would be taken for addresses in them.
+fn loaded_ranges(obj: &Object) -> Vec<Range<u64>> {
+    obj.sections
+        .iter()
+        .filter(|s| s.address != 0 && s.size != 0)
+        .map(|s| s.address..s.address + s.size)
+        .collect()
+}
+
+/// Hash each instruction. Instructions that can't be hashed still get an entry, so that the
+/// hashes line up with the symbol's opcodes.
+fn hash_instructions(
+    bytes: &[u8],
+    platform: Platform,
+    obj: &Object,
+    section: &Section,
+    insn_refs: &Vec<InstructionRef>,
+    modes: &InsnModes,
+    rename_registers: bool,
+) -> Vec<u64> {
+    let mut insn_hashes = Vec::with_capacity(insn_refs.len());
 
     let mut reloc_ids = HashMap::new();
+    let mut renamer = rename_registers.then(|| RegisterRenamer::new(platform.arch()));
 
-    let mut hashed_reloc;
+    let mut reloc_offset;
 
     let start_address = insn_refs.first().map(|r| r.address as usize).unwrap_or(0);
 
+    // Linked objects don't have relocations anymore, so recover them from the code
+    let pseudo_relocs = if section.relocations.is_empty() {
+        let arch = modes.function_arch(start_address as u64);
+        find_pseudo_relocs(
+            bytes,
+            start_address as u64,
+            platform,
+            arch,
+            &loaded_ranges(obj),
+        )
+    } else {
+        HashMap::new()
+    };
+
     for insn_ref in insn_refs {
+        let mut hasher = StableHasher::new();
+
         // Replace with constant when new objdiff is out
         if insn_ref.opcode == u16::MAX || insn_ref.opcode == u16::MAX - 1 {
+            insn_hashes.push(hasher.finish());
             continue;
         }
 
+        // Data within the code, e.g. a literal pool, is hashed along with what loads it
+        let Some(arch) = modes.arch_at(insn_ref.address) else {
+            insn_hashes.push(hasher.finish());
+            continue;
+        };
+
         // Hash the unique id for the relocation entry rather than the specifics
         if let Some(reloc) = section.relocation_at(insn_ref.address, insn_ref.size) {
-            let next_id = reloc_ids.len();
-            let hash_id = *reloc_ids
-                .entry((reloc.target_symbol, reloc.addend, reloc.flags))
-                .or_insert(next_id);
-            hash_id.hash(&mut hasher);
-            hashed_reloc = true;
+            let key = RelocKey::Object(reloc.target_symbol, reloc.addend, reloc.flags);
+            get_reloc_id(&mut reloc_ids, key).hash(&mut hasher);
+            reloc_offset = Some(reloc.address - insn_ref.address);
+        } else if let Some(pseudo_reloc) = pseudo_relocs.get(&insn_ref.address) {
+            get_reloc_id(&mut reloc_ids, RelocKey::Pseudo(*pseudo_reloc)).hash(&mut hasher);
+            reloc_offset = Some(0);
         } else {
-            hashed_reloc = false;
+            reloc_offset = None;
         }
 
         let offset = insn_ref.address as usize - start_address;
         let insn_length = insn_ref.size as usize;
         let insn_bytes = &bytes[offset..offset + insn_length];
 
-        let instruction = match decode_instruction(insn_bytes, platform, insn_ref) {
+        let instruction = match decode_instruction(insn_bytes, platform, arch, insn_ref) {
             Ok(insn) => insn,
             Err(_) => {
                 eprintln!(
                     "Warning: Failed to read instruction at {:#X}",
                     insn_ref.address
                 );
+                insn_hashes.push(hasher.finish());
                 continue;
             }
         };
 
-        hash_args_for_insn(instruction, &mut hasher, hashed_reloc);
+        if let (Some(pseudo_reloc), Insn::X86(ins, offsets)) =
+            (pseudo_relocs.get(&insn_ref.address), &instruction)
+        {
+            reloc_offset = x86_pseudo_reloc_offset(ins, offsets, pseudo_reloc);
+        }
+
+        // The offset of a literal pool load depends on how the pool was laid out, so hash what
+        // gets loaded instead
+        if let Some((literal_address, literal_size)) =
+            get_pc_relative_literal(&instruction, insn_ref.address)
+        {
+            if let Some(reloc) = section.relocation_at(literal_address, literal_size) {
+                let key = RelocKey::Object(reloc.target_symbol, reloc.addend, reloc.flags);
+                get_reloc_id(&mut reloc_ids, key).hash(&mut hasher);
+            } else if let Some(pseudo_reloc) = pseudo_relocs.get(&literal_address) {
+                get_reloc_id(&mut reloc_ids, RelocKey::Pseudo(*pseudo_reloc)).hash(&mut hasher);
+            } else if let Some(literal) = read_literal(
+                bytes,
+                start_address as u64,
+                literal_address,
+                literal_size,
+                platform,
+            ) {
+                literal.hash(&mut hasher);
+            }
+        }
+
+        hash_args_for_insn(instruction, &mut hasher, reloc_offset, renamer.as_mut());
+        insn_hashes.push(hasher.finish());
     }
 
-    hasher.finish()
+    insn_hashes
+}
+
+/// Get the equivalence hash of each instruction of linked code. `relocs` are the code's
+/// relocations if they're known, otherwise they're recovered from the code.
+pub(crate) fn get_equivalence_hashes_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+) -> Vec<u64> {
+    hash_instructions_raw(bytes, vram, platform, arch, relocs, false)
+}
+
+pub(crate) fn get_regalloc_hash_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+) -> Option<u64> {
+    RegisterRenamer::supports(arch).then(|| {
+        stable_hash(&hash_instructions_raw(
+            bytes, vram, platform, arch, relocs, true,
+        ))
+    })
+}
+
+/// Get what each instruction is relocated against, if anything. Targets are identified by symbol
+/// name and addend in relocatable objects and by address in linked code, so that they can be
+/// compared between symbols.
+pub(crate) fn get_reloc_targets(
+    bytes: &[u8],
+    platform: Platform,
+    obj: &Object,
+    section: &Section,
+    insn_refs: &[InstructionRef],
+    modes: &InsnModes,
+) -> Vec<Option<u64>> {
+    let start_address = insn_refs.first().map(|r| r.address).unwrap_or(0);
+
+    let pseudo_relocs = if section.relocations.is_empty() {
+        find_pseudo_relocs(
+            bytes,
+            start_address,
+            platform,
+            modes.function_arch(start_address),
+            &loaded_ranges(obj),
+        )
+    } else {
+        HashMap::new()
+    };
+
+    insn_refs
+        .iter()
+        .map(|insn_ref| {
+            if let Some(reloc) = section.relocation_at(insn_ref.address, insn_ref.size) {
+                let target = &obj.symbols[reloc.target_symbol];
+                Some(stable_hash(&(target.name.as_str(), reloc.addend)))
+            } else {
+                pseudo_relocs
+                    .get(&insn_ref.address)
+                    .map(|r| stable_hash(&r.target))
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn get_reloc_targets_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+) -> Vec<Option<u64>> {
+    let pseudo_relocs = known_or_recovered(bytes, vram as u64, platform, arch, relocs);
+
+    get_insn_spans_raw(bytes, platform, arch)
+        .iter()
+        .map(|(offset, _)| {
+            pseudo_relocs
+                .get(&((vram + offset) as u64))
+                .map(|r| stable_hash(&r.target))
+        })
+        .collect()
+}
+
+/// Get the name of what each instruction refers to, if anything. Relocated instructions refer to
+/// the symbol they're relocated against. Linked code has no relocations left, so the ones
+/// recovered from it are looked up in `names_by_address`.
+pub(crate) fn get_target_names(
+    bytes: &[u8],
+    platform: Platform,
+    obj: &Object,
+    section: &Section,
+    insn_refs: &[InstructionRef],
+    modes: &InsnModes,
+    names_by_address: &HashMap<u64, String>,
+) -> Vec<Option<String>> {
+    let start_address = insn_refs.first().map(|r| r.address).unwrap_or(0);
+
+    let pseudo_relocs = if section.relocations.is_empty() {
+        find_pseudo_relocs(
+            bytes,
+            start_address,
+            platform,
+            modes.function_arch(start_address),
+            &loaded_ranges(obj),
+        )
+    } else {
+        HashMap::new()
+    };
+
+    insn_refs
+        .iter()
+        .map(|insn_ref| {
+            if let Some(reloc) = section.relocation_at(insn_ref.address, insn_ref.size) {
+                let target = &obj.symbols[reloc.target_symbol];
+                (target.kind != SymbolKind::Section).then(|| target.name.clone())
+            } else {
+                pseudo_relocs
+                    .get(&insn_ref.address)
+                    .and_then(|r| names_by_address.get(&r.target).cloned())
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn get_target_names_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+    names_by_address: &HashMap<u64, String>,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+) -> Vec<Option<String>> {
+    let pseudo_relocs = known_or_recovered(bytes, vram as u64, platform, arch, relocs);
+
+    get_insn_spans_raw(bytes, platform, arch)
+        .iter()
+        .map(|(offset, _)| {
+            pseudo_relocs
+                .get(&((vram + offset) as u64))
+                .and_then(|r| names_by_address.get(&r.target).cloned())
+        })
+        .collect()
 }
 
-pub(crate) fn get_equivalence_hash_raw(bytes: &[u8], vram: usize, platform: Platform) -> u64 {
-    let mut hasher: DefaultHasher = DefaultHasher::new();
+fn hash_instructions_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+    rename_registers: bool,
+) -> Vec<u64> {
+    let mut reloc_ids = HashMap::new();
+    let pseudo_relocs = known_or_recovered(bytes, vram as u64, platform, arch, relocs);
+
+    if arch == Arch::X86 {
+        return decode_x86(bytes, vram as u64, platform)
+            .iter()
+            .map(|(ins, offsets)| {
+                let mut hasher = StableHasher::new();
+                let reloc_offset = pseudo_relocs.get(&ins.ip()).and_then(|pseudo_reloc| {
+                    get_reloc_id(&mut reloc_ids, RelocKey::Pseudo(*pseudo_reloc)).hash(&mut hasher);
+                    x86_pseudo_reloc_offset(ins, offsets, pseudo_reloc)
+                });
+                hash_x86_args(ins, offsets, &mut hasher, reloc_offset);
+                hasher.finish()
+            })
+            .collect();
+    }
+
+    let mut insn_hashes = Vec::new();
+
+    let mut renamer = rename_registers.then(|| RegisterRenamer::new(arch));
+
+    for (offset, insn_length) in get_insn_spans_raw(bytes, platform, arch) {
+        let mut hasher = StableHasher::new();
+        let cur_vram = vram + offset;
 
-    let insn_length = platform.arch().standard_insn_length();
+        let reloc_offset = match pseudo_relocs.get(&(cur_vram as u64)) {
+            Some(pseudo_reloc) => {
+                get_reloc_id(&mut reloc_ids, RelocKey::Pseudo(*pseudo_reloc)).hash(&mut hasher);
+                Some(0)
+            }
+            None => None,
+        };
 
-    for (i, chunk) in bytes.chunks_exact(insn_length).enumerate() {
-        let cur_vram = vram + i * insn_length;
+        // A pointer in a literal pool only stands for the relocation it had, which is hashed
+        // above, so neither it nor its second half is decoded
+        let is_pool_pointer = |address: u64| {
+            pseudo_relocs
+                .get(&address)
+                .is_some_and(|r| r.kind == PseudoRelocKind::Absolute)
+        };
+        if is_pool_pointer(cur_vram as u64)
+            || (insn_length == 2 && cur_vram >= 2 && is_pool_pointer(cur_vram as u64 - 2))
+        {
+            insn_hashes.push(hasher.finish());
+            continue;
+        }
 
         let insn = decode_instruction(
-            chunk,
+            &bytes[offset..offset + insn_length],
             platform,
+            arch,
             &InstructionRef {
                 address: cur_vram as u64,
                 size: insn_length as u8,
@@ -203,25 +1532,548 @@ pub(crate) fn get_equivalence_hash_raw(bytes: &[u8], vram: usize, platform: Plat
             Ok(insn) => insn,
             Err(_) => {
                 eprintln!("Warning: Failed to read instruction at {:#X}", cur_vram);
+                insn_hashes.push(hasher.finish());
                 continue;
             }
         };
 
-        hash_args_for_insn(insn, &mut hasher, false);
+        if let Some((literal_address, literal_size)) =
+            get_pc_relative_literal(&insn, cur_vram as u64)
+        {
+            if let Some(pseudo_reloc) = pseudo_relocs.get(&literal_address) {
+                get_reloc_id(&mut reloc_ids, RelocKey::Pseudo(*pseudo_reloc)).hash(&mut hasher);
+            } else if let Some(literal) =
+                read_literal(bytes, vram as u64, literal_address, literal_size, platform)
+            {
+                literal.hash(&mut hasher);
+            }
+        }
+
+        hash_args_for_insn(insn, &mut hasher, reloc_offset, renamer.as_mut());
+        insn_hashes.push(hasher.finish());
     }
 
-    hasher.finish()
+    insn_hashes
+}
+
+fn sign_extend(value: u32, bits: u32) -> i64 {
+    let shift = 32 - bits;
+    ((value << shift) as i32 >> shift) as i64
 }
 
-fn hash_args_for_insn(insn: Insn, hasher: &mut DefaultHasher, hashed_reloc: bool) {
+fn relative_target(address: u64, offset: i64) -> u64 {
+    address.wrapping_add_signed(offset)
+}
+
+/// Get where execution can go after an instruction, and whether it has a delay slot
+fn get_insn_flow(
+    insn_bytes: &[u8],
+    address: u64,
+    arch: Arch,
+    platform: Platform,
+) -> (InsnFlow, bool) {
+    let endianness = platform.endianness();
+    let word = || endianness.read_u32_bytes(insn_bytes.try_into().unwrap());
+    let half = || endianness.read_u16_bytes(insn_bytes[..2].try_into().unwrap());
+    let second_half = || {
+        (insn_bytes.len() == 4)
+            .then(|| endianness.read_u16_bytes(insn_bytes[2..4].try_into().unwrap()))
+    };
+
+    match arch {
+        Arch::Mips => {
+            let word = word();
+            (mips_flow(word, address), mips_has_delay_slot(word))
+        }
+        Arch::Ppc => (ppc_flow(word(), address), false),
+        Arch::Thumb => (thumb_flow(half(), second_half(), address), false),
+        Arch::Arm if insn_bytes.len() == 2 => (thumb_flow(half(), None, address), false),
+        Arch::Arm => (arm_flow(word(), address), false),
+        Arch::Aarch64 => (aarch64_flow(word(), address), false),
+        Arch::SuperH => superh_flow(half(), address),
+        Arch::X86 => {
+            let mut decoder = iced_x86::Decoder::with_ip(
+                platform.x86_bitness(),
+                insn_bytes,
+                address,
+                DecoderOptions::NONE,
+            );
+            (x86_flow(&decoder.decode()), false)
+        }
+    }
+}
+
+fn mips_flow(word: u32, address: u64) -> InsnFlow {
+    let op = word >> 26;
+    let rs = (word >> 21) & 0x1F;
+    let rt = (word >> 16) & 0x1F;
+    let branch_target = relative_target(address + 4, sign_extend(word & 0xFFFF, 16) << 2);
+
+    let jump_target = ((address + 4) & 0xF000_0000) | ((word & 0x03FF_FFFF) << 2) as u64;
+
+    match op {
+        // jr
+        0x00 if word & 0x3F == 0x08 => InsnFlow::Exit,
+        // jalr
+        0x00 if word & 0x3F == 0x09 => InsnFlow::Call(None),
+        // bltz, bgez, bltzl, bgezl
+        0x01 if rt <= 0x03 => InsnFlow::Branch(branch_target),
+        // bltzal, bgezal (including bal), bltzall, bgezall
+        0x01 if (0x10..=0x13).contains(&rt) => InsnFlow::Call(Some(branch_target)),
+        // j
+        0x02 => InsnFlow::Jump(jump_target),
+        // jal
+        0x03 => InsnFlow::Call(Some(jump_target)),
+        // beq $zero, $zero is an unconditional b
+        0x04 if rs == 0 && rt == 0 => InsnFlow::Jump(branch_target),
+        // beq, bne, blez, bgtz and their likely variants
+        0x04..=0x07 | 0x14..=0x17 => InsnFlow::Branch(branch_target),
+        // bc1f, bc1t and their likely variants
+        0x11 if rs == 0x08 => InsnFlow::Branch(branch_target),
+        _ => InsnFlow::Next,
+    }
+}
+
+/// Whether a MIPS instruction has effects the scheduler doesn't track, so that no instruction may
+/// be moved across it
+fn mips_is_barrier(word: u32, platform: Platform) -> bool {
+    let op = word >> 26;
+    let rs = (word >> 21) & 0x1F;
+    let rt = (word >> 16) & 0x1F;
+    let funct = word & 0x3F;
+    match op {
+        // syscall, break, sync and traps
+        0x00 => matches!(funct, 0x0C | 0x0D | 0x0F | 0x30..=0x36),
+        // trap on immediate
+        0x01 => (0x08..=0x0E).contains(&rt),
+        // COP0, COP2 (the PS1's GTE, the PS2's VU0), lwc2, swc2, cache
+        0x10 | 0x12 | 0x32 | 0x3A | 0x2F => true,
+        // cfc1, ctc1 access the FP control registers
+        0x11 => matches!(rs, 0x02 | 0x06),
+        // PS2 multimedia instructions, which use the upper halves of registers and HI/LO
+        0x1C => platform == Platform::Ps2,
+        // PSP VFPU instructions
+        0x18 | 0x19 | 0x1B | 0x34 | 0x36 | 0x37 | 0x3C | 0x3E | 0x3F => platform == Platform::Psp,
+        _ => false,
+    }
+}
+
+/// Get the registers and memory a MIPS instruction reads and writes
+fn mips_def_use(word: u32, platform: Platform) -> DefUse {
+    let insn = get_rabbitizer_instruction(word, 0, platform);
+    let opcode = insn.opcode();
+    let op = word >> 26;
+    let rs = (word >> 21) & 0x1F;
+    let rt = (word >> 16) & 0x1F;
+    let rd = (word >> 11) & 0x1F;
+    let fd = (word >> 6) & 0x1F;
+
+    let mut ret = DefUse {
+        nop: word == 0,
+        // beql, bnel, blezl, bgtzl, bltzl, bgezl, bltzall, bgezall, bc1fl, bc1tl
+        branch_likely: matches!(op, 0x14..=0x17)
+            || (op == 0x01 && matches!(rt, 0x02 | 0x03 | 0x12 | 0x13))
+            || (op == 0x11 && rs == 0x08 && rt & 0x02 != 0),
+        ..Default::default()
+    };
+
+    if !insn.is_valid() || mips_is_barrier(word, platform) {
+        ret.barrier = true;
+        return ret;
+    }
+
+    ret.load = opcode.does_load();
+    ret.store = opcode.does_store();
+
+    let regs = [
+        (opcode.reads_rs(), opcode.modifies_rs(), DefUse::gpr(rs)),
+        (opcode.reads_rt(), opcode.modifies_rt(), DefUse::gpr(rt)),
+        (opcode.reads_rd(), opcode.modifies_rd(), DefUse::gpr(rd)),
+        (opcode.reads_fs(), opcode.modifies_fs(), DefUse::fpr(rd)),
+        (opcode.reads_ft(), opcode.modifies_ft(), DefUse::fpr(rt)),
+        (opcode.reads_fd(), opcode.modifies_fd(), DefUse::fpr(fd)),
+        (opcode.reads_hi(), opcode.modifies_hi(), DefUse::hi()),
+        (opcode.reads_lo(), opcode.modifies_lo(), DefUse::lo()),
+    ];
+    for (reads, modifies, bits) in regs {
+        if reads {
+            ret.uses |= bits;
+        }
+        if modifies {
+            ret.defs |= bits;
+        }
+    }
+    if opcode.does_link() {
+        ret.defs |= DefUse::gpr(31);
+    }
+
+    // c.cond.fmt sets the FP condition flag, which bc1f and bc1t read
+    if op == 0x11 && matches!(rs, 0x10 | 0x11) && (0x30..=0x3F).contains(&(word & 0x3F)) {
+        ret.defs |= DefUse::fcc();
+    }
+    if op == 0x11 && rs == 0x08 {
+        ret.uses |= DefUse::fcc();
+    }
+
+    ret
+}
+
+/// Get the registers and memory each instruction of MIPS code reads and writes
+pub(crate) fn get_mips_def_uses(bytes: &[u8], platform: Platform) -> Vec<DefUse> {
+    let endianness = platform.endianness();
+    bytes
+        .chunks_exact(4)
+        .map(|chunk| endianness.read_u32_bytes(chunk.try_into().unwrap()))
+        .map(|word| mips_def_use(word, platform))
+        .collect()
+}
+
+fn ppc_flow(word: u32, address: u64) -> InsnFlow {
+    let op = word >> 26;
+    let bo = (word >> 21) & 0x1F;
+    let absolute = word & 2 != 0;
+    let link = word & 1 != 0;
+    // The condition is ignored when bits 0 and 2 of BO are set
+    let always = bo & 0x14 == 0x14;
+
+    let target = |offset: i64| {
+        if absolute {
+            offset as u64
+        } else {
+            relative_target(address, offset)
+        }
+    };
+
+    match op {
+        // bcl, bl
+        16 if link => InsnFlow::Call(Some(target(sign_extend(word & 0xFFFC, 16)))),
+        18 if link => InsnFlow::Call(Some(target(sign_extend(word & 0x03FF_FFFC, 26)))),
+        // bclrl, bcctrl
+        19 if link => InsnFlow::Call(None),
+        // bc
+        16 => {
+            let target = target(sign_extend(word & 0xFFFC, 16));
+            if always {
+                InsnFlow::Jump(target)
+            } else {
+                InsnFlow::Branch(target)
+            }
+        }
+        // b
+        18 => InsnFlow::Jump(target(sign_extend(word & 0x03FF_FFFC, 26))),
+        // bclr, bcctr
+        19 if matches!((word >> 1) & 0x3FF, 16 | 528) => {
+            if always {
+                InsnFlow::Exit
+            } else {
+                // A conditional return may fall through
+                InsnFlow::Next
+            }
+        }
+        _ => InsnFlow::Next,
+    }
+}
+
+fn thumb_flow(half: u16, second_half: Option<u16>, address: u64) -> InsnFlow {
+    let half = half as u32;
+
+    // BL/BLX pairs
+    if let Some(second_half) = second_half {
+        let second_half = second_half as u32;
+        if half & 0xF800 != 0xF000 {
+            return InsnFlow::Next;
+        }
+        let offset = (sign_extend(half & 0x7FF, 11) << 12) | ((second_half & 0x7FF) << 1) as i64;
+        let target = relative_target(address + 4, offset);
+        return match second_half & 0xF800 {
+            0xF800 => InsnFlow::Call(Some(target)),
+            // BLX switches to ARM, whose instructions are word-aligned
+            0xE800 => InsnFlow::Call(Some(target & !3)),
+            _ => InsnFlow::Next,
+        };
+    }
+
+    match half {
+        // b<cond> (0xE is undefined and 0xF is swi)
+        0xD000..=0xDDFF => InsnFlow::Branch(relative_target(
+            address + 4,
+            sign_extend(half & 0xFF, 8) << 1,
+        )),
+        // b
+        0xE000..=0xE7FF => InsnFlow::Jump(relative_target(
+            address + 4,
+            sign_extend(half & 0x7FF, 11) << 1,
+        )),
+        // bx
+        0x4700..=0x477F => InsnFlow::Exit,
+        // blx rm
+        0x4780..=0x47FF => InsnFlow::Call(None),
+        // pop {..., pc}
+        0xBD00..=0xBDFF => InsnFlow::Exit,
+        _ => InsnFlow::Next,
+    }
+}
+
+fn arm_flow(word: u32, address: u64) -> InsnFlow {
+    let cond = word >> 28;
+
+    // b, bl (cond 0xF is blx)
+    if word & 0x0E00_0000 == 0x0A00_0000 {
+        let target = relative_target(address + 8, sign_extend(word & 0x00FF_FFFF, 24) << 2);
+        if cond == 0xF {
+            // The H bit picks the halfword of the Thumb target
+            return InsnFlow::Call(Some(target + ((word >> 23) & 2) as u64));
+        }
+        if word & 0x0100_0000 != 0 {
+            return InsnFlow::Call(Some(target));
+        }
+        return if cond == 0xE {
+            InsnFlow::Jump(target)
+        } else {
+            InsnFlow::Branch(target)
+        };
+    }
+
+    // blx rm
+    if word & 0x0FFF_FFF0 == 0x012F_FF30 {
+        return InsnFlow::Call(None);
+    }
+
+    let exits =
+        // bx
+        word & 0x0FFF_FFF0 == 0x012F_FF10
+        // ldm with pc in the register list
+        || word & 0x0E10_8000 == 0x0810_8000
+        // ldr pc, [...]
+        || word & 0x0C10_F000 == 0x0410_F000
+        // mov pc, rm
+        || word & 0x0FEF_F000 == 0x01A0_F000;
+
+    match (exits, cond) {
+        (false, _) => InsnFlow::Next,
+        // A conditional return may fall through
+        (true, 0xE) => InsnFlow::Exit,
+        (true, _) => InsnFlow::Next,
+    }
+}
+
+fn aarch64_flow(word: u32, address: u64) -> InsnFlow {
+    if word & 0xFC00_0000 == 0x1400_0000 {
+        // b
+        InsnFlow::Jump(relative_target(
+            address,
+            sign_extend(word & 0x03FF_FFFF, 26) << 2,
+        ))
+    } else if word & 0xFC00_0000 == 0x9400_0000 {
+        // bl
+        InsnFlow::Call(Some(relative_target(
+            address,
+            sign_extend(word & 0x03FF_FFFF, 26) << 2,
+        )))
+    } else if word & 0xFF00_0010 == 0x5400_0000 {
+        // b.cond (al and nv are always taken)
+        let target = relative_target(address, sign_extend((word >> 5) & 0x7FFFF, 19) << 2);
+        if word & 0xE == 0xE {
+            InsnFlow::Jump(target)
+        } else {
+            InsnFlow::Branch(target)
+        }
+    } else if word & 0x7E00_0000 == 0x3400_0000 {
+        // cbz, cbnz
+        InsnFlow::Branch(relative_target(
+            address,
+            sign_extend((word >> 5) & 0x7FFFF, 19) << 2,
+        ))
+    } else if word & 0x7E00_0000 == 0x3600_0000 {
+        // tbz, tbnz
+        InsnFlow::Branch(relative_target(
+            address,
+            sign_extend((word >> 5) & 0x3FFF, 14) << 2,
+        ))
+    } else if matches!(word & 0xFFFF_FC1F, 0xD65F_0000 | 0xD61F_0000) {
+        // ret, br
+        InsnFlow::Exit
+    } else if word & 0xFFFF_FC1F == 0xD63F_0000 {
+        // blr
+        InsnFlow::Call(None)
+    } else {
+        InsnFlow::Next
+    }
+}
+
+fn superh_flow(insn: u16, address: u64) -> (InsnFlow, bool) {
+    let insn = insn as u32;
+    let target8 = relative_target(address + 4, sign_extend(insn & 0xFF, 8) << 1);
+    let target12 = relative_target(address + 4, sign_extend(insn & 0xFFF, 12) << 1);
+
     match insn {
-        Insn::Mips(insn) => hash_mips_args(insn, hasher, hashed_reloc),
-        Insn::Ppc(insn) => hash_ppc_args(insn, hasher, hashed_reloc),
-        Insn::Thumb(insn) => hash_thumb_args(insn, hasher, hashed_reloc),
+        // bt, bf
+        _ if insn & 0xFD00 == 0x8900 => (InsnFlow::Branch(target8), false),
+        // bt/s, bf/s
+        _ if insn & 0xFD00 == 0x8D00 => (InsnFlow::Branch(target8), true),
+        // bra
+        _ if insn & 0xF000 == 0xA000 => (InsnFlow::Jump(target12), true),
+        // bsr
+        _ if insn & 0xF000 == 0xB000 => (InsnFlow::Call(Some(target12)), true),
+        // rts, rte
+        0x000B | 0x002B => (InsnFlow::Exit, true),
+        // jmp @rn, braf rn
+        _ if insn & 0xF0FF == 0x402B || insn & 0xF0FF == 0x0023 => (InsnFlow::Exit, true),
+        // jsr @rn, bsrf rn
+        _ if insn & 0xF0FF == 0x400B || insn & 0xF0FF == 0x0003 => (InsnFlow::Call(None), true),
+        _ => (InsnFlow::Next, false),
     }
 }
 
-fn hash_mips_args(insn: rabbitizer::Instruction, hasher: &mut DefaultHasher, hashed_reloc: bool) {
+fn x86_flow(ins: &iced_x86::Instruction) -> InsnFlow {
+    match ins.flow_control() {
+        FlowControl::UnconditionalBranch => InsnFlow::Jump(ins.near_branch_target()),
+        FlowControl::ConditionalBranch => InsnFlow::Branch(ins.near_branch_target()),
+        FlowControl::IndirectBranch | FlowControl::Return | FlowControl::Exception => {
+            InsnFlow::Exit
+        }
+        FlowControl::Call => InsnFlow::Call(Some(ins.near_branch_target())),
+        FlowControl::IndirectCall => InsnFlow::Call(None),
+        _ => InsnFlow::Next,
+    }
+}
+
+/// Get where execution can go after each instruction of a symbol from an object. Branch targets
+/// come from objdiff, which resolves relocated branches to other symbols.
+pub(crate) fn get_insn_flows(
+    bytes: &[u8],
+    platform: Platform,
+    insn_refs: &[InstructionRef],
+    modes: &InsnModes,
+) -> Vec<CfgInsn> {
+    let start_address = insn_refs.first().map(|r| r.address).unwrap_or(0);
+
+    insn_refs
+        .iter()
+        .map(|insn_ref| {
+            let offset = (insn_ref.address - start_address) as usize;
+            let insn_bytes = &bytes[offset..offset + insn_ref.size as usize];
+            let (mut flow, delay_slot) = match modes.arch_at(insn_ref.address) {
+                Some(arch) => get_insn_flow(insn_bytes, insn_ref.address, arch, platform),
+                None => (InsnFlow::Next, false),
+            };
+
+            if let Some(dest) = insn_ref.branch_dest {
+                flow = match flow {
+                    InsnFlow::Jump(_) => InsnFlow::Jump(dest),
+                    InsnFlow::Branch(_) => InsnFlow::Branch(dest),
+                    flow => flow,
+                };
+            }
+
+            CfgInsn {
+                address: insn_ref.address,
+                flow,
+                delay_slot,
+            }
+        })
+        .collect()
+}
+
+/// Get where execution can go after each instruction of raw code, from the branch targets
+/// encoded in it
+pub(crate) fn get_insn_flows_raw(
+    bytes: &[u8],
+    vram: usize,
+    platform: Platform,
+    arch: Arch,
+) -> Vec<CfgInsn> {
+    get_insn_spans_raw(bytes, platform, arch)
+        .iter()
+        .map(|(offset, length)| {
+            let address = (vram + offset) as u64;
+            let (flow, delay_slot) =
+                get_insn_flow(&bytes[*offset..offset + length], address, arch, platform);
+            CfgInsn {
+                address,
+                flow,
+                delay_slot,
+            }
+        })
+        .collect()
+}
+
+/// Get what a symbol from an object calls, in order. Relocated calls are identified by the name
+/// of the function they're relocated against, or by address for calls to a section plus offset.
+pub(crate) fn get_calls(
+    obj: &Object,
+    section: &Section,
+    insn_refs: &[InstructionRef],
+    flows: &[CfgInsn],
+) -> Vec<CallTarget> {
+    insn_refs
+        .iter()
+        .zip(flows)
+        .filter_map(|(insn_ref, insn)| {
+            let InsnFlow::Call(target) = insn.flow else {
+                return None;
+            };
+
+            match section.relocation_at(insn_ref.address, insn_ref.size) {
+                Some(reloc) => {
+                    let symbol = &obj.symbols[reloc.target_symbol];
+                    if symbol.kind == SymbolKind::Section {
+                        Some(CallTarget::Address(
+                            symbol.address.wrapping_add_signed(reloc.addend),
+                        ))
+                    } else {
+                        Some(CallTarget::Symbol(symbol.name.clone()))
+                    }
+                }
+                None => target.map(CallTarget::Address),
+            }
+        })
+        .collect()
+}
+
+/// Get what raw code calls, in order, by the addresses encoded in the calls
+pub(crate) fn get_calls_raw(flows: &[CfgInsn]) -> Vec<CallTarget> {
+    flows
+        .iter()
+        .filter_map(|insn| match insn.flow {
+            InsnFlow::Call(target) => target.map(CallTarget::Address),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Hash the operands of an instruction. `reloc_offset` is the offset within the instruction of
+/// the relocation applied to it, if any. Registers are renamed through `regs` when given.
+fn hash_args_for_insn(
+    insn: Insn,
+    hasher: &mut StableHasher,
+    reloc_offset: Option<u64>,
+    regs: Option<&mut RegisterRenamer>,
+) {
+    let hashed_reloc = reloc_offset.is_some();
+    match insn {
+        Insn::Mips(insn, code) => hash_mips_args(insn, code, hasher, hashed_reloc, regs),
+        Insn::Ppc(insn) => hash_ppc_args(insn, hasher, hashed_reloc, regs),
+        Insn::Thumb(insn, code) => hash_thumb_args(insn, code, hasher, hashed_reloc),
+        Insn::Arm(insn, code) => hash_arm_args(insn, code, hasher, hashed_reloc),
+        Insn::Aarch64(insn) => hash_aarch64_args(insn, hasher, hashed_reloc),
+        Insn::SuperH(insn) => hash_superh_args(insn, hasher, hashed_reloc),
+        Insn::X86(insn, offsets) => hash_x86_args(&insn, &offsets, hasher, reloc_offset),
+    }
+}
+
+fn hash_mips_args(
+    insn: rabbitizer::Instruction,
+    code: u32,
+    hasher: &mut StableHasher,
+    hashed_reloc: bool,
+    mut regs: Option<&mut RegisterRenamer>,
+) {
+    let rs = (code >> 21) & 0x1F;
+    let rt = (code >> 16) & 0x1F;
+    let rd = (code >> 11) & 0x1F;
+    let fd = (code >> 6) & 0x1F;
+
     // hash opcode
     insn.opcode().hash(hasher);
 
@@ -229,25 +2081,47 @@ fn hash_mips_args(insn: rabbitizer::Instruction, hasher: &mut DefaultHasher, has
     for vo in insn.valued_operands_iter() {
         match vo {
             ValuedOperand::ALL_EMPTY() => vo.hash(hasher),
-            ValuedOperand::core_rs(_) => vo.hash(hasher),
-            ValuedOperand::core_rt(_) => vo.hash(hasher),
-            ValuedOperand::core_rd(_) => vo.hash(hasher),
+            ValuedOperand::core_rs(_) => {
+                hash_register(vo, RegisterKind::Gpr, rs, regs.as_deref_mut(), hasher)
+            }
+            ValuedOperand::core_rt(_) => {
+                hash_register(vo, RegisterKind::Gpr, rt, regs.as_deref_mut(), hasher)
+            }
+            ValuedOperand::core_rd(_) => {
+                hash_register(vo, RegisterKind::Gpr, rd, regs.as_deref_mut(), hasher)
+            }
             ValuedOperand::core_sa(_) => vo.hash(hasher),
             ValuedOperand::core_zero() => vo.hash(hasher),
             ValuedOperand::core_cop0d(_) => vo.hash(hasher),
             ValuedOperand::core_cop0cd(_) => vo.hash(hasher),
-            ValuedOperand::core_fs(_) => vo.hash(hasher),
-            ValuedOperand::core_ft(_) => vo.hash(hasher),
-            ValuedOperand::core_fd(_) => vo.hash(hasher),
-            // ValuedOperand::core_cop1cs(_) => {}
-            // ValuedOperand::core_cop2t(_) => {}
-            // ValuedOperand::core_cop2d(_) => {}
-            // ValuedOperand::core_cop2cd(_) => {}
-            // ValuedOperand::core_op(_) => {}
-            // ValuedOperand::core_hint(_) => {}
-            // ValuedOperand::core_code(_, _) => {}
-            // ValuedOperand::core_code_lower(_) => {}
-            // ValuedOperand::core_copraw(_) => {}
+            ValuedOperand::core_fs(_) => {
+                hash_register(vo, RegisterKind::Fpr, rd, regs.as_deref_mut(), hasher)
+            }
+            ValuedOperand::core_ft(_) => {
+                hash_register(vo, RegisterKind::Fpr, rt, regs.as_deref_mut(), hasher)
+            }
+            ValuedOperand::core_fd(_) => {
+                hash_register(vo, RegisterKind::Fpr, fd, regs.as_deref_mut(), hasher)
+            }
+            ValuedOperand::core_cop1cs(_)
+            | ValuedOperand::core_cop2t(_)
+            | ValuedOperand::core_cop2d(_)
+            | ValuedOperand::core_cop2cd(_)
+            | ValuedOperand::core_op(_)
+            | ValuedOperand::core_hint(_)
+            | ValuedOperand::core_code(_, _)
+            | ValuedOperand::core_code_lower(_)
+            | ValuedOperand::core_copraw(_) => vo.hash(hasher),
+            // jalr's link register and jr's target
+            ValuedOperand::core_maybe_rd_rs(_, _) | ValuedOperand::core_maybe_zero_rs(_, _) => {
+                match regs.as_deref_mut() {
+                    Some(regs) => {
+                        regs.rename(RegisterKind::Gpr, rd).hash(hasher);
+                        regs.rename(RegisterKind::Gpr, rs).hash(hasher);
+                    }
+                    None => vo.hash(hasher),
+                }
+            }
             ValuedOperand::core_label(_) => {
                 if !hashed_reloc {
                     vo.hash(hasher);
@@ -266,121 +2140,199 @@ fn hash_mips_args(insn: rabbitizer::Instruction, hasher: &mut DefaultHasher, has
             ValuedOperand::core_branch_target_label(_) => {
                 vo.hash(hasher);
             }
-            ValuedOperand::core_imm_rs(_, gpr) => {
+            ValuedOperand::core_imm_rs(imm, gpr) => {
+                hash_mips_offset_base(vo, imm, gpr, rs, hashed_reloc, regs.as_deref_mut(), hasher)
+            }
+
+            // RSP: vector registers and element selectors are hashed, the offset of a vector
+            // load/store is treated like any other relocatable immediate
+            ValuedOperand::rsp_cop0d(_)
+            | ValuedOperand::rsp_cop2cd(_)
+            | ValuedOperand::rsp_vs(_)
+            | ValuedOperand::rsp_vd(_)
+            | ValuedOperand::rsp_vt_elementhigh(_, _)
+            | ValuedOperand::rsp_vt_elementlow(_, _)
+            | ValuedOperand::rsp_vd_de(_, _)
+            | ValuedOperand::rsp_vs_index(_, _) => vo.hash(hasher),
+            ValuedOperand::rsp_offset_rs(offset, gpr) => hash_mips_offset_base(
+                vo,
+                offset,
+                gpr,
+                rs,
+                hashed_reloc,
+                regs.as_deref_mut(),
+                hasher,
+            ),
+
+            // GTE: the shift, matrix, vector, translation and saturation flags all change the
+            // result of the operation, so they're hashed
+            ValuedOperand::r3000gte_sf(_)
+            | ValuedOperand::r3000gte_mx(_)
+            | ValuedOperand::r3000gte_v(_)
+            | ValuedOperand::r3000gte_cv(_)
+            | ValuedOperand::r3000gte_lm(_) => vo.hash(hasher),
+
+            // Allegrex: VFPU registers are hashed along with the bank size they're accessed as
+            // (S/P/T/Q/M), since the same register number means different data in each.
+            // lv/sv offsets are masked under a relocation like core loads and stores.
+            ValuedOperand::r4000allegrex_s_vs(_)
+            | ValuedOperand::r4000allegrex_s_vt(_)
+            | ValuedOperand::r4000allegrex_s_vd(_)
+            | ValuedOperand::r4000allegrex_s_vt_imm(_)
+            | ValuedOperand::r4000allegrex_s_vd_imm(_)
+            | ValuedOperand::r4000allegrex_p_vs(_)
+            | ValuedOperand::r4000allegrex_p_vt(_)
+            | ValuedOperand::r4000allegrex_p_vd(_)
+            | ValuedOperand::r4000allegrex_t_vs(_)
+            | ValuedOperand::r4000allegrex_t_vt(_)
+            | ValuedOperand::r4000allegrex_t_vd(_)
+            | ValuedOperand::r4000allegrex_q_vs(_)
+            | ValuedOperand::r4000allegrex_q_vt(_)
+            | ValuedOperand::r4000allegrex_q_vd(_)
+            | ValuedOperand::r4000allegrex_q_vt_imm(_)
+            | ValuedOperand::r4000allegrex_mp_vs(_)
+            | ValuedOperand::r4000allegrex_mp_vt(_)
+            | ValuedOperand::r4000allegrex_mp_vd(_)
+            | ValuedOperand::r4000allegrex_mp_vs_transpose(_)
+            | ValuedOperand::r4000allegrex_mt_vs(_)
+            | ValuedOperand::r4000allegrex_mt_vt(_)
+            | ValuedOperand::r4000allegrex_mt_vd(_)
+            | ValuedOperand::r4000allegrex_mt_vs_transpose(_)
+            | ValuedOperand::r4000allegrex_mq_vs(_)
+            | ValuedOperand::r4000allegrex_mq_vt(_)
+            | ValuedOperand::r4000allegrex_mq_vd(_)
+            | ValuedOperand::r4000allegrex_mq_vs_transpose(_)
+            | ValuedOperand::r4000allegrex_cop2cs(_)
+            | ValuedOperand::r4000allegrex_cop2cd(_) => vo.hash(hasher),
+            ValuedOperand::r4000allegrex_pos(_)
+            | ValuedOperand::r4000allegrex_size(_)
+            | ValuedOperand::r4000allegrex_size_plus_pos(_)
+            | ValuedOperand::r4000allegrex_imm3(_)
+            | ValuedOperand::r4000allegrex_vcmp_cond_s_maybe_vs_maybe_vt(_, _, _)
+            | ValuedOperand::r4000allegrex_vcmp_cond_p_maybe_vs_maybe_vt(_, _, _)
+            | ValuedOperand::r4000allegrex_vcmp_cond_t_maybe_vs_maybe_vt(_, _, _)
+            | ValuedOperand::r4000allegrex_vcmp_cond_q_maybe_vs_maybe_vt(_, _, _)
+            | ValuedOperand::r4000allegrex_vconstant(_)
+            | ValuedOperand::r4000allegrex_power_of_two(_)
+            | ValuedOperand::r4000allegrex_vfpu_cc_bit(_)
+            | ValuedOperand::r4000allegrex_bn(_)
+            | ValuedOperand::r4000allegrex_float16(_)
+            | ValuedOperand::r4000allegrex_p_vrot_code(_)
+            | ValuedOperand::r4000allegrex_t_vrot_code(_)
+            | ValuedOperand::r4000allegrex_q_vrot_code(_) => vo.hash(hasher),
+            ValuedOperand::r4000allegrex_int16(_) => {
+                if !hashed_reloc {
+                    vo.hash(hasher);
+                }
+            }
+            ValuedOperand::r4000allegrex_offset14_base(offset, gpr) => hash_mips_offset_base(
+                vo,
+                offset,
+                gpr,
+                rs,
+                hashed_reloc,
+                regs.as_deref_mut(),
+                hasher,
+            ),
+            ValuedOperand::r4000allegrex_offset14_base_maybe_wb(offset, gpr, wb) => {
+                hash_mips_offset_base(
+                    vo,
+                    (offset, wb),
+                    gpr,
+                    rs,
+                    hashed_reloc,
+                    regs.as_deref_mut(),
+                    hasher,
+                )
+            }
+
+            // EE: VU0 float and integer registers, their xyzw field masks and the
+            // pre-decrement/post-increment forms are all hashed
+            ValuedOperand::r5900ee_I()
+            | ValuedOperand::r5900ee_Q()
+            | ValuedOperand::r5900ee_R()
+            | ValuedOperand::r5900ee_ACC()
+            | ValuedOperand::r5900ee_immediate5(_)
+            | ValuedOperand::r5900ee_vfs(_)
+            | ValuedOperand::r5900ee_vft(_)
+            | ValuedOperand::r5900ee_vfd(_)
+            | ValuedOperand::r5900ee_vis(_)
+            | ValuedOperand::r5900ee_vit(_)
+            | ValuedOperand::r5900ee_vid(_)
+            | ValuedOperand::r5900ee_ACCxyzw(_, _, _, _)
+            | ValuedOperand::r5900ee_vfsxyzw(_, _, _, _, _)
+            | ValuedOperand::r5900ee_vftxyzw(_, _, _, _, _)
+            | ValuedOperand::r5900ee_vfdxyzw(_, _, _, _, _)
+            | ValuedOperand::r5900ee_vftn(_, _)
+            | ValuedOperand::r5900ee_vfsl(_, _)
+            | ValuedOperand::r5900ee_vftm(_, _)
+            | ValuedOperand::r5900ee_vis_predecr(_, _)
+            | ValuedOperand::r5900ee_vit_predecr(_, _)
+            | ValuedOperand::r5900ee_vis_postincr(_, _)
+            | ValuedOperand::r5900ee_vit_postincr(_, _)
+            | ValuedOperand::r5900ee_vis_parenthesis(_) => vo.hash(hasher),
+            ValuedOperand::r5900ee_immediate15(_) => {
                 if !hashed_reloc {
                     vo.hash(hasher);
-                } else {
-                    gpr.hash(hasher);
                 }
             }
-            // ValuedOperand::core_maybe_rd_rs(_, _) => {}
-            // ValuedOperand::core_maybe_zero_rs(_, _) => {}
-            // ValuedOperand::rsp_cop0d(_) => {}
-            // ValuedOperand::rsp_cop2cd(_) => {}
-            // ValuedOperand::rsp_vs(_) => {}
-            // ValuedOperand::rsp_vd(_) => {}
-            // ValuedOperand::rsp_vt_elementhigh(_, _) => {}
-            // ValuedOperand::rsp_vt_elementlow(_, _) => {}
-            // ValuedOperand::rsp_vd_de(_, _) => {}
-            // ValuedOperand::rsp_vs_index(_, _) => {}
-            // ValuedOperand::rsp_offset_rs(_, _) => {}
-            // ValuedOperand::r3000gte_sf(_) => {}
-            // ValuedOperand::r3000gte_mx(_) => {}
-            // ValuedOperand::r3000gte_v(_) => {}
-            // ValuedOperand::r3000gte_cv(_) => {}
-            // ValuedOperand::r3000gte_lm(_) => {}
-            // ValuedOperand::r4000allegrex_s_vs(_) => {}
-            // ValuedOperand::r4000allegrex_s_vt(_) => {}
-            // ValuedOperand::r4000allegrex_s_vd(_) => {}
-            // ValuedOperand::r4000allegrex_s_vt_imm(_) => {}
-            // ValuedOperand::r4000allegrex_s_vd_imm(_) => {}
-            // ValuedOperand::r4000allegrex_p_vs(_) => {}
-            // ValuedOperand::r4000allegrex_p_vt(_) => {}
-            // ValuedOperand::r4000allegrex_p_vd(_) => {}
-            // ValuedOperand::r4000allegrex_t_vs(_) => {}
-            // ValuedOperand::r4000allegrex_t_vt(_) => {}
-            // ValuedOperand::r4000allegrex_t_vd(_) => {}
-            // ValuedOperand::r4000allegrex_q_vs(_) => {}
-            // ValuedOperand::r4000allegrex_q_vt(_) => {}
-            // ValuedOperand::r4000allegrex_q_vd(_) => {}
-            // ValuedOperand::r4000allegrex_q_vt_imm(_) => {}
-            // ValuedOperand::r4000allegrex_mp_vs(_) => {}
-            // ValuedOperand::r4000allegrex_mp_vt(_) => {}
-            // ValuedOperand::r4000allegrex_mp_vd(_) => {}
-            // ValuedOperand::r4000allegrex_mp_vs_transpose(_) => {}
-            // ValuedOperand::r4000allegrex_mt_vs(_) => {}
-            // ValuedOperand::r4000allegrex_mt_vt(_) => {}
-            // ValuedOperand::r4000allegrex_mt_vd(_) => {}
-            // ValuedOperand::r4000allegrex_mt_vs_transpose(_) => {}
-            // ValuedOperand::r4000allegrex_mq_vs(_) => {}
-            // ValuedOperand::r4000allegrex_mq_vt(_) => {}
-            // ValuedOperand::r4000allegrex_mq_vd(_) => {}
-            // ValuedOperand::r4000allegrex_mq_vs_transpose(_) => {}
-            // ValuedOperand::r4000allegrex_cop2cs(_) => {}
-            // ValuedOperand::r4000allegrex_cop2cd(_) => {}
-            // ValuedOperand::r4000allegrex_pos(_) => {}
-            // ValuedOperand::r4000allegrex_size(_) => {}
-            // ValuedOperand::r4000allegrex_size_plus_pos(_) => {}
-            // ValuedOperand::r4000allegrex_imm3(_) => {}
-            // ValuedOperand::r4000allegrex_offset14_base(_, _) => {}
-            // ValuedOperand::r4000allegrex_offset14_base_maybe_wb(_, _, _) => {}
-            // ValuedOperand::r4000allegrex_vcmp_cond_s_maybe_vs_maybe_vt(_, _, _) => {}
-            // ValuedOperand::r4000allegrex_vcmp_cond_p_maybe_vs_maybe_vt(_, _, _) => {}
-            // ValuedOperand::r4000allegrex_vcmp_cond_t_maybe_vs_maybe_vt(_, _, _) => {}
-            // ValuedOperand::r4000allegrex_vcmp_cond_q_maybe_vs_maybe_vt(_, _, _) => {}
-            // ValuedOperand::r4000allegrex_vconstant(_) => {}
-            // ValuedOperand::r4000allegrex_power_of_two(_) => {}
-            // ValuedOperand::r4000allegrex_vfpu_cc_bit(_) => {}
-            // ValuedOperand::r4000allegrex_bn(_) => {}
-            // ValuedOperand::r4000allegrex_int16(_) => {}
-            // ValuedOperand::r4000allegrex_float16(_) => {}
-            // ValuedOperand::r4000allegrex_p_vrot_code(_) => {}
-            // ValuedOperand::r4000allegrex_t_vrot_code(_) => {}
-            // ValuedOperand::r4000allegrex_q_vrot_code(_) => {}
-            // ValuedOperand::r4000allegrex_wpx(_) => {}
-            // ValuedOperand::r4000allegrex_wpy(_) => {}
-            // ValuedOperand::r4000allegrex_wpz(_) => {}
-            // ValuedOperand::r4000allegrex_wpw(_) => {}
-            // ValuedOperand::r4000allegrex_rpx(_) => {}
-            // ValuedOperand::r4000allegrex_rpy(_) => {}
-            // ValuedOperand::r4000allegrex_rpz(_) => {}
-            // ValuedOperand::r4000allegrex_rpw(_) => {}
-            // ValuedOperand::r5900ee_I() => {}
-            // ValuedOperand::r5900ee_Q() => {}
-            // ValuedOperand::r5900ee_R() => {}
-            // ValuedOperand::r5900ee_ACC() => {}
-            // ValuedOperand::r5900ee_immediate5(_) => {}
-            // ValuedOperand::r5900ee_immediate15(_) => {}
-            // ValuedOperand::r5900ee_vfs(_) => {}
-            // ValuedOperand::r5900ee_vft(_) => {}
-            // ValuedOperand::r5900ee_vfd(_) => {}
-            // ValuedOperand::r5900ee_vis(_) => {}
-            // ValuedOperand::r5900ee_vit(_) => {}
-            // ValuedOperand::r5900ee_vid(_) => {}
-            // ValuedOperand::r5900ee_ACCxyzw(_, _, _, _) => {}
-            // ValuedOperand::r5900ee_vfsxyzw(_, _, _, _, _) => {}
-            // ValuedOperand::r5900ee_vftxyzw(_, _, _, _, _) => {}
-            // ValuedOperand::r5900ee_vfdxyzw(_, _, _, _, _) => {}
-            // ValuedOperand::r5900ee_vftn(_, _) => {}
-            // ValuedOperand::r5900ee_vfsl(_, _) => {}
-            // ValuedOperand::r5900ee_vftm(_, _) => {}
-            // ValuedOperand::r5900ee_vis_predecr(_, _) => {}
-            // ValuedOperand::r5900ee_vit_predecr(_, _) => {}
-            // ValuedOperand::r5900ee_vis_postincr(_, _) => {}
-            // ValuedOperand::r5900ee_vit_postincr(_, _) => {}
-            // ValuedOperand::r5900ee_vis_parenthesis(_) => {}
+
+            // Everything else (e.g. the Allegrex prefix swizzles) describes what the instruction
+            // does, so it's hashed
             _ => vo.hash(hasher),
         }
     }
 }
 
-fn hash_ppc_args(insn: powerpc::Ins, hasher: &mut DefaultHasher, hashed_reloc: bool) {
+/// Hash an `offset(base)` operand. The offset is left out under a relocation, and the base
+/// register (always in the rs field) is renamed when register allocation is being ignored.
+fn hash_mips_offset_base<T: Hash, O: Hash, B: Hash>(
+    operand: T,
+    offset: O,
+    base: B,
+    rs: u32,
+    hashed_reloc: bool,
+    regs: Option<&mut RegisterRenamer>,
+    hasher: &mut StableHasher,
+) {
+    match regs {
+        Some(regs) => {
+            if !hashed_reloc {
+                offset.hash(hasher);
+            }
+            regs.rename(RegisterKind::Gpr, rs).hash(hasher);
+        }
+        None if hashed_reloc => base.hash(hasher),
+        None => operand.hash(hasher),
+    }
+}
+
+fn hash_ppc_args(
+    insn: powerpc::Ins,
+    hasher: &mut StableHasher,
+    hashed_reloc: bool,
+    mut regs: Option<&mut RegisterRenamer>,
+) {
     // hash opcode
     insn.op.hash(hasher);
 
     // hash operands
     for a in insn.basic().args {
         match a {
+            powerpc::Argument::GPR(powerpc::GPR(reg)) => hash_register(
+                a,
+                RegisterKind::Gpr,
+                reg as u32,
+                regs.as_deref_mut(),
+                hasher,
+            ),
+            powerpc::Argument::FPR(powerpc::FPR(reg)) => hash_register(
+                a,
+                RegisterKind::Fpr,
+                reg as u32,
+                regs.as_deref_mut(),
+                hasher,
+            ),
             powerpc::Argument::None => {}
             powerpc::Argument::Simm(_)
             | powerpc::Argument::Uimm(_)
@@ -396,7 +2348,541 @@ fn hash_ppc_args(insn: powerpc::Ins, hasher: &mut DefaultHasher, hashed_reloc: b
     }
 }
 
-fn hash_thumb_args(insn: unarm::Ins, hasher: &mut DefaultHasher, _hashed_reloc: bool) {
+/// Get the address of the literal loaded by `ldr rd, [pc, #imm]`
+fn get_thumb_literal(code: u32, address: u64) -> Option<(u64, u8)> {
+    if code & 0xF800 == 0x4800 {
+        let imm = (code & 0xFF) as u64 * 4;
+        Some((((address + 4) & !3) + imm, 4))
+    } else {
+        None
+    }
+}
+
+/// Whether the Thumb instruction computes an address relative to the PC (`add rd, pc, #imm`)
+fn is_thumb_pc_relative(code: u32) -> bool {
+    code & 0xF800 == 0x4800 || code & 0xF800 == 0xA000
+}
+
+/// Get the bits of a Thumb instruction that hold an immediate or offset which a relocation can
+/// patch. 32-bit BL/BLX pairs have the first half in the low 16 bits.
+fn thumb_variable_field_mask(code: u32) -> u32 {
+    let first = code & 0xFFFF;
+    if code > 0xFFFF {
+        // BL, BLX pair
+        0x07FF_07FF
+    } else if first & 0xF800 == 0xE000 {
+        // B
+        0x07FF
+    } else if first & 0xF000 == 0xD000 {
+        // B<cond>, SWI
+        0x00FF
+    } else if first & 0xE000 == 0x2000
+        || first & 0xF000 == 0x9000
+        || first & 0xF000 == 0xA000
+        || first & 0xF800 == 0x4800
+    {
+        // MOV/CMP/ADD/SUB (immediate), SP-relative load/store, ADD rd, pc/sp, LDR (literal)
+        0x00FF
+    } else if first & 0xE000 == 0x6000 || first & 0xF000 == 0x8000 {
+        // LDR/STR(B/H) (immediate offset)
+        0x07C0
+    } else if first & 0xFF00 == 0xB000 {
+        // ADD/SUB sp, #imm
+        0x007F
+    } else {
+        0
+    }
+}
+
+fn hash_thumb_args(insn: unarm::Ins, code: u32, hasher: &mut StableHasher, hashed_reloc: bool) {
+    // hash opcode
+    insn.discriminant().hash(hasher);
+
+    // Registers and condition codes are always hashed. Literal pool offsets depend on where the
+    // pool ended up, so they're never hashed, and other immediates/offsets are hashed unless
+    // they're relocated.
+    let mask = if hashed_reloc || is_thumb_pc_relative(code) {
+        thumb_variable_field_mask(code)
+    } else {
+        0
+    };
+    (code & !mask).hash(hasher);
+}
+
+/// Get the address of the literal loaded by `ldr rd, [pc, #+/-imm]`
+fn get_arm_literal(code: u32, address: u64) -> Option<(u64, u8)> {
+    if code & 0x0E5F_0000 == 0x041F_0000 {
+        let imm = (code & 0xFFF) as u64;
+        let base = address + 8;
+        let literal_address = if code & (1 << 23) != 0 {
+            base + imm
+        } else {
+            base.checked_sub(imm)?
+        };
+        Some((literal_address, 4))
+    } else {
+        None
+    }
+}
+
+/// Whether the ARM instruction computes an address relative to the PC (a literal load, or
+/// `add/sub rd, pc, #imm`)
+fn is_arm_pc_relative(code: u32) -> bool {
+    code & 0x0E5F_0000 == 0x041F_0000
+        || (code & 0x0FEF_0000 == 0x028F_0000 || code & 0x0FEF_0000 == 0x024F_0000)
+}
+
+/// Get the bits of an ARM instruction that hold an immediate or offset which a relocation can
+/// patch
+fn arm_variable_field_mask(code: u32) -> u32 {
+    if code & 0x0E00_0000 == 0x0A00_0000 {
+        // B, BL, BLX (immediate)
+        0x00FF_FFFF
+    } else if code & 0x0FB0_0000 == 0x0300_0000 {
+        // MOVW, MOVT
+        0x000F_0FFF
+    } else if code & 0x0E00_0000 == 0x0200_0000 || code & 0x0E00_0000 == 0x0400_0000 {
+        // data processing (immediate), LDR/STR(B) (immediate offset)
+        0x0000_0FFF
+    } else if code & 0x0E40_0090 == 0x0040_0090 {
+        // LDRH/STRH/LDRSB/LDRSH/LDRD/STRD (immediate offset)
+        0x0000_0F0F
+    } else if code & 0x0E00_0000 == 0x0C00_0000 {
+        // coprocessor load/store, e.g. VLDR
+        0x0000_00FF
+    } else if code & 0x0F00_0000 == 0x0F00_0000 {
+        // SWI
+        0x00FF_FFFF
+    } else {
+        0
+    }
+}
+
+fn hash_arm_args(insn: unarm::Ins, code: u32, hasher: &mut StableHasher, hashed_reloc: bool) {
     // hash opcode
     insn.discriminant().hash(hasher);
+
+    // Registers, shifts and condition codes are always hashed. Literal pool offsets depend on
+    // where the pool ended up, so they're never hashed, and other immediates/offsets are hashed
+    // unless they're relocated.
+    let mask = if hashed_reloc || is_arm_pc_relative(code) {
+        arm_variable_field_mask(code)
+    } else {
+        0
+    };
+    (code & !mask).hash(hasher);
+}
+
+/// Get the bits of an AArch64 instruction that hold an address or offset which a relocation can
+/// patch. This covers branches as well as the ADRP/ADD/LDR page + page-offset sequences.
+fn aarch64_reloc_field_mask(word: u32) -> u32 {
+    if word & 0x7C00_0000 == 0x1400_0000 {
+        // B, BL
+        0x03FF_FFFF
+    } else if word & 0xFF00_0010 == 0x5400_0000 || word & 0x7E00_0000 == 0x3400_0000 {
+        // B.cond, CBZ, CBNZ
+        0x00FF_FFE0
+    } else if word & 0x7E00_0000 == 0x3600_0000 {
+        // TBZ, TBNZ
+        0x0007_FFE0
+    } else if word & 0x1F00_0000 == 0x1000_0000 {
+        // ADR, ADRP
+        0x60FF_FFE0
+    } else if word & 0x1F00_0000 == 0x1100_0000 {
+        // ADD, SUB (immediate), including the optional 12-bit shift
+        0x007F_FC00
+    } else if word & 0x3B00_0000 == 0x3900_0000 {
+        // LDR, STR (unsigned immediate)
+        0x003F_FC00
+    } else if word & 0x3B00_0000 == 0x1800_0000 {
+        // LDR (literal)
+        0x00FF_FFE0
+    } else if word & 0x1F80_0000 == 0x1280_0000 {
+        // MOVN, MOVZ, MOVK
+        0x001F_FFE0
+    } else {
+        0
+    }
+}
+
+fn hash_aarch64_args(insn: u32, hasher: &mut StableHasher, hashed_reloc: bool) {
+    // AArch64 encodings are fixed-width with registers in fixed fields, so hashing the word
+    // covers the opcode and its operands. Only the relocated field is masked out.
+    if hashed_reloc {
+        (insn & !aarch64_reloc_field_mask(insn)).hash(hasher);
+    } else {
+        insn.hash(hasher);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SuperHFormat {
+    /// no operands
+    Zero,
+    /// Rn (or Rm) in bits 8-11
+    N,
+    /// Rn in bits 8-11, Rm in bits 4-7
+    Nm,
+    /// Rm in bits 4-7, 4-bit displacement
+    Md,
+    /// Rn in bits 4-7, 4-bit displacement
+    Nd4,
+    /// Rn in bits 8-11, Rm in bits 4-7, 4-bit displacement
+    Nmd,
+    /// 8-bit displacement
+    D,
+    /// 12-bit displacement
+    D12,
+    /// Rn in bits 8-11, 8-bit displacement
+    Nd8,
+    /// 8-bit immediate
+    I,
+    /// Rn in bits 8-11, 8-bit immediate
+    Ni,
+}
+
+impl SuperHFormat {
+    /// The bits of the instruction holding a displacement or immediate
+    fn variable_field_mask(&self) -> u16 {
+        match self {
+            SuperHFormat::Zero | SuperHFormat::N | SuperHFormat::Nm => 0,
+            SuperHFormat::Md | SuperHFormat::Nd4 | SuperHFormat::Nmd => 0x000F,
+            SuperHFormat::D | SuperHFormat::Nd8 | SuperHFormat::I | SuperHFormat::Ni => 0x00FF,
+            SuperHFormat::D12 => 0x0FFF,
+        }
+    }
+}
+
+/// SH-2 and SH-4 instruction encodings as (opcode, mask, value, format), ordered from most to
+/// least specific mask. Opcodes end up in the database, so an entry's opcode must never change
+/// and a new entry takes the next unused one, wherever in the table it goes.
+const SUPERH_INSNS: &[(u16, u16, u16, SuperHFormat)] = &[
+    // Zero format
+    (0, 0xFFFF, 0x0008, SuperHFormat::Zero),  // clrt
+    (1, 0xFFFF, 0x0028, SuperHFormat::Zero),  // clrmac
+    (2, 0xFFFF, 0x0019, SuperHFormat::Zero),  // div0u
+    (3, 0xFFFF, 0x0009, SuperHFormat::Zero),  // nop
+    (4, 0xFFFF, 0x002B, SuperHFormat::Zero),  // rte
+    (5, 0xFFFF, 0x000B, SuperHFormat::Zero),  // rts
+    (6, 0xFFFF, 0x0018, SuperHFormat::Zero),  // sett
+    (7, 0xFFFF, 0x001B, SuperHFormat::Zero),  // sleep
+    (8, 0xFFFF, 0x0048, SuperHFormat::Zero),  // clrs
+    (9, 0xFFFF, 0x0058, SuperHFormat::Zero),  // sets
+    (10, 0xFFFF, 0x0038, SuperHFormat::Zero), // ldtlb
+    (11, 0xFFFF, 0xF3FD, SuperHFormat::Zero), // fschg
+    (12, 0xFFFF, 0xFBFD, SuperHFormat::Zero), // frchg
+    (13, 0xF3FF, 0xF1FD, SuperHFormat::N),    // ftrv xmtrx, fvn
+    (14, 0xF1FF, 0xF0FD, SuperHFormat::N),    // fsca fpul, drn
+    // N format
+    (15, 0xF0FF, 0x4015, SuperHFormat::N), // cmp/pl
+    (16, 0xF0FF, 0x4011, SuperHFormat::N), // cmp/pz
+    (17, 0xF0FF, 0x4010, SuperHFormat::N), // dt
+    (18, 0xF0FF, 0x0029, SuperHFormat::N), // movt
+    (19, 0xF0FF, 0x4004, SuperHFormat::N), // rotl
+    (20, 0xF0FF, 0x4005, SuperHFormat::N), // rotr
+    (21, 0xF0FF, 0x4024, SuperHFormat::N), // rotcl
+    (22, 0xF0FF, 0x4025, SuperHFormat::N), // rotcr
+    (23, 0xF0FF, 0x4020, SuperHFormat::N), // shal
+    (24, 0xF0FF, 0x4021, SuperHFormat::N), // shar
+    (25, 0xF0FF, 0x4000, SuperHFormat::N), // shll
+    (26, 0xF0FF, 0x4001, SuperHFormat::N), // shlr
+    (27, 0xF0FF, 0x4008, SuperHFormat::N), // shll2
+    (28, 0xF0FF, 0x4009, SuperHFormat::N), // shlr2
+    (29, 0xF0FF, 0x4018, SuperHFormat::N), // shll8
+    (30, 0xF0FF, 0x4019, SuperHFormat::N), // shlr8
+    (31, 0xF0FF, 0x4028, SuperHFormat::N), // shll16
+    (32, 0xF0FF, 0x4029, SuperHFormat::N), // shlr16
+    (33, 0xF0FF, 0x0002, SuperHFormat::N), // stc sr, rn
+    (34, 0xF0FF, 0x0012, SuperHFormat::N), // stc gbr, rn
+    (35, 0xF0FF, 0x0022, SuperHFormat::N), // stc vbr, rn
+    (36, 0xF0FF, 0x000A, SuperHFormat::N), // sts mach, rn
+    (37, 0xF0FF, 0x001A, SuperHFormat::N), // sts macl, rn
+    (38, 0xF0FF, 0x002A, SuperHFormat::N), // sts pr, rn
+    (39, 0xF0FF, 0x4003, SuperHFormat::N), // stc.l sr, @-rn
+    (40, 0xF0FF, 0x4013, SuperHFormat::N), // stc.l gbr, @-rn
+    (41, 0xF0FF, 0x4023, SuperHFormat::N), // stc.l vbr, @-rn
+    (42, 0xF0FF, 0x4002, SuperHFormat::N), // sts.l mach, @-rn
+    (43, 0xF0FF, 0x4012, SuperHFormat::N), // sts.l macl, @-rn
+    (44, 0xF0FF, 0x4022, SuperHFormat::N), // sts.l pr, @-rn
+    (45, 0xF0FF, 0x401B, SuperHFormat::N), // tas.b @rn
+    (46, 0xF0FF, 0x402B, SuperHFormat::N), // jmp @rm
+    (47, 0xF0FF, 0x400B, SuperHFormat::N), // jsr @rm
+    (48, 0xF0FF, 0x0023, SuperHFormat::N), // braf rm
+    (49, 0xF0FF, 0x0003, SuperHFormat::N), // bsrf rm
+    (50, 0xF0FF, 0x400E, SuperHFormat::N), // ldc rm, sr
+    (51, 0xF0FF, 0x401E, SuperHFormat::N), // ldc rm, gbr
+    (52, 0xF0FF, 0x402E, SuperHFormat::N), // ldc rm, vbr
+    (53, 0xF0FF, 0x4007, SuperHFormat::N), // ldc.l @rm+, sr
+    (54, 0xF0FF, 0x4017, SuperHFormat::N), // ldc.l @rm+, gbr
+    (55, 0xF0FF, 0x4027, SuperHFormat::N), // ldc.l @rm+, vbr
+    (56, 0xF0FF, 0x400A, SuperHFormat::N), // lds rm, mach
+    (57, 0xF0FF, 0x401A, SuperHFormat::N), // lds rm, macl
+    (58, 0xF0FF, 0x402A, SuperHFormat::N), // lds rm, pr
+    (59, 0xF0FF, 0x4006, SuperHFormat::N), // lds.l @rm+, mach
+    (60, 0xF0FF, 0x4016, SuperHFormat::N), // lds.l @rm+, macl
+    (61, 0xF0FF, 0x4026, SuperHFormat::N), // lds.l @rm+, pr
+    (62, 0xF0FF, 0x0083, SuperHFormat::N), // pref @rn
+    (63, 0xF0FF, 0x00C3, SuperHFormat::N), // movca.l r0, @rn
+    (64, 0xF0FF, 0x0093, SuperHFormat::N), // ocbi @rn
+    (65, 0xF0FF, 0x00A3, SuperHFormat::N), // ocbp @rn
+    (66, 0xF0FF, 0x00B3, SuperHFormat::N), // ocbwb @rn
+    (67, 0xF0FF, 0x0032, SuperHFormat::N), // stc ssr, rn
+    (68, 0xF0FF, 0x0042, SuperHFormat::N), // stc spc, rn
+    (69, 0xF0FF, 0x403E, SuperHFormat::N), // ldc rm, ssr
+    (70, 0xF0FF, 0x404E, SuperHFormat::N), // ldc rm, spc
+    (71, 0xF0FF, 0x405A, SuperHFormat::N), // lds rm, fpul
+    (72, 0xF0FF, 0x406A, SuperHFormat::N), // lds rm, fpscr
+    (73, 0xF0FF, 0x4056, SuperHFormat::N), // lds.l @rm+, fpul
+    (74, 0xF0FF, 0x4066, SuperHFormat::N), // lds.l @rm+, fpscr
+    (75, 0xF0FF, 0x005A, SuperHFormat::N), // sts fpul, rn
+    (76, 0xF0FF, 0x006A, SuperHFormat::N), // sts fpscr, rn
+    (77, 0xF0FF, 0x4052, SuperHFormat::N), // sts.l fpul, @-rn
+    (78, 0xF0FF, 0x4062, SuperHFormat::N), // sts.l fpscr, @-rn
+    (79, 0xF0FF, 0xF08D, SuperHFormat::N), // fldi0 frn
+    (80, 0xF0FF, 0xF09D, SuperHFormat::N), // fldi1 frn
+    (81, 0xF0FF, 0xF01D, SuperHFormat::N), // flds frm, fpul
+    (82, 0xF0FF, 0xF00D, SuperHFormat::N), // fsts fpul, frn
+    (83, 0xF0FF, 0xF05D, SuperHFormat::N), // fabs frn
+    (84, 0xF0FF, 0xF04D, SuperHFormat::N), // fneg frn
+    (85, 0xF0FF, 0xF06D, SuperHFormat::N), // fsqrt frn
+    (86, 0xF0FF, 0xF02D, SuperHFormat::N), // float fpul, frn
+    (87, 0xF0FF, 0xF03D, SuperHFormat::N), // ftrc frm, fpul
+    (88, 0xF0FF, 0xF0BD, SuperHFormat::N), // fcnvds drm, fpul
+    (89, 0xF0FF, 0xF0AD, SuperHFormat::N), // fcnvsd fpul, drn
+    (90, 0xF0FF, 0xF07D, SuperHFormat::N), // fsrra frn
+    (91, 0xF0FF, 0xF0ED, SuperHFormat::N), // fipr fvm, fvn
+    // NM format
+    (92, 0xF00F, 0x300C, SuperHFormat::Nm),  // add
+    (93, 0xF00F, 0x300E, SuperHFormat::Nm),  // addc
+    (94, 0xF00F, 0x300F, SuperHFormat::Nm),  // addv
+    (95, 0xF00F, 0x2009, SuperHFormat::Nm),  // and
+    (96, 0xF00F, 0x3000, SuperHFormat::Nm),  // cmp/eq
+    (97, 0xF00F, 0x3002, SuperHFormat::Nm),  // cmp/hs
+    (98, 0xF00F, 0x3003, SuperHFormat::Nm),  // cmp/ge
+    (99, 0xF00F, 0x3006, SuperHFormat::Nm),  // cmp/hi
+    (100, 0xF00F, 0x3007, SuperHFormat::Nm), // cmp/gt
+    (101, 0xF00F, 0x200C, SuperHFormat::Nm), // cmp/str
+    (102, 0xF00F, 0x3004, SuperHFormat::Nm), // div1
+    (103, 0xF00F, 0x2007, SuperHFormat::Nm), // div0s
+    (104, 0xF00F, 0x300D, SuperHFormat::Nm), // dmuls.l
+    (105, 0xF00F, 0x3005, SuperHFormat::Nm), // dmulu.l
+    (106, 0xF00F, 0x600E, SuperHFormat::Nm), // exts.b
+    (107, 0xF00F, 0x600F, SuperHFormat::Nm), // exts.w
+    (108, 0xF00F, 0x600C, SuperHFormat::Nm), // extu.b
+    (109, 0xF00F, 0x600D, SuperHFormat::Nm), // extu.w
+    (110, 0xF00F, 0x000F, SuperHFormat::Nm), // mac.l
+    (111, 0xF00F, 0x400F, SuperHFormat::Nm), // mac.w
+    (112, 0xF00F, 0x6003, SuperHFormat::Nm), // mov rm, rn
+    (113, 0xF00F, 0x2000, SuperHFormat::Nm), // mov.b rm, @rn
+    (114, 0xF00F, 0x2001, SuperHFormat::Nm), // mov.w rm, @rn
+    (115, 0xF00F, 0x2002, SuperHFormat::Nm), // mov.l rm, @rn
+    (116, 0xF00F, 0x6000, SuperHFormat::Nm), // mov.b @rm, rn
+    (117, 0xF00F, 0x6001, SuperHFormat::Nm), // mov.w @rm, rn
+    (118, 0xF00F, 0x6002, SuperHFormat::Nm), // mov.l @rm, rn
+    (119, 0xF00F, 0x2004, SuperHFormat::Nm), // mov.b rm, @-rn
+    (120, 0xF00F, 0x2005, SuperHFormat::Nm), // mov.w rm, @-rn
+    (121, 0xF00F, 0x2006, SuperHFormat::Nm), // mov.l rm, @-rn
+    (122, 0xF00F, 0x6004, SuperHFormat::Nm), // mov.b @rm+, rn
+    (123, 0xF00F, 0x6005, SuperHFormat::Nm), // mov.w @rm+, rn
+    (124, 0xF00F, 0x6006, SuperHFormat::Nm), // mov.l @rm+, rn
+    (125, 0xF00F, 0x0004, SuperHFormat::Nm), // mov.b rm, @(r0, rn)
+    (126, 0xF00F, 0x0005, SuperHFormat::Nm), // mov.w rm, @(r0, rn)
+    (127, 0xF00F, 0x0006, SuperHFormat::Nm), // mov.l rm, @(r0, rn)
+    (128, 0xF00F, 0x000C, SuperHFormat::Nm), // mov.b @(r0, rm), rn
+    (129, 0xF00F, 0x000D, SuperHFormat::Nm), // mov.w @(r0, rm), rn
+    (130, 0xF00F, 0x000E, SuperHFormat::Nm), // mov.l @(r0, rm), rn
+    (131, 0xF00F, 0x0007, SuperHFormat::Nm), // mul.l
+    (132, 0xF00F, 0x200F, SuperHFormat::Nm), // muls.w
+    (133, 0xF00F, 0x200E, SuperHFormat::Nm), // mulu.w
+    (134, 0xF00F, 0x600B, SuperHFormat::Nm), // neg
+    (135, 0xF00F, 0x600A, SuperHFormat::Nm), // negc
+    (136, 0xF00F, 0x6007, SuperHFormat::Nm), // not
+    (137, 0xF00F, 0x200B, SuperHFormat::Nm), // or
+    (138, 0xF00F, 0x400C, SuperHFormat::Nm), // shad
+    (139, 0xF00F, 0x400D, SuperHFormat::Nm), // shld
+    (140, 0xF00F, 0x3008, SuperHFormat::Nm), // sub
+    (141, 0xF00F, 0x300A, SuperHFormat::Nm), // subc
+    (142, 0xF00F, 0x300B, SuperHFormat::Nm), // subv
+    (143, 0xF00F, 0x6008, SuperHFormat::Nm), // swap.b
+    (144, 0xF00F, 0x6009, SuperHFormat::Nm), // swap.w
+    (145, 0xF00F, 0x2008, SuperHFormat::Nm), // tst
+    (146, 0xF00F, 0x200A, SuperHFormat::Nm), // xor
+    (147, 0xF00F, 0x200D, SuperHFormat::Nm), // xtrct
+    (148, 0xF00F, 0xF000, SuperHFormat::Nm), // fadd
+    (149, 0xF00F, 0xF001, SuperHFormat::Nm), // fsub
+    (150, 0xF00F, 0xF002, SuperHFormat::Nm), // fmul
+    (151, 0xF00F, 0xF003, SuperHFormat::Nm), // fdiv
+    (152, 0xF00F, 0xF004, SuperHFormat::Nm), // fcmp/eq
+    (153, 0xF00F, 0xF005, SuperHFormat::Nm), // fcmp/gt
+    (154, 0xF00F, 0xF006, SuperHFormat::Nm), // fmov.s @(r0, rm), frn
+    (155, 0xF00F, 0xF007, SuperHFormat::Nm), // fmov.s frm, @(r0, rn)
+    (156, 0xF00F, 0xF008, SuperHFormat::Nm), // fmov.s @rm, frn
+    (157, 0xF00F, 0xF009, SuperHFormat::Nm), // fmov.s @rm+, frn
+    (158, 0xF00F, 0xF00A, SuperHFormat::Nm), // fmov.s frm, @rn
+    (159, 0xF00F, 0xF00B, SuperHFormat::Nm), // fmov.s frm, @-rn
+    (160, 0xF00F, 0xF00C, SuperHFormat::Nm), // fmov frm, frn
+    (161, 0xF00F, 0xF00E, SuperHFormat::Nm), // fmac fr0, frm, frn
+    // 8-bit fields
+    (162, 0xFF00, 0x8400, SuperHFormat::Md), // mov.b @(disp, rm), r0
+    (163, 0xFF00, 0x8500, SuperHFormat::Md), // mov.w @(disp, rm), r0
+    (164, 0xFF00, 0x8000, SuperHFormat::Nd4), // mov.b r0, @(disp, rn)
+    (165, 0xFF00, 0x8100, SuperHFormat::Nd4), // mov.w r0, @(disp, rn)
+    (166, 0xFF00, 0x8B00, SuperHFormat::D),  // bf
+    (167, 0xFF00, 0x8F00, SuperHFormat::D),  // bf/s
+    (168, 0xFF00, 0x8900, SuperHFormat::D),  // bt
+    (169, 0xFF00, 0x8D00, SuperHFormat::D),  // bt/s
+    (170, 0xFF00, 0xC000, SuperHFormat::D),  // mov.b r0, @(disp, gbr)
+    (171, 0xFF00, 0xC100, SuperHFormat::D),  // mov.w r0, @(disp, gbr)
+    (172, 0xFF00, 0xC200, SuperHFormat::D),  // mov.l r0, @(disp, gbr)
+    (173, 0xFF00, 0xC400, SuperHFormat::D),  // mov.b @(disp, gbr), r0
+    (174, 0xFF00, 0xC500, SuperHFormat::D),  // mov.w @(disp, gbr), r0
+    (175, 0xFF00, 0xC600, SuperHFormat::D),  // mov.l @(disp, gbr), r0
+    (176, 0xFF00, 0xC700, SuperHFormat::D),  // mova @(disp, pc), r0
+    (177, 0xFF00, 0x8800, SuperHFormat::I),  // cmp/eq #imm, r0
+    (178, 0xFF00, 0xC900, SuperHFormat::I),  // and #imm, r0
+    (179, 0xFF00, 0xCD00, SuperHFormat::I),  // and.b #imm, @(r0, gbr)
+    (180, 0xFF00, 0xCB00, SuperHFormat::I),  // or #imm, r0
+    (181, 0xFF00, 0xCF00, SuperHFormat::I),  // or.b #imm, @(r0, gbr)
+    (182, 0xFF00, 0xC800, SuperHFormat::I),  // tst #imm, r0
+    (183, 0xFF00, 0xCC00, SuperHFormat::I),  // tst.b #imm, @(r0, gbr)
+    (184, 0xFF00, 0xCA00, SuperHFormat::I),  // xor #imm, r0
+    (185, 0xFF00, 0xCE00, SuperHFormat::I),  // xor.b #imm, @(r0, gbr)
+    (186, 0xFF00, 0xC300, SuperHFormat::I),  // trapa #imm
+    // 12-bit fields
+    (187, 0xF000, 0x1000, SuperHFormat::Nmd), // mov.l rm, @(disp, rn)
+    (188, 0xF000, 0x5000, SuperHFormat::Nmd), // mov.l @(disp, rm), rn
+    (189, 0xF000, 0xA000, SuperHFormat::D12), // bra
+    (190, 0xF000, 0xB000, SuperHFormat::D12), // bsr
+    (191, 0xF000, 0x9000, SuperHFormat::Nd8), // mov.w @(disp, pc), rn
+    (192, 0xF000, 0xD000, SuperHFormat::Nd8), // mov.l @(disp, pc), rn
+    (193, 0xF000, 0xE000, SuperHFormat::Ni),  // mov #imm, rn
+    (194, 0xF000, 0x7000, SuperHFormat::Ni),  // add #imm, rn
+];
+
+fn lookup_superh_insn(insn: u16) -> Option<(u16, SuperHFormat)> {
+    SUPERH_INSNS
+        .iter()
+        .find(|(_, mask, value, _)| insn & mask == *value)
+        .map(|(opcode, _, _, format)| (*opcode, *format))
+}
+
+fn get_superh_opcode(insn: u16) -> u16 {
+    lookup_superh_insn(insn).map_or(u16::MAX, |(opcode, _)| opcode)
+}
+
+/// Whether the instruction's displacement is relative to the PC
+fn is_superh_pc_relative(insn: u16) -> bool {
+    matches!(insn & 0xF000, 0x9000 | 0xD000) || insn & 0xFF00 == 0xC700
+}
+
+/// Get the address and size of the literal loaded by mov.w/mov.l @(disp, pc)
+fn get_superh_literal(insn: u16, address: u64) -> Option<(u64, u8)> {
+    let disp = (insn & 0xFF) as u64;
+    match insn & 0xF000 {
+        0x9000 => Some((address + 4 + disp * 2, 2)),
+        0xD000 => Some(((address & !3) + 4 + disp * 4, 4)),
+        _ => None,
+    }
+}
+
+fn hash_superh_args(insn: u16, hasher: &mut StableHasher, hashed_reloc: bool) {
+    let Some((_, format)) = lookup_superh_insn(insn) else {
+        insn.hash(hasher);
+        return;
+    };
+
+    // Registers are always hashed. PC-relative displacements depend on where the literal pool
+    // ended up, so they're never hashed, and other displacements/immediates are hashed unless
+    // they're relocated.
+    let mask = if hashed_reloc || is_superh_pc_relative(insn) {
+        format.variable_field_mask()
+    } else {
+        0
+    };
+    (insn & !mask).hash(hasher);
+}
+
+/// Get where in an x86 instruction the field a recovered relocation applies to is: the
+/// displacement of an absolute memory operand, or else an immediate holding an address
+fn x86_pseudo_reloc_offset(
+    insn: &iced_x86::Instruction,
+    offsets: &ConstantOffsets,
+    pseudo_reloc: &PseudoReloc,
+) -> Option<u64> {
+    match pseudo_reloc.kind {
+        PseudoRelocKind::Absolute => {
+            if offsets.has_displacement() && insn.memory_displacement64() == pseudo_reloc.target {
+                Some(offsets.displacement_offset() as u64)
+            } else if offsets.has_immediate() {
+                Some(offsets.immediate_offset() as u64)
+            } else {
+                None
+            }
+        }
+        // a branch's displacement is always at the end of the instruction
+        _ => Some(insn.len() as u64 - 1),
+    }
+}
+
+fn hash_x86_args(
+    insn: &iced_x86::Instruction,
+    offsets: &ConstantOffsets,
+    hasher: &mut StableHasher,
+    reloc_offset: Option<u64>,
+) {
+    // hash opcode
+    (insn.code() as u32).hash(hasher);
+
+    // Only the field the relocation applies to is masked, so e.g. the immediate of
+    // `mov dword ptr [sym], 5` is still hashed
+    let displacement_relocated = reloc_offset.is_some_and(|o| {
+        offsets.has_displacement()
+            && (offsets.displacement_offset()
+                ..offsets.displacement_offset() + offsets.displacement_size())
+                .contains(&(o as usize))
+    });
+    let immediate_relocated = reloc_offset.is_some_and(|o| {
+        offsets.has_immediate()
+            && (offsets.immediate_offset()..offsets.immediate_offset() + offsets.immediate_size())
+                .contains(&(o as usize))
+    });
+
+    for i in 0..insn.op_count() {
+        match insn.op_kind(i) {
+            OpKind::Register => {
+                (insn.op_register(i) as u32).hash(hasher);
+            }
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64 => {
+                // branches within the function are hashed by their relative offset
+                if reloc_offset.is_none() {
+                    (insn.near_branch_target().wrapping_sub(insn.next_ip()) as i64).hash(hasher);
+                }
+            }
+            OpKind::Immediate8
+            | OpKind::Immediate8_2nd
+            | OpKind::Immediate16
+            | OpKind::Immediate32
+            | OpKind::Immediate64
+            | OpKind::Immediate8to16
+            | OpKind::Immediate8to32
+            | OpKind::Immediate8to64
+            | OpKind::Immediate32to64 => {
+                if !immediate_relocated {
+                    insn.immediate(i).hash(hasher);
+                }
+            }
+            OpKind::Memory => {
+                (insn.memory_segment() as u32).hash(hasher);
+                (insn.memory_base() as u32).hash(hasher);
+                (insn.memory_index() as u32).hash(hasher);
+                insn.memory_index_scale().hash(hasher);
+                if !displacement_relocated {
+                    insn.memory_displacement64().hash(hasher);
+                }
+            }
+            kind => {
+                (kind as u32).hash(hasher);
+            }
+        }
+    }
 }
@@ -0,0 +1,230 @@
+//! Call graphs and match propagation.
+//!
+//! Each symbol records what it calls, which is resolved into a call graph over a binary's
+//! symbols. When comparing two binaries, confident matches then serve as anchors: the functions
+//! that matched symbols call, and are called by, likely correspond as well, even when they're too
+//! different to match on their own.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use serde::Serialize;
+
+use crate::{Binary, SimilarityMetric, SimilarityReport, Symbol, diff_symbols};
+
+/// Neighbourhoods larger than this (e.g. the callers of a common helper) are too ambiguous to
+/// propagate matches through, and too costly to compare pairwise
+const MAX_NEIGHBORS: usize = 64;
+
+/// What a call goes to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CallTarget {
+    /// A symbol, by name
+    Symbol(String),
+    /// An address
+    Address(u64),
+}
+
+/// Who calls whom among the symbols of a binary, by their index in it
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    callees: Vec<Vec<usize>>,
+    callers: Vec<Vec<usize>>,
+}
+
+impl CallGraph {
+    pub fn new(symbols: &[Symbol]) -> Self {
+        let mut by_name: HashMap<&str, usize> = HashMap::new();
+        let mut by_address: HashMap<u64, usize> = HashMap::new();
+        for (i, symbol) in symbols.iter().enumerate() {
+            by_name.entry(&symbol.name).or_insert(i);
+            by_address.entry(symbol.vram as u64).or_insert(i);
+        }
+
+        let mut callees = vec![Vec::new(); symbols.len()];
+        let mut callers = vec![Vec::new(); symbols.len()];
+
+        for (i, symbol) in symbols.iter().enumerate() {
+            for call in &symbol.calls {
+                let callee = match call {
+                    CallTarget::Symbol(name) => by_name.get(name.as_str()),
+                    CallTarget::Address(address) => by_address.get(address),
+                };
+
+                if let Some(&callee) = callee
+                    && !callees[i].contains(&callee)
+                {
+                    callees[i].push(callee);
+                    callers[callee].push(i);
+                }
+            }
+        }
+
+        Self { callees, callers }
+    }
+
+    /// The symbols a symbol calls, in the order they're first called
+    pub fn callees(&self, symbol: usize) -> &[usize] {
+        &self.callees[symbol]
+    }
+
+    /// The symbols that call a symbol
+    pub fn callers(&self, symbol: usize) -> &[usize] {
+        &self.callers[symbol]
+    }
+}
+
+impl Binary {
+    pub fn call_graph(&self) -> CallGraph {
+        CallGraph::new(&self.symbols)
+    }
+}
+
+/// A match found by propagating from another one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct PropagatedMatch {
+    /// Index of the symbol in the first binary
+    pub symbol1: usize,
+    /// Index of the symbol in the second binary
+    pub symbol2: usize,
+    pub report: SimilarityReport,
+    /// The matched pair of symbols this one was found from
+    pub from: (usize, usize),
+}
+
+fn compare_candidates(
+    a: &SimilarityReport,
+    b: &SimilarityReport,
+    metric: SimilarityMetric,
+) -> Ordering {
+    let key = |r: &SimilarityReport| [r.score(metric), r.equivalent, r.structural];
+    key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal)
+}
+
+/// The best of `candidates`, unless several are equally good
+fn unique_best<'a>(
+    candidates: impl Iterator<Item = (usize, &'a SimilarityReport)>,
+    metric: SimilarityMetric,
+) -> Option<usize> {
+    let mut best: Option<(usize, &SimilarityReport)> = None;
+    let mut tied = false;
+
+    for (idx, report) in candidates {
+        match best.map(|(_, b)| compare_candidates(report, b, metric)) {
+            None | Some(Ordering::Greater) => {
+                best = Some((idx, report));
+                tied = false;
+            }
+            Some(Ordering::Equal) => tied = true,
+            Some(Ordering::Less) => {}
+        }
+    }
+
+    best.filter(|_| !tied).map(|(idx, _)| idx)
+}
+
+/// Pair up two neighbourhoods of symbols, keeping pairs that are each other's unique best
+/// candidate
+fn pair_neighbors(
+    bin1: &Binary,
+    neighbors1: &[usize],
+    bin2: &Binary,
+    neighbors2: &[usize],
+    metric: SimilarityMetric,
+    threshold: f32,
+) -> Vec<(usize, usize, SimilarityReport)> {
+    if neighbors1.len() > MAX_NEIGHBORS || neighbors2.len() > MAX_NEIGHBORS {
+        return Vec::new();
+    }
+
+    let mut candidates: Vec<(usize, usize, SimilarityReport)> = Vec::new();
+    for &i in neighbors1 {
+        for &j in neighbors2 {
+            if let Some(report) =
+                diff_symbols(&bin1.symbols[i], &bin2.symbols[j], metric, threshold)
+                && report.structural > threshold
+            {
+                candidates.push((i, j, report));
+            }
+        }
+    }
+
+    candidates
+        .iter()
+        .filter(|(i, j, _)| {
+            let best_for_i = unique_best(
+                candidates
+                    .iter()
+                    .filter(|c| c.0 == *i)
+                    .map(|(_, j, r)| (*j, r)),
+                metric,
+            );
+            let best_for_j = unique_best(
+                candidates
+                    .iter()
+                    .filter(|c| c.1 == *j)
+                    .map(|(i, _, r)| (*i, r)),
+                metric,
+            );
+            best_for_i == Some(*j) && best_for_j == Some(*i)
+        })
+        .copied()
+        .collect()
+}
+
+/// Find more matches between two binaries by propagating from `anchors`, pairs of symbols
+/// (by index) already known to match. The unmatched callees and callers of each matched pair are
+/// compared with one another, and those that are each other's unique best candidate are matched
+/// if they score above `threshold` both for `metric` and structurally. This is meant to be a
+/// lower threshold than the anchors had to meet, as the call graph backs the match up. New matches
+/// are propagated from in turn.
+pub fn propagate_matches(
+    bin1: &Binary,
+    graph1: &CallGraph,
+    bin2: &Binary,
+    graph2: &CallGraph,
+    anchors: &[(usize, usize)],
+    metric: SimilarityMetric,
+    threshold: f32,
+) -> Vec<PropagatedMatch> {
+    let mut matched1: HashSet<usize> = anchors.iter().map(|(i, _)| *i).collect();
+    let mut matched2: HashSet<usize> = anchors.iter().map(|(_, j)| *j).collect();
+    let mut queue: VecDeque<(usize, usize)> = anchors.iter().copied().collect();
+    let mut found = Vec::new();
+
+    while let Some((a, b)) = queue.pop_front() {
+        let neighborhoods = [
+            (graph1.callees(a), graph2.callees(b)),
+            (graph1.callers(a), graph2.callers(b)),
+        ];
+
+        for (neighbors1, neighbors2) in neighborhoods {
+            let neighbors1: Vec<usize> = neighbors1
+                .iter()
+                .copied()
+                .filter(|i| !matched1.contains(i))
+                .collect();
+            let neighbors2: Vec<usize> = neighbors2
+                .iter()
+                .copied()
+                .filter(|j| !matched2.contains(j))
+                .collect();
+
+            for (i, j, report) in
+                pair_neighbors(bin1, &neighbors1, bin2, &neighbors2, metric, threshold)
+            {
+                matched1.insert(i);
+                matched2.insert(j);
+                queue.push_back((i, j));
+                found.push(PropagatedMatch {
+                    symbol1: i,
+                    symbol2: j,
+                    report,
+                    from: (a, b),
+                });
+            }
+        }
+    }
+
+    found
+}
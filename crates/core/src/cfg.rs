@@ -0,0 +1,280 @@
+//! Control flow graphs and structural similarity.
+//!
+//! A symbol's instructions are split into basic blocks at branches and branch targets. Two
+//! symbols are then compared by the shape of their graphs and by the opcodes in each block,
+//! regardless of the order the blocks were laid out in, which catches code the compiler
+//! reordered.
+
+use std::collections::{BTreeSet, HashMap};
+
+use serde::Serialize;
+
+use crate::Symbol;
+
+/// Where execution can go after an instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InsnFlow {
+    /// Execution continues with the next instruction
+    Next,
+    /// A call to a function, at the given address if it's known, after which execution continues
+    /// with the next instruction
+    Call(Option<u64>),
+    /// A branch that's always taken
+    Jump(u64),
+    /// A branch that may be taken, or fall through to the next instruction
+    Branch(u64),
+    /// Execution leaves the function or goes somewhere unknown (returns, indirect jumps)
+    Exit,
+}
+
+impl InsnFlow {
+    fn ends_block(self) -> bool {
+        !matches!(self, InsnFlow::Next | InsnFlow::Call(_))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CfgInsn {
+    pub address: u64,
+    pub flow: InsnFlow,
+    /// Whether the instruction after this one executes before the branch is taken
+    pub delay_slot: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BasicBlock {
+    /// Index of the block's first instruction
+    pub start: usize,
+    /// Index of the instruction after the block's last one
+    pub end: usize,
+    /// Indices of the blocks execution can continue with
+    pub successors: Vec<usize>,
+}
+
+impl BasicBlock {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Cfg {
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl Cfg {
+    pub(crate) fn build(insns: &[CfgInsn]) -> Self {
+        let n = insns.len();
+        if n == 0 {
+            return Self::default();
+        }
+
+        // Instructions are in address order, so targets can be looked up by address
+        let index_of = |address: u64| insns.binary_search_by_key(&address, |i| i.address).ok();
+
+        // The instruction after a block's last one, for each instruction that ends a block
+        let block_end = |i: usize| (i + 1 + usize::from(insns[i].delay_slot)).min(n);
+
+        let mut leaders = BTreeSet::from([0]);
+        for (i, insn) in insns.iter().enumerate() {
+            if !insn.flow.ends_block() {
+                continue;
+            }
+            leaders.insert(block_end(i));
+            if let InsnFlow::Jump(target) | InsnFlow::Branch(target) = insn.flow
+                && let Some(target) = index_of(target)
+            {
+                leaders.insert(target);
+            }
+        }
+        leaders.remove(&n);
+
+        let starts: Vec<usize> = leaders.into_iter().collect();
+        let block_of = |i: usize| starts.partition_point(|&s| s <= i) - 1;
+
+        let blocks = starts
+            .iter()
+            .enumerate()
+            .map(|(b, &start)| {
+                let end = starts.get(b + 1).copied().unwrap_or(n);
+                let fallthrough = (end < n).then(|| b + 1);
+
+                // The branch ending the block, if any, is followed only by its delay slot
+                let last = (start..end)
+                    .rev()
+                    .find(|&i| insns[i].flow.ends_block() && block_end(i) == end);
+
+                let mut successors: Vec<usize> = match last.map(|i| insns[i].flow) {
+                    None | Some(InsnFlow::Next | InsnFlow::Call(_)) => {
+                        fallthrough.into_iter().collect()
+                    }
+                    Some(InsnFlow::Exit) => vec![],
+                    Some(InsnFlow::Jump(target)) => {
+                        index_of(target).map(block_of).into_iter().collect()
+                    }
+                    Some(InsnFlow::Branch(target)) => fallthrough
+                        .into_iter()
+                        .chain(index_of(target).map(block_of))
+                        .collect(),
+                };
+                successors.dedup();
+
+                BasicBlock {
+                    start,
+                    end,
+                    successors,
+                }
+            })
+            .collect();
+
+        Self { blocks }
+    }
+
+    /// Number of edges going into each block
+    fn in_degrees(&self) -> Vec<usize> {
+        let mut degrees = vec![0; self.blocks.len()];
+        for successor in self.blocks.iter().flat_map(|b| &b.successors) {
+            degrees[*successor] += 1;
+        }
+        degrees
+    }
+}
+
+/// How many instructions two histograms have in common
+fn histogram_overlap<K: Eq + std::hash::Hash>(
+    a: &HashMap<K, usize>,
+    b: &HashMap<K, usize>,
+) -> usize {
+    a.iter()
+        .map(|(key, count)| (*count).min(b.get(key).copied().unwrap_or(0)))
+        .sum()
+}
+
+fn ratio(a: usize, b: usize) -> f32 {
+    if a.max(b) == 0 {
+        1.0
+    } else {
+        a.min(b) as f32 / a.max(b) as f32
+    }
+}
+
+fn opcode_histograms(symbol: &Symbol) -> Vec<HashMap<u16, usize>> {
+    symbol
+        .cfg
+        .blocks
+        .iter()
+        .map(|block| {
+            let mut histogram = HashMap::new();
+            for opcode in symbol
+                .opcodes
+                .get(block.start..block.end)
+                .unwrap_or_default()
+            {
+                *histogram.entry(*opcode).or_default() += 1;
+            }
+            histogram
+        })
+        .collect()
+}
+
+/// Blocks of two symbols that correspond to one another
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BlockMatch {
+    /// Index of the block in the first symbol
+    pub block1: usize,
+    /// Index of the block in the second symbol
+    pub block2: usize,
+    /// Share of the two blocks' instructions with opcodes in common, from 0 to 1
+    pub similarity: f32,
+}
+
+/// Pair up blocks greedily, those with the most opcodes in common first. Returns (block1, block2,
+/// opcodes in common) for each pair.
+fn pair_blocks(
+    histograms1: &[HashMap<u16, usize>],
+    histograms2: &[HashMap<u16, usize>],
+) -> Vec<(usize, usize, usize)> {
+    let mut candidates: Vec<(usize, usize, usize)> = Vec::new();
+    for (i, h1) in histograms1.iter().enumerate() {
+        for (j, h2) in histograms2.iter().enumerate() {
+            let overlap = histogram_overlap(h1, h2);
+            if overlap > 0 {
+                candidates.push((i, j, overlap));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(&b.0)).then(a.1.cmp(&b.1)));
+
+    let mut used1 = vec![false; histograms1.len()];
+    let mut used2 = vec![false; histograms2.len()];
+
+    let mut pairs: Vec<(usize, usize, usize)> = candidates
+        .into_iter()
+        .filter(|&(i, j, _)| {
+            let unused = !used1[i] && !used2[j];
+            if unused {
+                used1[i] = true;
+                used2[j] = true;
+            }
+            unused
+        })
+        .collect();
+    pairs.sort_by_key(|&(i, _, _)| i);
+    pairs
+}
+
+/// Pair up the blocks of two symbols by the opcodes they contain, regardless of their order
+pub fn match_blocks(sym1: &Symbol, sym2: &Symbol) -> Vec<BlockMatch> {
+    pair_blocks(&opcode_histograms(sym1), &opcode_histograms(sym2))
+        .into_iter()
+        .map(|(i, j, overlap)| BlockMatch {
+            block1: i,
+            block2: j,
+            similarity: overlap as f32
+                / sym1.cfg.blocks[i].len().max(sym2.cfg.blocks[j].len()) as f32,
+        })
+        .collect()
+}
+
+/// Compare the structure of two symbols, from 0 (nothing in common) to 1 (identical). This
+/// weighs the number of blocks, the shape of the edges between them, and the opcodes in
+/// corresponding blocks, none of which depend on the order of the blocks.
+pub fn structural_similarity(sym1: &Symbol, sym2: &Symbol) -> f32 {
+    let (cfg1, cfg2) = (&sym1.cfg, &sym2.cfg);
+
+    let block_count = ratio(cfg1.blocks.len(), cfg2.blocks.len());
+
+    // Blocks are described by how many edges go in and out of them
+    let shapes = |cfg: &Cfg| {
+        let mut shapes: HashMap<(usize, usize), usize> = HashMap::new();
+        for (block, in_degree) in cfg.blocks.iter().zip(cfg.in_degrees()) {
+            *shapes
+                .entry((in_degree, block.successors.len()))
+                .or_default() += 1;
+        }
+        shapes
+    };
+    let edge_shape = if cfg1.blocks.is_empty() && cfg2.blocks.is_empty() {
+        1.0
+    } else {
+        histogram_overlap(&shapes(cfg1), &shapes(cfg2)) as f32
+            / cfg1.blocks.len().max(cfg2.blocks.len()) as f32
+    };
+
+    let longest = sym1.opcodes.len().max(sym2.opcodes.len());
+    let block_opcodes = if longest == 0 {
+        1.0
+    } else {
+        let shared: usize = pair_blocks(&opcode_histograms(sym1), &opcode_histograms(sym2))
+            .iter()
+            .map(|(_, _, overlap)| overlap)
+            .sum();
+        shared as f32 / longest as f32
+    };
+
+    0.2 * block_count + 0.2 * edge_shape + 0.6 * block_opcodes
+}
@@ -0,0 +1,124 @@
+//! CodeWarrior (Metrowerks) linker maps, as shipped with many GameCube/Wii games.
+//!
+//! Each section's layout lists the symbols in it, one per line:
+//!
+//! ```text
+//! .text section layout
+//!   Starting        Virtual
+//!   address  Size   address
+//!   -----------------------
+//!   00000000 000140 80005940  4 OSInit 	os.a OS.o
+//! ```
+//!
+//! Later linkers add a file offset column after the virtual address. Unused symbols that were
+//! stripped have `UNUSED` as their address and no virtual address.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CwMapSymbol {
+    pub name: String,
+    /// The section the symbol is in
+    pub section: String,
+    /// Offset of the symbol from the start of its section
+    pub offset: u32,
+    pub size: u32,
+    /// Where the symbol is loaded. REL modules aren't loaded anywhere fixed and list their
+    /// symbols at 0-based addresses.
+    pub vram: u32,
+    /// The object (and library, if any) the symbol comes from
+    pub object: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CwMap {
+    pub symbols: Vec<CwMapSymbol>,
+}
+
+/// Whether a map looks like one written by the CodeWarrior linker
+pub fn is_cw_map(map_str: &str) -> bool {
+    map_str
+        .lines()
+        .any(|line| line.trim_end().ends_with(" section layout"))
+}
+
+fn parse_hex(token: &str) -> Option<u32> {
+    u32::from_str_radix(token, 16).ok()
+}
+
+/// Parse a symbol line of a section layout, skipping unused symbols and `(entry of ...)` lines,
+/// which name a point within another symbol
+fn parse_symbol(line: &str, section: &str) -> Option<CwMapSymbol> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let offset = parse_hex(tokens.first()?)?;
+    let size = parse_hex(tokens.get(1)?)?;
+    let vram = parse_hex(tokens.get(2)?)?;
+
+    // The alignment is a small decimal number, and follows the file offset if there is one
+    let has_file_offset = tokens.get(3).is_some_and(|t| t.len() == 8) && tokens.len() > 5;
+    let rest = &tokens[if has_file_offset { 4 } else { 3 }..];
+    rest.first()?.parse::<u32>().ok()?;
+
+    let name = rest.get(1)?.to_string();
+    let object = rest[2..].join(" ");
+
+    Some(CwMapSymbol {
+        name,
+        section: section.to_string(),
+        offset,
+        size,
+        vram,
+        object,
+    })
+}
+
+impl CwMap {
+    pub fn parse(map_str: &str) -> Self {
+        let mut symbols = Vec::new();
+        let mut section: Option<&str> = None;
+
+        for line in map_str.lines() {
+            if let Some(name) = line.trim_end().strip_suffix(" section layout") {
+                section = Some(name.trim());
+                continue;
+            }
+            // The memory map and linker-generated symbols follow the last section layout
+            if line.starts_with("Memory map:") || line.starts_with("Linker generated symbols:") {
+                section = None;
+                continue;
+            }
+
+            if let Some(section) = section
+                && let Some(symbol) = parse_symbol(line, section)
+            {
+                symbols.push(symbol);
+            }
+        }
+
+        Self { symbols }
+    }
+
+    /// Functions in the map's code sections, leaving out the entries that cover a whole section
+    /// of an object
+    pub fn functions(&self) -> impl Iterator<Item = &CwMapSymbol> {
+        self.symbols.iter().filter(|s| {
+            matches!(s.section.as_str(), ".init" | ".text")
+                && s.size > 0
+                && !s.name.starts_with('.')
+        })
+    }
+
+    /// Names of the map's symbols by address, given where each symbol is placed
+    pub fn names_by_address(
+        &self,
+        address: impl Fn(&CwMapSymbol) -> Option<u64>,
+    ) -> HashMap<u64, String> {
+        let mut ret = HashMap::new();
+        for symbol in self.symbols.iter().filter(|s| !s.name.starts_with('.')) {
+            if let Some(address) = address(symbol) {
+                ret.entry(address).or_insert_with(|| symbol.name.clone());
+            }
+        }
+        ret
+    }
+}
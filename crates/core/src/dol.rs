@@ -0,0 +1,96 @@
+//! GameCube/Wii DOL executables.
+//!
+//! A DOL is a fixed-size header followed by up to 7 text and 11 data sections, each loaded at an
+//! address given in the header. It's linked, so the code has no relocations left.
+
+use anyhow::{Result, anyhow};
+
+const HEADER_SIZE: usize = 0x100;
+const TEXT_SECTIONS: usize = 7;
+const DATA_SECTIONS: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DolSectionKind {
+    Text,
+    Data,
+}
+
+#[derive(Debug, Clone)]
+pub struct DolSection {
+    pub kind: DolSectionKind,
+    /// Where the section is loaded
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Dol {
+    pub sections: Vec<DolSection>,
+    pub bss_address: u32,
+    pub bss_size: u32,
+    pub entry_point: u32,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+impl Dol {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(anyhow!("DOL is smaller than its header"));
+        }
+
+        let mut sections = Vec::new();
+        for (kind, first, count) in [
+            (DolSectionKind::Text, 0, TEXT_SECTIONS),
+            (DolSectionKind::Data, TEXT_SECTIONS, DATA_SECTIONS),
+        ] {
+            for i in first..first + count {
+                let offset = read_u32(bytes, i * 4) as usize;
+                let address = read_u32(bytes, 0x48 + i * 4);
+                let size = read_u32(bytes, 0x90 + i * 4) as usize;
+                if offset == 0 || size == 0 {
+                    continue;
+                }
+
+                let data = bytes.get(offset..offset + size).ok_or_else(|| {
+                    anyhow!(
+                        "DOL section at {:#x}..{:#x} is out of bounds",
+                        offset,
+                        offset + size
+                    )
+                })?;
+                sections.push(DolSection {
+                    kind,
+                    address,
+                    data: data.to_vec(),
+                });
+            }
+        }
+
+        Ok(Self {
+            sections,
+            bss_address: read_u32(bytes, 0xD8),
+            bss_size: read_u32(bytes, 0xDC),
+            entry_point: read_u32(bytes, 0xE0),
+        })
+    }
+
+    /// Get the loaded bytes at an address, if they're all within one section
+    pub fn read(&self, address: u32, size: u32) -> Option<&[u8]> {
+        self.sections.iter().find_map(|s| {
+            let start = address.checked_sub(s.address)? as usize;
+            s.data.get(start..start + size as usize)
+        })
+    }
+
+    /// Whether an address is in a text section
+    pub fn is_text(&self, address: u32) -> bool {
+        self.sections.iter().any(|s| {
+            s.kind == DolSectionKind::Text
+                && address >= s.address
+                && ((address - s.address) as usize) < s.data.len()
+        })
+    }
+}
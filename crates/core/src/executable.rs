@@ -0,0 +1,254 @@
+//! Executables as they're found on PlayStation discs.
+//!
+//! - A PS-X EXE is a 0x800-byte header followed by the code and data, loaded as one block at an
+//!   address given in the header.
+//! - PS2 executables are linked ELFs, usually stripped. Their loadable segments are placed at the
+//!   addresses in the program headers.
+//! - A PSP PRX is an ELF module meant to be loaded anywhere. Its segments are addressed as if it
+//!   were loaded at 0, and the relocations the loader applies are kept for hashing, like a REL's.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+use object::LittleEndian;
+use object::elf::{self, FileHeader32};
+use object::read::elf::{FileHeader, ProgramHeader, SectionHeader};
+
+use crate::pseudo_reloc::{PseudoReloc, PseudoRelocKind};
+
+const PSX_EXE_MAGIC: &[u8] = b"PS-X EXE";
+const PSX_EXE_HEADER_SIZE: usize = 0x800;
+
+/// ELF type of PSP PRX modules
+const ET_SCE_PRX: u16 = 0xFFA0;
+/// Section type of the relocations of a PRX
+const SHT_PSP_RELOC: u32 = 0x7000_00A0;
+/// Section type of the compressed relocations of newer PRXs
+const SHT_PSP_RELOC_V2: u32 = 0x7000_00A1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecutableFormat {
+    PsxExe,
+    Elf,
+    Prx,
+}
+
+#[derive(Debug, Clone)]
+pub struct ExecutableSegment {
+    /// Where the segment is loaded
+    pub address: u32,
+    pub data: Vec<u8>,
+    /// Whether the segment may contain code. A PS-X EXE's only segment always may.
+    pub executable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Executable {
+    pub format: ExecutableFormat,
+    pub segments: Vec<ExecutableSegment>,
+    pub entry_point: u32,
+    /// The relocations of the code, keyed by the address of the relocated instruction, if the
+    /// executable has them
+    pub(crate) relocs: Option<HashMap<u64, PseudoReloc>>,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// Get the format of an executable, if it's one that can be read
+pub fn detect_format(bytes: &[u8]) -> Option<ExecutableFormat> {
+    if bytes.starts_with(PSX_EXE_MAGIC) {
+        return Some(ExecutableFormat::PsxExe);
+    }
+
+    let header = FileHeader32::<LittleEndian>::parse(bytes).ok()?;
+    if header.e_machine(LittleEndian) != elf::EM_MIPS {
+        return None;
+    }
+    match header.e_type(LittleEndian) {
+        elf::ET_EXEC => Some(ExecutableFormat::Elf),
+        ET_SCE_PRX => Some(ExecutableFormat::Prx),
+        _ => None,
+    }
+}
+
+/// Whether the data is an executable that can be read, rather than a ROM or an object
+pub fn is_executable(bytes: &[u8]) -> bool {
+    detect_format(bytes).is_some()
+}
+
+impl Executable {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        match detect_format(bytes) {
+            Some(ExecutableFormat::PsxExe) => Self::parse_psx_exe(bytes),
+            Some(format) => Self::parse_elf(bytes, format),
+            None => Err(anyhow!("Not a PS-X EXE, MIPS ELF executable or PRX")),
+        }
+    }
+
+    fn parse_psx_exe(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < PSX_EXE_HEADER_SIZE {
+            return Err(anyhow!("PS-X EXE is smaller than its header"));
+        }
+
+        let entry_point = read_u32(bytes, 0x10);
+        let address = read_u32(bytes, 0x18);
+        let size = read_u32(bytes, 0x1C) as usize;
+
+        // Some tools write a size that doesn't match the file, so trust the file
+        let end = bytes.len().min(PSX_EXE_HEADER_SIZE + size);
+        Ok(Self {
+            format: ExecutableFormat::PsxExe,
+            segments: vec![ExecutableSegment {
+                address,
+                data: bytes[PSX_EXE_HEADER_SIZE..end].to_vec(),
+                executable: true,
+            }],
+            entry_point,
+            relocs: None,
+        })
+    }
+
+    fn parse_elf(bytes: &[u8], format: ExecutableFormat) -> Result<Self> {
+        let endian = LittleEndian;
+        let header = FileHeader32::<LittleEndian>::parse(bytes)?;
+        let program_headers = header.program_headers(endian, bytes)?;
+
+        let mut segments = vec![];
+        for ph in program_headers {
+            if ph.p_type(endian) != elf::PT_LOAD || ph.p_filesz(endian) == 0 {
+                continue;
+            }
+            let data = ph.data(endian, bytes).map_err(|_| {
+                anyhow!("ELF segment at {:#x} is out of bounds", ph.p_offset(endian))
+            })?;
+            segments.push(ExecutableSegment {
+                address: ph.p_vaddr(endian),
+                data: data.to_vec(),
+                executable: ph.p_flags(endian) & elf::PF_X != 0,
+            });
+        }
+
+        let mut ret = Self {
+            format,
+            segments,
+            entry_point: header.e_entry(endian),
+            relocs: None,
+        };
+        if format == ExecutableFormat::Prx {
+            ret.relocs = ret.prx_code_relocs(header, bytes)?;
+        }
+        Ok(ret)
+    }
+
+    /// Get the loaded bytes at an address, if they're all within one segment
+    pub fn read(&self, address: u32, size: u32) -> Option<&[u8]> {
+        self.segments.iter().find_map(|s| {
+            let start = address.checked_sub(s.address)? as usize;
+            s.data.get(start..start + size as usize)
+        })
+    }
+
+    /// The segment an address is in, if it's one that may contain code
+    pub fn text_segment_at(&self, address: u32) -> Option<&ExecutableSegment> {
+        self.segments.iter().find(|s| {
+            s.executable && address >= s.address && ((address - s.address) as usize) < s.data.len()
+        })
+    }
+
+    /// Whether an address is in a segment that may contain code
+    pub fn is_text(&self, address: u32) -> bool {
+        self.text_segment_at(address).is_some()
+    }
+
+    /// The segments that may contain code
+    pub fn text_segments(&self) -> impl Iterator<Item = &ExecutableSegment> {
+        self.segments.iter().filter(|s| s.executable)
+    }
+
+    /// Read the relocations of a PRX's code. Each one gives the segment of the relocated field and
+    /// the segment its target is in, whose address is added to what's already in the field.
+    /// Newer PRXs compress their relocations, which isn't supported, so theirs are recovered from
+    /// the code instead.
+    fn prx_code_relocs(
+        &self,
+        header: &FileHeader32<LittleEndian>,
+        bytes: &[u8],
+    ) -> Result<Option<HashMap<u64, PseudoReloc>>> {
+        const R_MIPS_26: u32 = 4;
+        const R_MIPS_HI16: u32 = 5;
+        const R_MIPS_LO16: u32 = 6;
+
+        let endian = LittleEndian;
+        let program_headers = header.program_headers(endian, bytes)?;
+        let segment_address = |i: u32| program_headers.get(i as usize).map(|ph| ph.p_vaddr(endian));
+
+        let mut ret = HashMap::new();
+        let mut add = |address: u32, target: u32, kind: PseudoRelocKind| {
+            if self.is_text(address) {
+                ret.insert(
+                    address as u64,
+                    PseudoReloc {
+                        target: target as u64,
+                        kind,
+                    },
+                );
+            }
+        };
+
+        let sections = header.section_headers(endian, bytes)?;
+        if sections
+            .iter()
+            .any(|s| s.sh_type(endian) == SHT_PSP_RELOC_V2)
+        {
+            return Ok(None);
+        }
+
+        for section in sections
+            .iter()
+            .filter(|s| s.sh_type(endian) == SHT_PSP_RELOC)
+        {
+            // A hi16 is completed by the lo16 that follows it, which can also complete several
+            let mut pending_hi: Vec<(u32, u32)> = vec![];
+            let mut last_hi: u32 = 0;
+            for entry in section.data(endian, bytes)?.chunks_exact(8) {
+                let offset = read_u32(entry, 0);
+                let info = read_u32(entry, 4);
+                let (Some(place_base), Some(target_base)) = (
+                    segment_address((info >> 8) & 0xFF),
+                    segment_address((info >> 16) & 0xFF),
+                ) else {
+                    continue;
+                };
+                let address = place_base.wrapping_add(offset) & !3;
+                let Some(word) = self.read(address, 4).map(|b| read_u32(b, 0)) else {
+                    continue;
+                };
+
+                match info & 0xFF {
+                    R_MIPS_26 => add(
+                        address,
+                        target_base.wrapping_add((word & 0x03FF_FFFF) << 2),
+                        PseudoRelocKind::Jump,
+                    ),
+                    R_MIPS_HI16 => {
+                        last_hi = word << 16;
+                        pending_hi.push((address, last_hi));
+                    }
+                    R_MIPS_LO16 => {
+                        let lo = word as u16 as i16 as i32 as u32;
+                        for (hi_address, hi) in pending_hi.drain(..) {
+                            let target = target_base.wrapping_add(hi).wrapping_add(lo);
+                            add(hi_address, target, PseudoRelocKind::Hi);
+                        }
+                        let target = target_base.wrapping_add(last_hi).wrapping_add(lo);
+                        add(address, target, PseudoRelocKind::Lo);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(Some(ret))
+    }
+}
@@ -0,0 +1,98 @@
+//! Hashing for values that get persisted.
+//!
+//! `std`'s `DefaultHasher` makes no promises about its output across Rust releases, and the
+//! `Hash` impls of integers feed it native-endian, pointer-sized data. Hashes that end up in a
+//! database or an exported index instead go through [`StableHasher`]: XXH64 with a fixed seed,
+//! fed fixed-width little-endian integers.
+
+use std::hash::{Hash, Hasher};
+use twox_hash::XxHash64;
+
+/// Version of the hashing scheme. This must be bumped whenever any persisted hash changes for
+/// the same input, whether from the hasher itself or from what gets fed to it (e.g. how operands
+/// are hashed for the equivalent level), so that hashes from different versions are never
+/// compared with one another.
+pub const HASH_VERSION: i32 = 3;
+
+const SEED: u64 = 0;
+
+/// A [`Hasher`] whose output only depends on the data hashed, regardless of the Rust release
+/// or the host's endianness and pointer width
+pub struct StableHasher(XxHash64);
+
+impl StableHasher {
+    pub fn new() -> Self {
+        Self(XxHash64::with_seed(SEED))
+    }
+}
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes);
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.write(&[i]);
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i8(&mut self, i: i8) {
+        self.write_u8(i as u8);
+    }
+
+    fn write_i16(&mut self, i: i16) {
+        self.write_u16(i as u16);
+    }
+
+    fn write_i32(&mut self, i: i32) {
+        self.write_u32(i as u32);
+    }
+
+    fn write_i64(&mut self, i: i64) {
+        self.write_u64(i as u64);
+    }
+
+    fn write_i128(&mut self, i: i128) {
+        self.write_u128(i as u128);
+    }
+
+    fn write_isize(&mut self, i: isize) {
+        self.write_i64(i as i64);
+    }
+}
+
+/// Hash a single value with [`StableHasher`]
+pub fn stable_hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = StableHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
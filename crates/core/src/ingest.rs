@@ -1,20 +1,193 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Range;
 
-use crate::{OBJDIFF_CONFIG, Platform, Symbol, arch};
+use crate::cfg::{Cfg, InsnFlow};
+use crate::cwmap::{self, CwMap, CwMapSymbol};
+use crate::dol::Dol;
+use crate::executable::{self, Executable};
+use crate::hash::stable_hash;
+use crate::n64;
+use crate::nds::{self, NdsRom, NdsSegment};
+use crate::pseudo_reloc::{PseudoReloc, find_pseudo_relocs};
+use crate::rel::{self, Rel};
+use crate::symbol_list;
+use crate::{
+    Arch, LibraryMember, OBJDIFF_CONFIG, Platform, Symbol, arch, arch::InsnModes, schedule,
+    semantic,
+};
 use anyhow::{Result, anyhow};
 use mapfile_parser::MapFile;
 use objdiff_core::{
     diff::DiffSide,
     obj::{ResolvedSymbol, SymbolFlag},
 };
+use object::read::archive::ArchiveFile;
+use object::{FileKind, Object, ObjectSection, ObjectSymbol};
+
+/// Read symbols from an object file, detecting its format
+pub fn read_object(
+    platform: Platform,
+    unmatched_funcs: &Option<Vec<String>>,
+    obj_data: &[u8],
+) -> Result<Vec<Symbol>> {
+    match FileKind::parse(obj_data)? {
+        FileKind::Elf32 | FileKind::Elf64 => read_elf(platform, unmatched_funcs, obj_data),
+        FileKind::Coff | FileKind::CoffBig => read_coff(platform, unmatched_funcs, obj_data),
+        kind => Err(anyhow!("Unsupported object format: {:?}", kind)),
+    }
+}
 
 pub fn read_elf(
     platform: Platform,
     unmatched_funcs: &Option<Vec<String>>,
     elf_data: &[u8],
 ) -> Result<Vec<Symbol>> {
-    let objdiff_obj = objdiff_core::obj::read::parse(elf_data, &OBJDIFF_CONFIG, DiffSide::Base)
-        .map_err(|e| anyhow!("Failed to parse ELF object: {}", e))?;
+    read_objdiff_object(platform, unmatched_funcs, elf_data, "ELF")
+}
+
+pub fn read_coff(
+    platform: Platform,
+    unmatched_funcs: &Option<Vec<String>>,
+    coff_data: &[u8],
+) -> Result<Vec<Symbol>> {
+    read_objdiff_object(platform, unmatched_funcs, coff_data, "COFF")
+}
+
+/// Get the name and data of each object in a static library (an `ar` archive of ELF or COFF
+/// objects, including MSVC `.lib`s). Anything else in it is left out.
+pub fn archive_members(archive_data: &[u8]) -> Result<Vec<(String, &[u8])>> {
+    let archive = ArchiveFile::parse(archive_data)?;
+
+    let mut ret = vec![];
+    for member in archive.members() {
+        let member = member?;
+        let name = String::from_utf8_lossy(member.name()).to_string();
+        let data = member.data(archive_data)?;
+        if matches!(
+            FileKind::parse(data),
+            Ok(FileKind::Elf32 | FileKind::Elf64 | FileKind::Coff | FileKind::CoffBig)
+        ) {
+            ret.push((name, data));
+        }
+    }
+    Ok(ret)
+}
+
+/// Read the symbols of every object in a static library, tagging each with the archive and member
+/// it's from. Members that can't be read are skipped.
+pub fn read_archive(
+    platform: Platform,
+    archive_name: &str,
+    archive_data: &[u8],
+) -> Result<Vec<Symbol>> {
+    let mut ret = vec![];
+    for (member, data) in archive_members(archive_data)? {
+        let symbols = match read_object(platform, &None, data) {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                eprintln!("Failed to read {} in {}: {}", member, archive_name, e);
+                continue;
+            }
+        };
+
+        ret.extend(symbols.into_iter().map(|s| Symbol {
+            library: Some(LibraryMember {
+                archive: archive_name.to_string(),
+                member: member.clone(),
+            }),
+            ..s
+        }));
+    }
+    Ok(ret)
+}
+
+/// ARM/Thumb state information for an object, from its mapping symbols and function symbols
+#[derive(Debug, Default)]
+struct ArmStates {
+    /// Changes of instruction set given by `$a`/`$t`/`$d` mapping symbols, by section name. `$d`
+    /// (data) is recorded as `None`.
+    mapping_symbols: HashMap<String, Vec<(u64, Option<Arch>)>>,
+    /// Functions whose symbol value has the Thumb bit set, by section name and address
+    thumb_functions: HashSet<(String, u64)>,
+}
+
+impl ArmStates {
+    fn parse(obj_data: &[u8]) -> Result<Self> {
+        let file = object::File::parse(obj_data)?;
+        let mut ret = Self::default();
+
+        for symbol in file.symbols() {
+            let (Ok(name), Some(section_index)) = (symbol.name(), symbol.section_index()) else {
+                continue;
+            };
+            let Ok(section_name) = file
+                .section_by_index(section_index)
+                .and_then(|s| s.name().map(str::to_string))
+            else {
+                continue;
+            };
+
+            // Mapping symbols may have a suffix, e.g. `$t.0`
+            let mapping = match name.split('.').next() {
+                Some("$a") => Some(Some(Arch::Arm)),
+                Some("$t") => Some(Some(Arch::Thumb)),
+                Some("$d") => Some(None),
+                _ => None,
+            };
+
+            if let Some(arch) = mapping {
+                ret.mapping_symbols
+                    .entry(section_name)
+                    .or_default()
+                    .push((symbol.address(), arch));
+            } else if symbol.kind() == object::SymbolKind::Text && symbol.address() & 1 == 1 {
+                ret.thumb_functions
+                    .insert((section_name, symbol.address() & !1));
+            }
+        }
+
+        for changes in ret.mapping_symbols.values_mut() {
+            changes.sort_by_key(|(address, _)| *address);
+        }
+
+        Ok(ret)
+    }
+
+    /// Mapping symbols take priority, followed by the function's Thumb bit, then the platform's
+    /// usual instruction set
+    fn modes_for(&self, section_name: &str, address: u64, platform: Platform) -> InsnModes {
+        let default = if self
+            .thumb_functions
+            .contains(&(section_name.to_string(), address & !1))
+        {
+            Arch::Thumb
+        } else {
+            platform.arch()
+        };
+
+        let changes = self
+            .mapping_symbols
+            .get(section_name)
+            .cloned()
+            .unwrap_or_default();
+
+        InsnModes::new(default, changes)
+    }
+}
+
+fn read_objdiff_object(
+    platform: Platform,
+    unmatched_funcs: &Option<Vec<String>>,
+    obj_data: &[u8],
+    format_name: &str,
+) -> Result<Vec<Symbol>> {
+    let objdiff_obj = objdiff_core::obj::read::parse(obj_data, &OBJDIFF_CONFIG, DiffSide::Base)
+        .map_err(|e| anyhow!("Failed to parse {} object: {}", format_name, e))?;
+
+    let arm_states = match platform.arch() {
+        Arch::Thumb | Arch::Arm => ArmStates::parse(obj_data)?,
+        _ => ArmStates::default(),
+    };
 
     let symbols = objdiff_obj
         .symbols
@@ -33,6 +206,18 @@ pub fn read_elf(
         })
         .collect::<Vec<_>>();
 
+    // Recovered relocations only know their target's address, which names are looked up by
+    let mut names_by_address: HashMap<u64, String> = HashMap::new();
+    for s in objdiff_obj
+        .symbols
+        .iter()
+        .filter(|s| s.section.is_some() && s.kind != objdiff_core::obj::SymbolKind::Section)
+    {
+        names_by_address
+            .entry(s.address)
+            .or_insert_with(|| s.name.clone());
+    }
+
     let ret: Vec<Symbol> = symbols
         .iter()
         .filter_map(|(idx, symbol)| {
@@ -84,16 +269,64 @@ pub fn read_elf(
 
             let vram = symbol.address as usize;
 
-            let mut hasher = DefaultHasher::new();
-            bytes.hash(&mut hasher);
-            let exact_hash = hasher.finish();
+            let exact_hash = stable_hash(&bytes);
 
-            let equiv_hash = arch::get_equivalence_hash(&bytes, platform, section, &insn_refs);
+            let modes = match platform.arch() {
+                Arch::Thumb | Arch::Arm => {
+                    arm_states.modes_for(&section.name, symbol.address, platform)
+                }
+                arch => InsnModes::uniform(arch),
+            };
 
-            let opcodes: Vec<u16> = insn_refs.iter().map(|r| r.opcode).collect();
-            let mut hasher = DefaultHasher::new();
-            opcodes.hash(&mut hasher);
-            let opcode_hash = hasher.finish();
+            let insn_equiv_hashes = arch::get_equivalence_hashes(
+                &bytes,
+                platform,
+                &objdiff_obj,
+                section,
+                &insn_refs,
+                &modes,
+            );
+            let equiv_hash = stable_hash(&insn_equiv_hashes);
+            let regalloc_hash = arch::get_regalloc_hash(
+                &bytes,
+                platform,
+                &objdiff_obj,
+                section,
+                &insn_refs,
+                &modes,
+            );
+            let target_names = arch::get_target_names(
+                &bytes,
+                platform,
+                &objdiff_obj,
+                section,
+                &insn_refs,
+                &modes,
+                &names_by_address,
+            );
+            let semantic_hash = semantic::semantic_hash(&insn_equiv_hashes, &target_names);
+            let insn_reloc_targets = arch::get_reloc_targets(
+                &bytes,
+                platform,
+                &objdiff_obj,
+                section,
+                &insn_refs,
+                &modes,
+            );
+            let flows = arch::get_insn_flows(&bytes, platform, &insn_refs, &modes);
+            let cfg = Cfg::build(&flows);
+            let calls = arch::get_calls(&objdiff_obj, section, &insn_refs, &flows);
+
+            let opcodes = arch::get_opcodes(&bytes, platform, &insn_refs);
+            let opcode_hash = stable_hash(&opcodes);
+            let canonical_order = schedule::canonical_order(
+                &bytes,
+                platform,
+                &opcodes,
+                &insn_equiv_hashes,
+                &flows,
+                &cfg,
+            );
 
             Some(Symbol {
                 name: symbol.name.clone(),
@@ -105,7 +338,16 @@ pub fn read_elf(
                     .is_none_or(|fs| !fs.contains(&symbol.name)),
                 exact_hash,
                 equiv_hash,
+                insn_equiv_hashes,
+                insn_reloc_targets,
+                regalloc_hash,
+                semantic_hash,
                 opcode_hash,
+                cfg,
+                calls,
+                canonical_order,
+                segment: None,
+                library: None,
                 symbol_idx: *idx,
             })
         })
@@ -122,65 +364,701 @@ pub fn read_map(
 ) -> Result<Vec<Symbol>> {
     let mapfile = MapFile::new_from_map_str(map_str);
 
+    // The address of a Thumb function has bit 0 set on ARM platforms
+    let thumb_bit = |vram: u64| match platform.arch() {
+        Arch::Arm | Arch::Thumb => vram & 1,
+        _ => 0,
+    };
+
+    // Overlays share VRAM, so names are looked up in the referring symbol's own segment first
+    let mut global_names: HashMap<u64, String> = HashMap::new();
+    let mut segment_names: HashMap<&str, HashMap<u64, String>> = HashMap::new();
+    for segment in &mapfile.segments_list {
+        for section in &segment.sections_list {
+            for x in &section.symbols {
+                let vram = match section.section_type.as_str() {
+                    ".text" => x.vram - thumb_bit(x.vram),
+                    _ => x.vram,
+                };
+                global_names.entry(vram).or_insert_with(|| x.name.clone());
+                segment_names
+                    .entry(&segment.name)
+                    .or_default()
+                    .entry(vram)
+                    .or_insert_with(|| x.name.clone());
+            }
+        }
+    }
+    let names_by_segment: HashMap<&str, HashMap<u64, String>> = segment_names
+        .into_iter()
+        .map(|(segment, names)| {
+            let mut all_names = global_names.clone();
+            all_names.extend(names);
+            (segment, all_names)
+        })
+        .collect();
+
+    // x86 immediates are only taken as addresses if they point into one of the sections
+    let loaded: Vec<Range<u64>> = mapfile
+        .segments_list
+        .iter()
+        .flat_map(|segment| &segment.sections_list)
+        .filter(|x| x.vram != 0 && x.size != 0)
+        .map(|x| x.vram..x.vram + x.size)
+        .collect();
+
     let ret: Vec<Symbol> = mapfile
         .segments_list
         .iter()
-        .flat_map(|x| x.sections_list.iter())
-        .filter(|x| x.section_type == ".text")
-        .flat_map(|x| x.symbols.iter())
-        .filter(|x| x.vrom.is_some())
+        .flat_map(|segment| {
+            segment
+                .sections_list
+                .iter()
+                .filter(|x| x.section_type == ".text")
+                .flat_map(|x| x.symbols.iter())
+                .filter(|x| x.vrom.is_some())
+                .map(move |x| (segment, x))
+        })
         .enumerate()
-        .map(|(symbol_idx, x)| {
-            let start = x.vrom.unwrap() as usize;
+        .filter_map(|(symbol_idx, (segment, x))| {
+            let thumb_bit = thumb_bit(x.vram);
+            let start = (x.vrom.unwrap() - thumb_bit) as usize;
             let end = start + x.size as usize;
-            let raw = &rom_bytes[start..end];
-            let vram = x.vram as usize;
+            let Some(raw) = rom_bytes.get(start..end) else {
+                eprintln!("Symbol {} is out of bounds of the ROM", x.name);
+                return None;
+            };
+
+            let arch = raw_function_arch(platform, thumb_bit == 1, raw);
+            let vram = x.vram - thumb_bit;
+            let relocs =
+                (arch == Arch::X86).then(|| find_pseudo_relocs(raw, vram, platform, arch, &loaded));
+
+            Some(read_raw_symbol(
+                platform,
+                arch,
+                &x.name,
+                raw,
+                vram as usize,
+                Some(segment.name.clone()),
+                symbol_idx,
+                is_decompiled_in_map(&unmatched_funcs, &x.name),
+                names_by_segment
+                    .get(segment.name.as_str())
+                    .unwrap_or(&global_names),
+                relocs.as_ref(),
+            ))
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// Read the functions of an N64 ROM in any byte order, with their boundaries from a GNU map.
+/// Symbols are tagged with the segment or overlay they're in.
+pub fn read_n64_rom(
+    unmatched_funcs: Option<Vec<String>>,
+    rom_bytes: Vec<u8>,
+    map_str: &str,
+) -> Result<Vec<Symbol>> {
+    read_map(
+        Platform::N64,
+        unmatched_funcs,
+        n64::to_big_endian(rom_bytes)?,
+        map_str,
+    )
+}
+
+/// Get the instruction set of a function of linked code. On ARM platforms, a Thumb function is
+/// marked by bit 0 of its address if it's set. Otherwise it's told apart by its code: most ARM
+/// instructions are unconditional, so have the `AL` condition in their top bits, which Thumb
+/// code rarely has at the same place.
+fn raw_function_arch(platform: Platform, thumb_bit: bool, raw: &[u8]) -> Arch {
+    if !matches!(platform.arch(), Arch::Arm | Arch::Thumb) {
+        return platform.arch();
+    }
+    if thumb_bit {
+        return Arch::Thumb;
+    }
+
+    let words: Vec<u32> = raw
+        .chunks_exact(4)
+        .map(|c| platform.endianness().read_u32_bytes(c.try_into().unwrap()))
+        .collect();
+    if words.is_empty() {
+        return platform.arch();
+    }
+    let unconditional = words.iter().filter(|w| *w >> 28 == 0xE).count();
+    if unconditional * 2 > words.len() {
+        Arch::Arm
+    } else {
+        Arch::Thumb
+    }
+}
+
+/// Symbols read from a map are only known to be decompiled if the project lists the ones that
+/// aren't
+fn is_decompiled_in_map(unmatched_funcs: &Option<Vec<String>>, name: &str) -> bool {
+    unmatched_funcs
+        .as_ref()
+        .is_some_and(|fs| !fs.iter().any(|f| f == name))
+}
+
+/// Read a function from linked code, in the given instruction set of the platform. `relocs` are
+/// the code's relocations if they're known, otherwise they're recovered from it.
+#[allow(clippy::too_many_arguments)]
+fn read_raw_symbol(
+    platform: Platform,
+    arch: Arch,
+    name: &str,
+    raw: &[u8],
+    vram: usize,
+    segment: Option<String>,
+    symbol_idx: usize,
+    is_decompiled: bool,
+    names_by_address: &HashMap<u64, String>,
+    relocs: Option<&HashMap<u64, PseudoReloc>>,
+) -> Symbol {
+    let mut bytes = raw.to_vec();
+
+    let insn_length = arch.standard_insn_length();
+
+    if arch == Arch::X86 {
+        // trim int3/nop padding between functions
+        bytes.truncate(arch::trim_x86_padding(&bytes, platform));
+    } else {
+        // trim trailing nops
+        while bytes.len() >= insn_length
+            && bytes[bytes.len() - insn_length..] == vec![0; insn_length]
+        {
+            bytes.truncate(bytes.len() - insn_length);
+        }
+
+        // but keep the nop in the delay slot of the final jump, as the object has it
+        if arch == Arch::Mips
+            && bytes.len() >= 4
+            && bytes.len() < raw.len()
+            && arch::mips_has_delay_slot(
+                platform
+                    .endianness()
+                    .read_u32_bytes(bytes[bytes.len() - 4..].try_into().unwrap()),
+            )
+        {
+            bytes.extend_from_slice(&[0; 4]);
+        }
+    }
+    let opcodes: Vec<u16> = arch::get_opcodes_raw(&bytes, platform, arch);
+
+    let exact_hash = stable_hash(&bytes);
+
+    let insn_equiv_hashes = arch::get_equivalence_hashes_raw(&bytes, vram, platform, arch, relocs);
+    let equiv_hash = stable_hash(&insn_equiv_hashes);
+    let regalloc_hash = arch::get_regalloc_hash_raw(&bytes, vram, platform, arch, relocs);
+    let target_names =
+        arch::get_target_names_raw(&bytes, vram, platform, arch, names_by_address, relocs);
+    let semantic_hash = semantic::semantic_hash(&insn_equiv_hashes, &target_names);
+    let insn_reloc_targets = arch::get_reloc_targets_raw(&bytes, vram, platform, arch, relocs);
+    let flows = arch::get_insn_flows_raw(&bytes, vram, platform, arch);
+    let cfg = Cfg::build(&flows);
+    let calls = arch::get_calls_raw(&flows);
+
+    let opcode_hash = stable_hash(&opcodes);
+    let canonical_order =
+        schedule::canonical_order(&bytes, platform, &opcodes, &insn_equiv_hashes, &flows, &cfg);
+
+    Symbol {
+        name: name.to_string(),
+        bytes,
+        opcodes,
+        vram,
+        is_decompiled,
+        exact_hash,
+        equiv_hash,
+        insn_equiv_hashes,
+        insn_reloc_targets,
+        regalloc_hash,
+        semantic_hash,
+        opcode_hash,
+        cfg,
+        calls,
+        canonical_order,
+        segment,
+        library: None,
+        symbol_idx,
+    }
+}
+
+/// Read the functions of a GameCube/Wii DOL executable, with their boundaries from a CodeWarrior
+/// linker map
+pub fn read_dol(
+    platform: Platform,
+    unmatched_funcs: Option<Vec<String>>,
+    dol_bytes: &[u8],
+    map_str: &str,
+) -> Result<Vec<Symbol>> {
+    let dol = Dol::parse(dol_bytes)?;
+    let map = CwMap::parse(map_str);
+    let names_by_address = map.names_by_address(|s| Some(s.vram as u64));
+
+    let ret = map
+        .functions()
+        .filter(|f| dol.is_text(f.vram))
+        .enumerate()
+        .filter_map(|(symbol_idx, f)| {
+            let Some(raw) = dol.read(f.vram, f.size) else {
+                eprintln!("Symbol {} is out of bounds of the DOL", f.name);
+                return None;
+            };
+
+            Some(read_raw_symbol(
+                platform,
+                platform.arch(),
+                &f.name,
+                raw,
+                f.vram as usize,
+                None,
+                symbol_idx,
+                is_decompiled_in_map(&unmatched_funcs, &f.name),
+                &names_by_address,
+                None,
+            ))
+        })
+        .collect();
+    Ok(ret)
+}
+
+/// Read the functions of a GameCube/Wii REL module, with their boundaries from its CodeWarrior
+/// linker map. The module's relocations are applied and used for hashing. Its sections are
+/// addressed by their offset in the file.
+pub fn read_rel(
+    platform: Platform,
+    unmatched_funcs: Option<Vec<String>>,
+    rel_bytes: &[u8],
+    map_str: &str,
+) -> Result<Vec<Symbol>> {
+    let mut rel = Rel::parse(rel_bytes)?;
+    rel.apply_relocations();
+    let relocs = rel.code_relocs();
+    let map = CwMap::parse(map_str);
+
+    // The map names sections, the module only numbers them. Code sections are matched up in
+    // order, as are the rest.
+    let mut section_names: Vec<&str> = vec![];
+    for symbol in &map.symbols {
+        if !section_names.contains(&symbol.section.as_str()) {
+            section_names.push(&symbol.section);
+        }
+    }
+    let is_code = |name: &str| matches!(name, ".init" | ".text");
+    let section_index = |name: &str| {
+        let nth = section_names
+            .iter()
+            .filter(|s| is_code(s) == is_code(name))
+            .position(|s| *s == name)?;
+        rel.sections
+            .iter()
+            .enumerate()
+            .filter(|(_, s)| s.executable == is_code(name) && s.size > 0)
+            .nth(nth)
+            .map(|(i, _)| i)
+    };
+    let sections: HashMap<&str, usize> = section_names
+        .iter()
+        .filter_map(|name| Some((*name, section_index(name)?)))
+        .collect();
+
+    let address = |s: &CwMapSymbol| {
+        let section = &rel.sections[*sections.get(s.section.as_str())?];
+        (section.offset != 0).then(|| section.offset as u64 + s.offset as u64)
+    };
+    let names_by_address = map.names_by_address(address);
 
-            let mut bytes = raw.to_vec();
+    let ret = map
+        .functions()
+        .enumerate()
+        .filter_map(|(symbol_idx, f)| {
+            let section = &rel.sections[*sections.get(f.section.as_str())?];
+            let start = f.offset as usize;
+            let Some(raw) = section.data.get(start..start + f.size as usize) else {
+                eprintln!("Symbol {} is out of bounds of the REL", f.name);
+                return None;
+            };
+
+            Some(read_raw_symbol(
+                platform,
+                platform.arch(),
+                &f.name,
+                raw,
+                section.offset as usize + start,
+                None,
+                symbol_idx,
+                is_decompiled_in_map(&unmatched_funcs, &f.name),
+                &names_by_address,
+                Some(&relocs),
+            ))
+        })
+        .collect();
+    Ok(ret)
+}
 
-            let insn_length = platform.arch().standard_insn_length();
+/// A function of an executable, as listed by a map or symbol list or found by scanning its code
+struct ExecutableFunction {
+    name: String,
+    vram: u32,
+    size: u32,
+    segment: Option<String>,
+}
 
-            // trim trailing nops
-            while bytes.len() >= insn_length
-                && bytes[bytes.len() - insn_length..] == vec![0; insn_length]
+/// Get the functions listed by a GNU map or, failing that, a symbol list, along with the names of
+/// every symbol listed by address
+fn list_executable_functions(
+    exe: &Executable,
+    symbols_str: &str,
+) -> (Vec<ExecutableFunction>, HashMap<u64, String>) {
+    let mut names_by_address: HashMap<u64, String> = HashMap::new();
+    let mut functions = vec![];
+
+    let mapfile = MapFile::new_from_map_str(symbols_str);
+    for segment in &mapfile.segments_list {
+        for section in &segment.sections_list {
+            for x in &section.symbols {
+                names_by_address
+                    .entry(x.vram)
+                    .or_insert_with(|| x.name.clone());
+                if section.section_type == ".text" {
+                    functions.push(ExecutableFunction {
+                        name: x.name.clone(),
+                        vram: x.vram as u32,
+                        size: x.size as u32,
+                        segment: Some(segment.name.clone()),
+                    });
+                }
+            }
+        }
+    }
+    if !names_by_address.is_empty() {
+        return (functions, names_by_address);
+    }
+
+    let mut listed = symbol_list::parse(symbols_str);
+    listed.sort_by_key(|s| s.vram);
+    for (i, symbol) in listed.iter().enumerate() {
+        names_by_address
+            .entry(symbol.vram as u64)
+            .or_insert_with(|| symbol.name.clone());
+
+        let Some(segment) = exe.text_segment_at(symbol.vram) else {
+            continue;
+        };
+        if !symbol.is_function {
+            continue;
+        }
+
+        // Functions without a size run up to the next symbol
+        let segment_end = segment.address + segment.data.len() as u32;
+        let next = listed[i + 1..]
+            .iter()
+            .map(|s| s.vram)
+            .find(|vram| *vram > symbol.vram)
+            .unwrap_or(segment_end)
+            .min(segment_end);
+        functions.push(ExecutableFunction {
+            name: symbol.name.clone(),
+            vram: symbol.vram,
+            size: symbol.size.unwrap_or(next - symbol.vram),
+            segment: None,
+        });
+    }
+    (functions, names_by_address)
+}
+
+/// Guess where the functions of an executable are when nothing lists them: at the start of each
+/// code segment, at the entry point, at the targets of calls and after each return (MIPS only).
+/// Each one runs up to the next.
+fn find_executable_functions(exe: &Executable, platform: Platform) -> Vec<ExecutableFunction> {
+    const JR_RA: u32 = 0x03E0_0008;
+
+    let mut ret = vec![];
+    for segment in exe.text_segments() {
+        let start = segment.address;
+        let end = start + segment.data.len() as u32;
+        let in_segment = |address: u64| address >= start as u64 && address < end as u64;
+
+        let mut starts = BTreeSet::from([start]);
+        if in_segment(exe.entry_point as u64) {
+            starts.insert(exe.entry_point);
+        }
+        for insn in
+            arch::get_insn_flows_raw(&segment.data, start as usize, platform, platform.arch())
+        {
+            if let InsnFlow::Call(Some(target)) = insn.flow
+                && in_segment(target)
+                && target.is_multiple_of(4)
             {
-                bytes.truncate(bytes.len() - insn_length);
+                starts.insert(target as u32);
             }
-            let opcodes: Vec<u16> = arch::get_opcodes_raw(&bytes, platform);
+        }
+        if platform.arch() == Arch::Mips {
+            let words: Vec<u32> = segment
+                .data
+                .chunks_exact(4)
+                .map(|c| platform.endianness().read_u32_bytes(c.try_into().unwrap()))
+                .collect();
+            for (i, _) in words.iter().enumerate().filter(|(_, w)| **w == JR_RA) {
+                // Skip the delay slot and any padding
+                let mut next = i + 2;
+                while words.get(next) == Some(&0) {
+                    next += 1;
+                }
+                if next < words.len() {
+                    starts.insert(start + next as u32 * 4);
+                }
+            }
+        }
+
+        let starts: Vec<u32> = starts.into_iter().collect();
+        for (i, vram) in starts.iter().enumerate() {
+            let next = starts.get(i + 1).copied().unwrap_or(end);
+            ret.push(ExecutableFunction {
+                name: format!("func_{vram:08X}"),
+                vram: *vram,
+                size: next - vram,
+                segment: None,
+            });
+        }
+    }
+    ret
+}
 
-            let mut hasher = DefaultHasher::new();
-            bytes.hash(&mut hasher);
-            let exact_hash = hasher.finish();
+/// Read the functions of a PlayStation executable: a PS-X EXE, a PS2 ELF or a PSP PRX. Their
+/// boundaries come from a GNU map or a splat symbol list if one is given, otherwise they're
+/// guessed by scanning the code and named after their address. A PRX's relocations are used for
+/// hashing.
+pub fn read_executable(
+    platform: Platform,
+    unmatched_funcs: Option<Vec<String>>,
+    exe_bytes: &[u8],
+    symbols_str: Option<&str>,
+) -> Result<Vec<Symbol>> {
+    let exe = Executable::parse(exe_bytes)?;
 
-            let equiv_hash = arch::get_equivalence_hash_raw(&bytes, vram, platform);
+    let (functions, names_by_address) = match symbols_str {
+        Some(symbols_str) => list_executable_functions(&exe, symbols_str),
+        None => {
+            let functions = find_executable_functions(&exe, platform);
+            let names_by_address = functions
+                .iter()
+                .map(|f| (f.vram as u64, f.name.clone()))
+                .collect();
+            (functions, names_by_address)
+        }
+    };
 
-            let mut hasher = DefaultHasher::new();
-            opcodes.hash(&mut hasher);
-            let opcode_hash = hasher.finish();
+    let ret = functions
+        .into_iter()
+        .filter(|f| exe.is_text(f.vram))
+        .enumerate()
+        .filter_map(|(symbol_idx, f)| {
+            let Some(raw) = exe.read(f.vram, f.size) else {
+                eprintln!("Symbol {} is out of bounds of the executable", f.name);
+                return None;
+            };
 
-            Symbol {
-                name: x.name.clone(),
-                bytes,
-                opcodes,
-                vram,
-                is_decompiled: unmatched_funcs
-                    .as_ref()
-                    .is_some_and(|fs| !fs.contains(&x.name)),
-                exact_hash,
-                equiv_hash,
-                opcode_hash,
+            Some(read_raw_symbol(
+                platform,
+                platform.arch(),
+                &f.name,
+                raw,
+                f.vram as usize,
+                f.segment,
                 symbol_idx,
+                is_decompiled_in_map(&unmatched_funcs, &f.name),
+                &names_by_address,
+                exe.relocs.as_ref(),
+            ))
+        })
+        .filter(|s| !s.bytes.is_empty())
+        .collect();
+    Ok(ret)
+}
+
+/// Read the functions of an NDS ROM's ARM9 and ARM7 binaries, autoloads and overlays, with their
+/// boundaries from a GNU map or a splat symbol list. Map segments are matched to the ROM's by name
+/// (e.g. `ov002`), and listed symbols by their `rom:` offset if they have one, since overlays
+/// share VRAM. Symbols are tagged with the segment they're in.
+pub fn read_nds(
+    platform: Platform,
+    unmatched_funcs: Option<Vec<String>>,
+    rom_bytes: &[u8],
+    map_str: &str,
+) -> Result<Vec<Symbol>> {
+    let rom = NdsRom::parse(rom_bytes)?;
+
+    // (segment, name, vram, size, instruction set if listed) of each function, and the names of
+    // every symbol by segment
+    let mut functions: Vec<(&NdsSegment, String, u32, u32, Option<Arch>)> = vec![];
+    let mut segment_names: HashMap<&str, HashMap<u64, String>> = HashMap::new();
+
+    let mapfile = MapFile::new_from_map_str(map_str);
+    for map_segment in &mapfile.segments_list {
+        let symbols = map_segment
+            .sections_list
+            .iter()
+            .flat_map(|x| x.symbols.iter());
+        let Some(segment) = symbols
+            .clone()
+            .next()
+            .and_then(|x| rom.segment_for_map_name(&map_segment.name, x.vram as u32))
+        else {
+            continue;
+        };
+
+        for section in &map_segment.sections_list {
+            let is_text = section.section_type == ".text";
+            for x in &section.symbols {
+                // A Thumb function's address has bit 0 set
+                let vram = if is_text { x.vram & !1 } else { x.vram };
+                segment_names
+                    .entry(&segment.name)
+                    .or_default()
+                    .entry(vram)
+                    .or_insert_with(|| x.name.clone());
+                if is_text {
+                    functions.push((segment, x.name.clone(), x.vram as u32, x.size as u32, None));
+                }
+            }
+        }
+    }
+
+    if segment_names.is_empty() {
+        let mut listed = symbol_list::parse(map_str);
+        listed.sort_by_key(|s| s.vram);
+        for (i, symbol) in listed.iter().enumerate() {
+            let Some(segment) = rom.segment_at(symbol.vram, symbol.rom) else {
+                continue;
+            };
+            // A Thumb function's address has bit 0 set
+            let vram = if symbol.is_function {
+                symbol.vram & !1
+            } else {
+                symbol.vram
+            };
+            segment_names
+                .entry(&segment.name)
+                .or_default()
+                .entry(vram as u64)
+                .or_insert_with(|| symbol.name.clone());
+            if !symbol.is_function {
+                continue;
             }
+
+            // Functions without a size run up to the next symbol in the same segment
+            let segment_end = segment.address + segment.data.len() as u32;
+            let next = listed[i + 1..]
+                .iter()
+                .filter(|s| s.vram > symbol.vram)
+                .find(|s| {
+                    rom.segment_at(s.vram, s.rom)
+                        .is_some_and(|x| x.name == segment.name)
+                })
+                .map_or(segment_end, |s| s.vram);
+            functions.push((
+                segment,
+                symbol.name.clone(),
+                symbol.vram,
+                symbol.size.unwrap_or(next - vram),
+                symbol.arch,
+            ));
+        }
+    }
+
+    // A segment's own symbols are named first, then those of the main binaries
+    let main_names: HashMap<u64, String> = rom
+        .segments
+        .iter()
+        .filter(|s| s.overlay_id.is_none())
+        .filter_map(|s| segment_names.get(s.name.as_str()))
+        .flat_map(|names| names.iter().map(|(k, v)| (*k, v.clone())))
+        .collect();
+    let names_by_segment: HashMap<&str, HashMap<u64, String>> = segment_names
+        .iter()
+        .map(|(segment, names)| {
+            let mut all_names = main_names.clone();
+            all_names.extend(names.iter().map(|(k, v)| (*k, v.clone())));
+            (*segment, all_names)
+        })
+        .collect();
+
+    let ret = functions
+        .into_iter()
+        .enumerate()
+        .filter_map(|(symbol_idx, (segment, name, vram, size, arch))| {
+            let thumb_bit = vram & 1 == 1;
+            let vram = vram & !1;
+            let start = vram.wrapping_sub(segment.address) as usize;
+            let Some(raw) = segment.data.get(start..start + size as usize) else {
+                eprintln!("Symbol {} is out of bounds of {}", name, segment.name);
+                return None;
+            };
+
+            Some(read_raw_symbol(
+                platform,
+                arch.unwrap_or_else(|| raw_function_arch(platform, thumb_bit, raw)),
+                &name,
+                raw,
+                vram as usize,
+                Some(segment.name.clone()),
+                symbol_idx,
+                is_decompiled_in_map(&unmatched_funcs, &name),
+                names_by_segment
+                    .get(segment.name.as_str())
+                    .unwrap_or(&main_names),
+                None,
+            ))
         })
         .collect();
     Ok(ret)
 }
 
+/// Read symbols from a linked binary and its map, detecting their formats. GameCube/Wii binaries
+/// with a CodeWarrior map are read as a DOL or REL, NDS ROMs by their segments, and PlayStation
+/// executables as such, with a map or symbol list if there is one. Anything else is read as a ROM
+/// with a GNU map. N64 ROMs may be in any byte order.
+pub fn read_target(
+    platform: Platform,
+    unmatched_funcs: Option<Vec<String>>,
+    target_bytes: Vec<u8>,
+    map_str: Option<&str>,
+) -> Result<Vec<Symbol>> {
+    if matches!(platform, Platform::Psx | Platform::Ps2 | Platform::Psp)
+        && executable::is_executable(&target_bytes)
+    {
+        return read_executable(platform, unmatched_funcs, &target_bytes, map_str);
+    }
+
+    let map_str = map_str.ok_or_else(|| anyhow!("A map is needed to read this binary"))?;
+    if platform == Platform::Nds && nds::is_nds(&target_bytes) {
+        return read_nds(platform, unmatched_funcs, &target_bytes, map_str);
+    }
+    if platform == Platform::GcWii && cwmap::is_cw_map(map_str) {
+        return if rel::is_rel(&target_bytes) {
+            read_rel(platform, unmatched_funcs, &target_bytes, map_str)
+        } else {
+            read_dol(platform, unmatched_funcs, &target_bytes, map_str)
+        };
+    }
+    if platform == Platform::N64 {
+        return read_n64_rom(unmatched_funcs, target_bytes, map_str);
+    }
+    read_map(platform, unmatched_funcs, target_bytes, map_str)
+}
+
 #[cfg(test)]
 mod tests {
     use std::{fs, path::PathBuf};
 
     use super::*;
+    use crate::pseudo_reloc::PseudoRelocKind;
+    use crate::{Binary, InsnSeqMatch};
 
     #[test]
     fn test_simple_mips() {
@@ -208,6 +1086,18 @@ mod tests {
         assert_eq!(math_op_1.exact_hash, math_op_1_dup.exact_hash);
     }
 
+    #[test]
+    fn test_stable_hashes() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        // persisted hashes must not depend on the toolchain or host; if this changes on purpose,
+        // bump HASH_VERSION
+        let math_op_1 = symbols.iter().find(|s| s.name == "math_op_1").unwrap();
+        assert_eq!(math_op_1.exact_hash, 0xAA19AD8A464437B3);
+    }
+
     #[test]
     fn test_simple_mips_linked() {
         let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -220,8 +1110,7 @@ mod tests {
         let tf3 = symbols.iter().find(|s| s.name == "test_3").unwrap();
 
         assert_eq!(tf1.opcode_hash, tf2.opcode_hash);
-        // TODO need to figure out what to do when we have no relocations
-        //assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
+        assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
         assert_ne!(tf1.exact_hash, tf2.exact_hash);
 
         assert_eq!(tf1.opcode_hash, tf3.opcode_hash);
@@ -248,8 +1137,7 @@ mod tests {
         let tf3 = symbols.iter().find(|s| s.name == "test_3").unwrap();
 
         assert_eq!(tf1.opcode_hash, tf2.opcode_hash);
-        // TODO need to figure out what to do when we have no relocations
-        //assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
+        assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
         assert_ne!(tf1.exact_hash, tf2.exact_hash);
 
         assert_eq!(tf1.opcode_hash, tf3.opcode_hash);
@@ -263,6 +1151,655 @@ mod tests {
         assert_eq!(math_op_1.exact_hash, math_op_1_dup.exact_hash);
     }
 
+    #[test]
+    fn test_n64_rom() {
+        let z64 = vec![0x80, 0x37, 0x12, 0x40, 1, 2, 3, 4];
+        let v64 = vec![0x37, 0x80, 0x40, 0x12, 2, 1, 4, 3];
+        let n64 = vec![0x40, 0x12, 0x37, 0x80, 4, 3, 2, 1];
+        assert_eq!(
+            n64::detect_byte_order(&z64),
+            Some(n64::RomByteOrder::BigEndian)
+        );
+        assert_eq!(
+            n64::detect_byte_order(&v64),
+            Some(n64::RomByteOrder::ByteSwapped)
+        );
+        assert_eq!(
+            n64::detect_byte_order(&n64),
+            Some(n64::RomByteOrder::LittleEndian)
+        );
+        assert_eq!(n64::to_big_endian(v64).unwrap(), z64);
+        assert_eq!(n64::to_big_endian(n64).unwrap(), z64);
+        assert!(n64::to_big_endian(vec![0x37, 0x80, 0x40, 0x12, 1]).is_err());
+
+        // Headerless data is left alone
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let rom_bytes = fs::read(d.join("../../test/simple_mips_raw.bin")).unwrap();
+        let map_str = fs::read_to_string(d.join("../../test/simple_mips.map")).unwrap();
+        let symbols = read_n64_rom(None, rom_bytes, &map_str).unwrap();
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        assert!(tf1.segment.is_some());
+        assert!(symbols.iter().all(|s| s.segment == tf1.segment));
+    }
+
+    #[test]
+    fn test_simple_mips_semantic() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        // test_1 and test_2 are equivalent, but call math_op_1 and math_op_2 the other way around
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let tf2 = symbols.iter().find(|s| s.name == "test_2").unwrap();
+        assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
+        assert!(tf1.semantic_hash.is_some());
+        assert_ne!(tf1.semantic_hash, tf2.semantic_hash);
+
+        let math_op_1 = symbols.iter().find(|s| s.name == "math_op_1").unwrap();
+        let math_op_1_dup = symbols.iter().find(|s| s.name == "math_op_1_dup").unwrap();
+        assert!(math_op_1.semantic_hash.is_some());
+        assert_eq!(math_op_1.semantic_hash, math_op_1_dup.semantic_hash);
+
+        // math_op_2 doesn't refer to anything
+        let math_op_2 = symbols.iter().find(|s| s.name == "math_op_2").unwrap();
+        assert_eq!(math_op_2.semantic_hash, None);
+
+        assert!(semantic::is_well_known_name("osSetIntMask"));
+        assert!(semantic::is_well_known_name("Lead"));
+        assert!(!semantic::is_well_known_name("func_80012345"));
+        assert!(!semantic::is_well_known_name("func_80012345_A1B2C0"));
+        assert!(!semantic::is_well_known_name("D_80001234"));
+        assert!(!semantic::is_well_known_name(".L80001234"));
+    }
+
+    #[test]
+    fn test_simple_mips_pseudo_relocs() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let obj_symbols = read_elf(
+            Platform::N64,
+            &None,
+            &fs::read(d.join("../../test/simple_mips.o")).unwrap(),
+        )
+        .unwrap();
+        let linked_symbols = read_elf(
+            Platform::N64,
+            &None,
+            &fs::read(d.join("../../test/simple_mips_linked.o")).unwrap(),
+        )
+        .unwrap();
+        let map_symbols = read_map(
+            Platform::N64,
+            None,
+            fs::read(d.join("../../test/simple_mips_raw.bin")).unwrap(),
+            &fs::read_to_string(d.join("../../test/simple_mips.map")).unwrap(),
+        )
+        .unwrap();
+
+        // recovered relocations should hash the same as the real ones
+        for name in ["test_1", "test_3", "math_op_1"] {
+            let obj = obj_symbols.iter().find(|s| s.name == name).unwrap();
+            let linked = linked_symbols.iter().find(|s| s.name == name).unwrap();
+            let map = map_symbols.iter().find(|s| s.name == name).unwrap();
+
+            assert_eq!(obj.equiv_hash, linked.equiv_hash);
+            assert_eq!(obj.equiv_hash, map.equiv_hash);
+        }
+    }
+
+    #[test]
+    fn test_simple_ppc_pseudo_relocs() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let obj_symbols = read_elf(
+            Platform::GcWii,
+            &None,
+            &fs::read(d.join("../../test/simple_ppc_relocs.o")).unwrap(),
+        )
+        .unwrap();
+        let linked_symbols = read_elf(
+            Platform::GcWii,
+            &None,
+            &fs::read(d.join("../../test/simple_ppc_relocs_linked.o")).unwrap(),
+        )
+        .unwrap();
+        let map_symbols = read_map(
+            Platform::GcWii,
+            None,
+            fs::read(d.join("../../test/simple_ppc_relocs_raw.bin")).unwrap(),
+            &fs::read_to_string(d.join("../../test/simple_ppc_relocs.map")).unwrap(),
+        )
+        .unwrap();
+
+        // recovered relocations should hash the same as the real ones, and code that only looks
+        // like it's relocated shouldn't be
+        for name in ["test_1", "test_2", "math_op_1", "float_bits", "mr_clobber"] {
+            let obj = obj_symbols.iter().find(|s| s.name == name).unwrap();
+            let linked = linked_symbols.iter().find(|s| s.name == name).unwrap();
+            let map = map_symbols.iter().find(|s| s.name == name).unwrap();
+
+            assert_eq!(obj.equiv_hash, linked.equiv_hash);
+            assert_eq!(obj.equiv_hash, map.equiv_hash);
+        }
+    }
+
+    #[test]
+    fn test_mips_regimm_delay_slots() {
+        // bgezal a0, 1
+        assert!(arch::mips_has_delay_slot(0x04910001));
+        // teqi a0, 1 shares bgezal's opcode but is a trap, not a branch
+        assert!(!arch::mips_has_delay_slot(0x048C0001));
+    }
+
+    #[test]
+    fn test_simple_mips_regalloc() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_regalloc.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        let ra1 = symbols.iter().find(|s| s.name == "regalloc_1").unwrap();
+        let ra2 = symbols.iter().find(|s| s.name == "regalloc_2").unwrap();
+        let ra3 = symbols.iter().find(|s| s.name == "regalloc_3").unwrap();
+        let ra4 = symbols.iter().find(|s| s.name == "regalloc_4").unwrap();
+
+        // same code with different temporaries
+        assert_ne!(ra1.equiv_hash, ra2.equiv_hash);
+        assert!(ra1.regalloc_hash.is_some());
+        assert_eq!(ra1.regalloc_hash, ra2.regalloc_hash);
+
+        // different dataflow
+        assert_ne!(ra1.regalloc_hash, ra3.regalloc_hash);
+
+        // $sp isn't renamed
+        assert_ne!(ra1.regalloc_hash, ra4.regalloc_hash);
+    }
+
+    #[test]
+    fn test_align_simple_mips() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let tf3 = symbols.iter().find(|s| s.name == "test_3").unwrap();
+
+        let same = crate::align::align_symbols(tf1, tf1);
+        assert_eq!(same.edits, 0);
+        assert_eq!(same.rows.len(), tf1.opcodes.len());
+        assert!(same.rows.iter().all(|r| !r.differs()));
+
+        // same opcodes, different operands
+        let alignment = crate::align::align_symbols(tf1, tf3);
+        assert_eq!(alignment.edits, 0);
+        assert_eq!(alignment.similarity(), 1.0);
+        assert!(alignment.operand_mismatches() > 0);
+    }
+
+    #[test]
+    fn test_similarity_report() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let tf3 = symbols.iter().find(|s| s.name == "test_3").unwrap();
+
+        let report = crate::diff_symbols(tf1, tf3, crate::SimilarityMetric::Opcode, 0.5).unwrap();
+        assert_eq!(report.opcode, 1.0);
+        assert!(report.equivalent < 1.0);
+        assert!(!report.exact);
+        assert_eq!(report.length_ratio, 1.0);
+
+        // the operands differ, so they fall short at the equivalent level
+        assert!(
+            crate::diff_symbols(tf1, tf3, crate::SimilarityMetric::Equivalent, 0.9999).is_none()
+        );
+
+        let math_op_1 = symbols.iter().find(|s| s.name == "math_op_1").unwrap();
+        let math_op_1_dup = symbols.iter().find(|s| s.name == "math_op_1_dup").unwrap();
+        let report = crate::diff_symbols(
+            math_op_1,
+            math_op_1_dup,
+            crate::SimilarityMetric::Opcode,
+            0.5,
+        )
+        .unwrap();
+        assert!(report.exact);
+        assert_eq!(report.equivalent, 1.0);
+        assert_eq!(report.reloc_diffs, 0);
+    }
+
+    #[test]
+    fn test_significance() {
+        use crate::significance::OpcodeBackground;
+
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+        let background = OpcodeBackground::new(&symbols);
+
+        // unseen opcodes carry the most information
+        let seen = symbols[0].opcodes[0];
+        assert!(background.opcode_bits(0xFFFF) > background.opcode_bits(seen));
+
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let tf3 = symbols.iter().find(|s| s.name == "test_3").unwrap();
+        let math_op_1 = symbols.iter().find(|s| s.name == "math_op_1").unwrap();
+        let math_op_1_dup = symbols.iter().find(|s| s.name == "math_op_1_dup").unwrap();
+
+        // matching opcodes are worth the same whatever the operands
+        let long = background.significance(tf1, tf3, symbols.len());
+        assert_eq!(long.bits, background.sequence_bits(&tf1.opcodes) as f32);
+
+        // the shorter identical pair is more likely to be a coincidence
+        assert!(math_op_1.opcodes.len() < tf1.opcodes.len());
+        let short = background.significance(math_op_1, math_op_1_dup, symbols.len());
+        assert!(short.evalue > long.evalue);
+    }
+
+    #[test]
+    fn test_containment() {
+        use crate::align::align_within;
+
+        assert_eq!(
+            align_within(&[1, 2, 3], &[5, 1, 2, 3, 6], 0),
+            Some((1, 4, 0))
+        );
+        assert_eq!(
+            align_within(&[1, 2, 3, 4], &[9, 1, 2, 7, 4, 9], 1),
+            Some((1, 5, 1))
+        );
+        assert_eq!(align_within(&[1, 2, 3, 4], &[9, 1, 2, 7, 4, 9], 0), None);
+
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        // test_1 inlined into a bigger function
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let mut outer = tf1.clone();
+        outer.opcodes = [vec![0xFFFF; 10], tf1.opcodes.clone(), vec![0xFFFF; 10]].concat();
+        let len = tf1.opcodes.len();
+
+        assert!(crate::diff_symbols(tf1, &outer, crate::SimilarityMetric::Opcode, 0.9).is_none());
+
+        let report =
+            crate::diff_symbols(tf1, &outer, crate::SimilarityMetric::Containment, 0.9).unwrap();
+        let containment = report.containment.unwrap();
+        assert!(containment.first_in_second);
+        assert_eq!((containment.start, containment.end), (10, 10 + len));
+        assert_eq!(containment.score, 1.0);
+
+        let report =
+            crate::diff_symbols(&outer, tf1, crate::SimilarityMetric::Containment, 0.9).unwrap();
+        assert!(!report.containment.unwrap().first_in_second);
+    }
+
+    #[test]
+    fn test_cfg_simple_mips() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        // blocks cover every instruction once, in order
+        for symbol in &symbols {
+            let blocks = &symbol.cfg.blocks;
+            assert!(!blocks.is_empty());
+            assert_eq!(blocks.first().unwrap().start, 0);
+            assert_eq!(blocks.last().unwrap().end, symbol.opcodes.len());
+            for (block, next) in blocks.iter().zip(blocks.iter().skip(1)) {
+                assert!(!block.is_empty());
+                assert_eq!(block.end, next.start);
+            }
+            assert!(
+                blocks
+                    .iter()
+                    .flat_map(|b| &b.successors)
+                    .all(|s| *s < blocks.len())
+            );
+        }
+
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let tf2 = symbols.iter().find(|s| s.name == "test_2").unwrap();
+
+        assert_eq!(crate::cfg::structural_similarity(tf1, tf1), 1.0);
+        assert_eq!(crate::cfg::structural_similarity(tf1, tf2), 1.0);
+
+        let block_matches = crate::cfg::match_blocks(tf1, tf2);
+        assert_eq!(block_matches.len(), tf1.cfg.blocks.len());
+        assert!(block_matches.iter().all(|m| m.similarity == 1.0));
+
+        let report =
+            crate::diff_symbols(tf1, tf2, crate::SimilarityMetric::Structural, 0.9).unwrap();
+        assert_eq!(report.structural, 1.0);
+    }
+
+    #[test]
+    fn test_scheduling() {
+        let canonical_hashes = |words: &[u32]| {
+            let bytes: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+            let opcodes = arch::get_opcodes_raw(&bytes, Platform::N64, Arch::Mips);
+            let hashes = arch::get_equivalence_hashes_raw(
+                &bytes,
+                0x80000000,
+                Platform::N64,
+                Arch::Mips,
+                None,
+            );
+            let flows = arch::get_insn_flows_raw(&bytes, 0x80000000, Platform::N64, Arch::Mips);
+            let cfg = Cfg::build(&flows);
+            schedule::canonical_order(&bytes, Platform::N64, &opcodes, &hashes, &flows, &cfg)
+                .iter()
+                .map(|&i| hashes[i])
+                .collect::<Vec<_>>()
+        };
+
+        // lw $t0, 0($a0); lw $t1, 4($a0); addu $v0, $t0, $t1; jr $ra; nop
+        let ido = canonical_hashes(&[0x8C880000, 0x8C890004, 0x01091021, 0x03E00008, 0]);
+        // the loads swapped and the addu in the delay slot
+        let gcc = canonical_hashes(&[0x8C890004, 0x8C880000, 0x03E00008, 0x01091021]);
+        assert_eq!(ido.len(), 4);
+        assert_eq!(ido, gcc);
+
+        // lw $t1, 0($t0) depends on the load of $t0, so the two can't be swapped
+        let dependent = canonical_hashes(&[0x8C880000, 0x8D090000, 0x03E00008, 0]);
+        let swapped = canonical_hashes(&[0x8D090000, 0x8C880000, 0x03E00008, 0]);
+        assert_ne!(dependent, swapped);
+
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+
+        for symbol in &symbols {
+            let mut order = symbol.canonical_order.clone();
+            order.sort();
+            order.dedup();
+            assert_eq!(order.len(), symbol.canonical_order.len());
+            assert!(order.iter().all(|&i| i < symbol.opcodes.len()));
+
+            let rescheduled = symbol.rescheduled();
+            assert_eq!(rescheduled.opcodes.len(), symbol.canonical_order.len());
+            assert_eq!(
+                rescheduled.cfg.blocks.last().unwrap().end,
+                rescheduled.opcodes.len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_callgraph_simple_mips() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let elf_bin = Binary {
+            name: "elf".to_string(),
+            symbols: read_elf(Platform::N64, &None, &elf_data).unwrap(),
+        };
+        let map_bin = Binary {
+            name: "map".to_string(),
+            symbols: read_map(
+                Platform::N64,
+                None,
+                fs::read(d.join("../../test/simple_mips_raw.bin")).unwrap(),
+                &fs::read_to_string(d.join("../../test/simple_mips.map")).unwrap(),
+            )
+            .unwrap(),
+        };
+
+        // calls are resolved through relocations in the object and by address in the raw binary,
+        // and the external function isn't part of either
+        for bin in [&elf_bin, &map_bin] {
+            let graph = bin.call_graph();
+            let idx = |name: &str| bin.symbols.iter().position(|s| s.name == name).unwrap();
+            let names = |idxs: &[usize]| -> Vec<String> {
+                idxs.iter().map(|i| bin.symbols[*i].name.clone()).collect()
+            };
+
+            assert_eq!(
+                names(graph.callees(idx("test_1"))),
+                ["math_op_1", "math_op_2"]
+            );
+            assert_eq!(names(graph.callees(idx("test_3"))), ["math_op_1"]);
+            assert_eq!(
+                names(graph.callers(idx("math_op_1"))),
+                ["test_1", "test_2", "test_3"]
+            );
+            assert!(graph.callers(idx("math_op_1_dup")).is_empty());
+        }
+
+        // propagating from test_1 reaches everything connected to it, and nothing else
+        let elf_bin2 = Binary {
+            name: "elf2".to_string(),
+            symbols: read_elf(Platform::N64, &None, &elf_data).unwrap(),
+        };
+        let test_1 = elf_bin
+            .symbols
+            .iter()
+            .position(|s| s.name == "test_1")
+            .unwrap();
+        let propagated = crate::callgraph::propagate_matches(
+            &elf_bin,
+            &elf_bin.call_graph(),
+            &elf_bin2,
+            &elf_bin2.call_graph(),
+            &[(test_1, test_1)],
+            crate::SimilarityMetric::Opcode,
+            0.5,
+        );
+
+        let mut names: Vec<&str> = propagated
+            .iter()
+            .map(|m| {
+                assert_eq!(m.symbol1, m.symbol2);
+                elf_bin.symbols[m.symbol1].name.as_str()
+            })
+            .collect();
+        names.sort();
+        assert_eq!(names, ["math_op_1", "math_op_2", "test_2", "test_3"]);
+    }
+
+    #[test]
+    fn test_submatches() {
+        // every occurrence is found, and runs only continue along a diagonal
+        let matches = crate::get_submatches(&[1, 2, 3], &[9, 1, 2, 3, 1, 2, 7, 3], 2);
+        assert_eq!(
+            matches,
+            vec![
+                InsnSeqMatch {
+                    offset1: 0,
+                    offset2: 1,
+                    length: 4
+                },
+                InsnSeqMatch {
+                    offset1: 0,
+                    offset2: 4,
+                    length: 3
+                },
+                InsnSeqMatch {
+                    offset1: 2,
+                    offset2: 7,
+                    length: 2
+                },
+            ]
+        );
+
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+        let binary = Binary {
+            name: "simple_mips".to_string(),
+            symbols: read_elf(Platform::N64, &None, &elf_data).unwrap(),
+        };
+
+        let mut index = crate::submatch::SubmatchIndex::new(4);
+        index.add_binary(&binary);
+
+        let tf1 = binary.symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let results = index.query(tf1);
+        let tf2 = results.iter().find(|r| r.symbol.name == "test_2").unwrap();
+        assert!(tf2.matches.contains(&InsnSeqMatch {
+            offset1: 0,
+            offset2: 0,
+            length: tf1.opcodes.len()
+        }));
+    }
+
+    #[test]
+    fn test_gapped_submatches() {
+        use crate::submatch::{GapScoring, GappedMatch, chain_runs, extend_runs};
+
+        let a: Vec<u16> = (0..20).collect();
+        let runs_for = |b: &[u16]| {
+            crate::get_submatches(&crate::get_hashes(&a, 4), &crate::get_hashes(b, 4), 4)
+        };
+        let scoring = GapScoring::default();
+
+        // one substituted instruction
+        let mut b = a.clone();
+        b[10] = 100;
+        let expected = GappedMatch {
+            offset1: 0,
+            length1: 20,
+            offset2: 0,
+            length2: 20,
+            matches: 19,
+            edits: 1,
+        };
+        assert_eq!(chain_runs(&runs_for(&b), &scoring), vec![expected]);
+        assert_eq!(extend_runs(&a, &b, &runs_for(&b), &scoring), vec![expected]);
+        assert_eq!(expected.identity(), 0.95);
+
+        // one inserted instruction, plus a mismatch too close to the start for a run to reach
+        let mut b = a.clone();
+        b.insert(10, 100);
+        b[2] = 100;
+        let regions = extend_runs(&a, &b, &runs_for(&b), &scoring);
+        assert_eq!(
+            regions,
+            vec![GappedMatch {
+                offset1: 0,
+                length1: 20,
+                offset2: 0,
+                length2: 21,
+                matches: 19,
+                edits: 2,
+            }]
+        );
+
+        // too many differences to bridge
+        let mut b = a.clone();
+        b[8..13].copy_from_slice(&[100; 5]);
+        assert_eq!(chain_runs(&runs_for(&b), &scoring).len(), 2);
+    }
+
+    /// A PPC function that loads the address of `target` and calls 0x80005000: lis r3, 0;
+    /// addi r3, r3, 0; bl 0; blr
+    fn rel_module(target: u32) -> Vec<u8> {
+        let mut rel = vec![0u8; 0x90];
+        let mut put = |offset: usize, value: u32| {
+            rel[offset..offset + 4].copy_from_slice(&value.to_be_bytes())
+        };
+        put(0x00, 1); // module id
+        put(0x0C, 2); // number of sections
+        put(0x10, 0x40); // section info
+        put(0x1C, 1); // version
+        put(0x28, 0x60); // imports
+        put(0x2C, 8);
+        put(0x48, 0x50 | 1); // executable section 1
+        put(0x4C, 16);
+        for (i, insn) in [0x3C600000, 0x38630000, 0x48000001, 0x4E800020]
+            .into_iter()
+            .enumerate()
+        {
+            put(0x50 + i * 4, insn);
+        }
+        put(0x60, 0); // relocations against the DOL
+        put(0x64, 0x68);
+
+        let relocs: [(u16, u8, u8, u32); 5] = [
+            (0, 202, 1, 0),
+            (2, 6, 0, target),
+            (4, 4, 0, target),
+            (2, 10, 0, 0x80005000),
+            (0, 203, 0, 0),
+        ];
+        for (i, (offset, kind, section, addend)) in relocs.into_iter().enumerate() {
+            let entry = 0x68 + i * 8;
+            rel[entry..entry + 2].copy_from_slice(&offset.to_be_bytes());
+            rel[entry + 2] = kind;
+            rel[entry + 3] = section;
+            rel[entry + 4..entry + 8].copy_from_slice(&addend.to_be_bytes());
+        }
+        rel
+    }
+
+    #[test]
+    fn test_gcwii_binaries() {
+        let map_str = "\
+.text section layout
+  Starting        Virtual
+  address  Size   address
+  -----------------------
+  00000000 000010 80003100  4 .text \tmain.o
+  00000000 000008 80003100  4 func_a \tmain.o
+  00000000 000000 80003100    alias_a (entry of func_a) \tmain.o
+  UNUSED   000020 ........ unused_func main.o
+  00000008 000008 80003108 00000108  4 func_b \tlib.a util.o
+";
+        let map = CwMap::parse(map_str);
+        assert!(cwmap::is_cw_map(map_str));
+        let names: Vec<&str> = map.functions().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["func_a", "func_b"]);
+        assert_eq!(map.symbols.last().unwrap().object, "lib.a util.o");
+
+        // a DOL with a single text section at 0x80003100: li r3, 1; blr; li r3, 2; blr
+        let mut dol = vec![0u8; 0x110];
+        dol[0x00..0x04].copy_from_slice(&0x100u32.to_be_bytes());
+        dol[0x48..0x4C].copy_from_slice(&0x80003100u32.to_be_bytes());
+        dol[0x90..0x94].copy_from_slice(&0x10u32.to_be_bytes());
+        for (i, insn) in [0x38600001u32, 0x4E800020, 0x38600002, 0x4E800020]
+            .into_iter()
+            .enumerate()
+        {
+            dol[0x100 + i * 4..0x104 + i * 4].copy_from_slice(&insn.to_be_bytes());
+        }
+
+        assert!(!rel::is_rel(&dol));
+        let symbols = read_target(Platform::GcWii, None, dol, Some(map_str)).unwrap();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[1].name, "func_b");
+        assert_eq!(symbols[1].vram, 0x80003108);
+        assert_eq!(symbols[0].opcode_hash, symbols[1].opcode_hash);
+        assert_ne!(symbols[0].equiv_hash, symbols[1].equiv_hash);
+
+        let rel_map = "\
+.text section layout
+  Starting        Virtual
+  address  Size   address
+  -----------------------
+  00000000 000010 00000000  4 load_thing \tthing.o
+";
+        let rel_1 = rel_module(0x80001234);
+        let rel_2 = rel_module(0x80005678);
+        assert!(rel::is_rel(&rel_1));
+
+        let thing_1 = read_target(Platform::GcWii, None, rel_1, Some(rel_map))
+            .unwrap()
+            .remove(0);
+        let thing_2 = read_target(Platform::GcWii, None, rel_2, Some(rel_map))
+            .unwrap()
+            .remove(0);
+
+        // relocations are applied, and the code is addressed by its offset in the file
+        assert_eq!(thing_1.vram, 0x50);
+        assert_eq!(
+            thing_1.bytes[..8],
+            [0x3C, 0x60, 0x80, 0x00, 0x38, 0x63, 0x12, 0x34]
+        );
+
+        // the relocated operands are left out of the equivalent hash
+        assert!(thing_1.insn_reloc_targets[..3].iter().all(Option::is_some));
+        assert_eq!(thing_1.insn_reloc_targets[3], None);
+        assert_ne!(thing_1.exact_hash, thing_2.exact_hash);
+        assert_eq!(thing_1.equiv_hash, thing_2.equiv_hash);
+    }
+
     #[test]
     fn test_simple_ppc() {
         let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -315,6 +1852,59 @@ mod tests {
         assert_eq!(math_op_1.exact_hash, math_op_1_dup.exact_hash);
     }
 
+    #[test]
+    fn test_simple_x86() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let coff_data = fs::read(d.join("../../test/simple_x86.obj")).unwrap();
+        let symbols = read_object(Platform::Win32, &None, &coff_data).unwrap();
+        assert!(!symbols.is_empty());
+
+        let tf1 = symbols.iter().find(|s| s.name == "_test_1").unwrap();
+        let tf2 = symbols.iter().find(|s| s.name == "_test_2").unwrap();
+        let tf3 = symbols.iter().find(|s| s.name == "_test_3").unwrap();
+
+        assert_eq!(tf1.opcode_hash, tf2.opcode_hash);
+        assert_eq!(tf1.equiv_hash, tf2.equiv_hash);
+        assert_eq!(tf1.exact_hash, tf2.exact_hash);
+
+        assert_eq!(tf1.opcode_hash, tf3.opcode_hash);
+        assert_ne!(tf1.equiv_hash, tf3.equiv_hash);
+        assert_eq!(tf1.exact_hash, tf3.exact_hash);
+
+        let math_op_1 = symbols.iter().find(|s| s.name == "_math_op_1").unwrap();
+        let math_op_1_dup = symbols.iter().find(|s| s.name == "_math_op_1_dup").unwrap();
+        assert_eq!(math_op_1.opcode_hash, math_op_1_dup.opcode_hash);
+        assert_eq!(math_op_1.equiv_hash, math_op_1_dup.equiv_hash);
+        assert_eq!(math_op_1.exact_hash, math_op_1_dup.exact_hash);
+    }
+
+    #[test]
+    fn test_simple_x86_pseudo_relocs() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let obj_symbols = read_object(
+            Platform::Win32,
+            &None,
+            &fs::read(d.join("../../test/simple_x86_relocs.obj")).unwrap(),
+        )
+        .unwrap();
+        let map_symbols = read_map(
+            Platform::Win32,
+            None,
+            fs::read(d.join("../../test/simple_x86_relocs_raw.bin")).unwrap(),
+            &fs::read_to_string(d.join("../../test/simple_x86_relocs.map")).unwrap(),
+        )
+        .unwrap();
+
+        // addresses in immediates (`push offset x`, `mov reg, offset x`) should hash the same as
+        // their relocations
+        for name in ["_push_offsets", "_mov_offsets"] {
+            let obj = obj_symbols.iter().find(|s| s.name == name).unwrap();
+            let map = map_symbols.iter().find(|s| s.name == name).unwrap();
+
+            assert_eq!(obj.equiv_hash, map.equiv_hash);
+        }
+    }
+
     #[test]
     fn test_simple_gba() {
         let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
@@ -339,5 +1929,674 @@ mod tests {
         assert_eq!(math_op_1.opcode_hash, math_op_1_dup.opcode_hash);
         assert_eq!(math_op_1.equiv_hash, math_op_1_dup.equiv_hash);
         assert_eq!(math_op_1.exact_hash, math_op_1_dup.exact_hash);
+
+        // registers aren't renamed for Thumb
+        assert_eq!(math_op_1.regalloc_hash, None);
+    }
+
+    #[test]
+    fn test_simple_arm() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_arm.o")).unwrap();
+        let symbols = read_elf(Platform::Nds, &None, &elf_data).unwrap();
+
+        let arm_1 = symbols.iter().find(|s| s.name == "arm_1").unwrap();
+        let arm_2 = symbols.iter().find(|s| s.name == "arm_2").unwrap();
+        let thumb_1 = symbols.iter().find(|s| s.name == "thumb_1").unwrap();
+        let thumb_2 = symbols.iter().find(|s| s.name == "thumb_2").unwrap();
+        let thumb_3 = symbols.iter().find(|s| s.name == "thumb_3").unwrap();
+
+        // the loaded constant differs
+        assert_eq!(arm_1.opcode_hash, arm_2.opcode_hash);
+        assert_ne!(arm_1.equiv_hash, arm_2.equiv_hash);
+        assert_eq!(thumb_1.opcode_hash, thumb_2.opcode_hash);
+        assert_ne!(thumb_1.equiv_hash, thumb_2.equiv_hash);
+
+        // the literal pool isn't hashed as an instruction, its value is hashed by the load
+        assert_eq!(
+            arm_1.insn_equiv_hashes.last(),
+            arm_2.insn_equiv_hashes.last()
+        );
+
+        // only an operand differs
+        assert_eq!(thumb_1.opcode_hash, thumb_3.opcode_hash);
+        assert_ne!(thumb_1.equiv_hash, thumb_3.equiv_hash);
+        assert_ne!(arm_1.opcode_hash, thumb_1.opcode_hash);
+
+        // linked functions without a Thumb bit are told apart by their code
+        assert_eq!(
+            raw_function_arch(Platform::Nds, false, &arm_1.bytes),
+            Arch::Arm
+        );
+        assert_eq!(
+            raw_function_arch(Platform::Nds, false, &thumb_1.bytes),
+            Arch::Thumb
+        );
+        assert_eq!(
+            raw_function_arch(Platform::Nds, true, &arm_1.bytes),
+            Arch::Thumb
+        );
+        assert_eq!(
+            arch::get_opcodes_raw(&thumb_1.bytes[..6], Platform::Nds, Arch::Thumb),
+            arch::get_opcodes_raw(&thumb_3.bytes[..6], Platform::Nds, Arch::Thumb)
+        );
+        assert_ne!(
+            arch::get_equivalence_hashes_raw(&thumb_1.bytes, 0, Platform::Nds, Arch::Thumb, None),
+            arch::get_equivalence_hashes_raw(&thumb_3.bytes, 0, Platform::Nds, Arch::Thumb, None)
+        );
+    }
+
+    #[test]
+    fn test_simple_psx() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_psx.o")).unwrap();
+        let symbols = read_elf(Platform::Psx, &None, &elf_data).unwrap();
+        assert!(!symbols.is_empty());
+
+        let gte_1 = symbols.iter().find(|s| s.name == "gte_1").unwrap();
+        let gte_2 = symbols.iter().find(|s| s.name == "gte_2").unwrap();
+        let gte_3 = symbols.iter().find(|s| s.name == "gte_3").unwrap();
+        let gte_4 = symbols.iter().find(|s| s.name == "gte_4").unwrap();
+
+        // the sf flag changes the result
+        assert_eq!(gte_1.opcode_hash, gte_2.opcode_hash);
+        assert_ne!(gte_1.equiv_hash, gte_2.equiv_hash);
+
+        // relocated offsets are masked
+        assert_eq!(gte_3.opcode_hash, gte_4.opcode_hash);
+        assert_eq!(gte_3.equiv_hash, gte_4.equiv_hash);
+        assert_ne!(gte_3.exact_hash, gte_4.exact_hash);
+    }
+
+    #[test]
+    fn test_archive() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_mips.o")).unwrap();
+
+        let mut archive = b"!<arch>\n".to_vec();
+        for (name, data) in [
+            ("simple_mips.o/", elf_data.as_slice()),
+            ("notes.txt/", b"not an object".as_slice()),
+        ] {
+            archive.extend(
+                format!(
+                    "{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n",
+                    name,
+                    0,
+                    0,
+                    0,
+                    644,
+                    data.len()
+                )
+                .bytes(),
+            );
+            archive.extend_from_slice(data);
+            if data.len() % 2 == 1 {
+                archive.push(b'\n');
+            }
+        }
+
+        let members = archive_members(&archive).unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].0, "simple_mips.o");
+
+        let symbols = read_archive(Platform::N64, "libsimple.a", &archive).unwrap();
+        let obj_symbols = read_elf(Platform::N64, &None, &elf_data).unwrap();
+        assert_eq!(symbols.len(), obj_symbols.len());
+
+        let tf1 = symbols.iter().find(|s| s.name == "test_1").unwrap();
+        let library = tf1.library.as_ref().unwrap();
+        assert_eq!(library.archive, "libsimple.a");
+        assert_eq!(library.member, "simple_mips.o");
+        assert_eq!(
+            tf1.equiv_hash,
+            obj_symbols
+                .iter()
+                .find(|s| s.name == "test_1")
+                .unwrap()
+                .equiv_hash
+        );
+    }
+
+    #[test]
+    fn test_nds_rom() {
+        // mov r0, r0 seven times, as four literals and two back-references read from the end
+        let compressed = [
+            0x01, 0x30, 0x01, 0xF0, 0x00, 0x00, 0xA0, 0xE1, 0x0C, 17, 0, 0, 8, 11, 0, 0, 0,
+        ];
+        let decompressed = [0x00, 0x00, 0xA0, 0xE1].repeat(7);
+        assert_eq!(nds::blz_decompress(&compressed).unwrap(), decompressed);
+
+        let put = |rom: &mut Vec<u8>, offset: usize, words: &[u32]| {
+            for (i, word) in words.iter().enumerate() {
+                rom[offset + i * 4..offset + i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+            }
+        };
+
+        // an ARM9 binary with two functions and two overlays loaded at the same address, the
+        // first compressed, and an ARM7 binary with a Thumb function
+        let mut rom = vec![0u8; 0x298];
+        rom[0x15C..0x15E].copy_from_slice(&0xCF56u16.to_le_bytes());
+        put(&mut rom, 0x20, &[0x200, 0, 0x02000000, 0x10]);
+        put(&mut rom, 0x30, &[0x290, 0, 0x037F8000, 0x8]);
+        put(&mut rom, 0x48, &[0x250, 0x10, 0x210, 0x40]);
+        put(
+            &mut rom,
+            0x200,
+            &[0xE3A00001, 0xE12FFF1E, 0xE3A00002, 0xE12FFF1E],
+        );
+        put(
+            &mut rom,
+            0x210,
+            &[0, 0x02100000, 0x1C, 0, 0, 0, 0, (1 << 24) | 17],
+        );
+        put(&mut rom, 0x230, &[1, 0x02100000, 0x8, 0, 0, 0, 1, 0]);
+        put(&mut rom, 0x250, &[0x260, 0x271, 0x280, 0x288]);
+        rom[0x260..0x271].copy_from_slice(&compressed);
+        put(&mut rom, 0x280, &[0xE3A00001, 0xE12FFF1E]);
+        // movs r0, #1; b 1f; 1: bx lr; b .
+        put(&mut rom, 0x290, &[0xE7FF2001, 0xE7FE4770]);
+
+        let nds_rom = NdsRom::parse(&rom).unwrap();
+        let names: Vec<&str> = nds_rom.segments.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["arm9", "arm7", "ov000", "ov001"]);
+        assert_eq!(
+            nds_rom
+                .segment_for_map_name("overlay_1", 0x02100000)
+                .unwrap()
+                .name,
+            "ov001"
+        );
+        assert_eq!(
+            nds_rom
+                .segment_for_map_name("main", 0x02000004)
+                .unwrap()
+                .name,
+            "arm9"
+        );
+
+        let symbol_list = "\
+func_a = 0x02000000; // type:func size:0x8
+func_b = 0x02000008; // type:func
+ov0_func = 0x02100000; // type:func rom:0x260
+ov1_func = 0x02100000; // type:func rom:0x280
+thumb_func kind:function(thumb,size=0x8) addr:0x037f8000
+";
+        let symbols = read_target(Platform::Nds, None, rom, Some(symbol_list)).unwrap();
+        let segments: Vec<Option<&str>> = symbols.iter().map(|s| s.segment.as_deref()).collect();
+        assert_eq!(
+            segments,
+            vec![
+                Some("arm9"),
+                Some("arm9"),
+                Some("ov000"),
+                Some("ov001"),
+                Some("arm7")
+            ]
+        );
+
+        // overlays are read from their own segments, decompressed
+        let func_a = &symbols[0];
+        let (ov0_func, ov1_func) = (&symbols[2], &symbols[3]);
+        assert_eq!(symbols[1].bytes.len(), 8);
+        assert_eq!(ov0_func.bytes, decompressed);
+        assert_eq!(ov0_func.vram, ov1_func.vram);
+        assert_eq!(ov1_func.exact_hash, func_a.exact_hash);
+
+        // the listed instruction set is used, though the code looks like ARM going by its words
+        let thumb_func = &symbols[4];
+        assert_eq!(
+            raw_function_arch(Platform::Nds, false, &thumb_func.bytes),
+            Arch::Arm
+        );
+        assert_eq!(thumb_func.opcodes.len(), 4);
+        assert_eq!(
+            thumb_func.opcodes,
+            arch::get_opcodes_raw(&thumb_func.bytes, Platform::Nds, Arch::Thumb)
+        );
+    }
+
+    #[test]
+    fn test_psx_exe() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_psx.o")).unwrap();
+        let obj_symbols = read_elf(Platform::Psx, &None, &elf_data).unwrap();
+        let text = object::File::parse(&*elf_data)
+            .unwrap()
+            .section_by_name(".text")
+            .unwrap()
+            .data()
+            .unwrap()
+            .to_vec();
+
+        // the object's code, loaded at 0x80010000
+        let mut exe = vec![0u8; 0x800];
+        exe[..8].copy_from_slice(b"PS-X EXE");
+        exe[0x10..0x14].copy_from_slice(&0x80010000u32.to_le_bytes());
+        exe[0x18..0x1C].copy_from_slice(&0x80010000u32.to_le_bytes());
+        exe[0x1C..0x20].copy_from_slice(&(text.len() as u32).to_le_bytes());
+        exe.extend_from_slice(&text);
+        assert_eq!(
+            executable::detect_format(&exe),
+            Some(executable::ExecutableFormat::PsxExe)
+        );
+        assert!(!executable::is_executable(&elf_data));
+
+        // without a symbol list, functions are found after each return
+        let scanned = read_target(Platform::Psx, None, exe.clone(), None).unwrap();
+        let vrams: Vec<usize> = scanned.iter().map(|s| s.vram).collect();
+        assert_eq!(vrams, vec![0x80010000, 0x8001000C, 0x80010018, 0x8001002C]);
+        assert_eq!(scanned[1].name, "func_8001000C");
+        for (scanned, name) in scanned.iter().zip(["gte_1", "gte_2", "gte_3", "gte_4"]) {
+            let obj = obj_symbols.iter().find(|s| s.name == name).unwrap();
+            assert_eq!(scanned.opcode_hash, obj.opcode_hash);
+        }
+
+        // functions without a size run up to the next symbol or the end of the code
+        let symbol_list = "\
+gte_1 = 0x80010000; // type:func size:0xC
+gte_3 = 0x80010018;
+gte_data = 0x80020000; // type:s32
+";
+        let listed = read_target(Platform::Psx, None, exe, Some(symbol_list)).unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].name, "gte_1");
+        assert_eq!(listed[0].opcode_hash, scanned[0].opcode_hash);
+        assert_eq!(listed[1].vram, 0x80010018);
+        assert_eq!(listed[1].bytes.len(), 0x28);
+    }
+
+    #[test]
+    fn test_simple_ps2() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_ps2.o")).unwrap();
+        let symbols = read_elf(Platform::Ps2, &None, &elf_data).unwrap();
+        assert!(!symbols.is_empty());
+
+        let vu_1 = symbols.iter().find(|s| s.name == "vu_1").unwrap();
+        let vu_2 = symbols.iter().find(|s| s.name == "vu_2").unwrap();
+        let vu_3 = symbols.iter().find(|s| s.name == "vu_3").unwrap();
+        let vu_4 = symbols.iter().find(|s| s.name == "vu_4").unwrap();
+
+        // the field mask changes the result
+        assert_eq!(vu_1.opcode_hash, vu_2.opcode_hash);
+        assert_ne!(vu_1.equiv_hash, vu_2.equiv_hash);
+
+        // so does the destination register
+        assert_eq!(vu_1.opcode_hash, vu_3.opcode_hash);
+        assert_ne!(vu_1.equiv_hash, vu_3.equiv_hash);
+
+        assert_eq!(vu_1.equiv_hash, vu_4.equiv_hash);
+        assert_eq!(vu_1.exact_hash, vu_4.exact_hash);
+    }
+
+    #[test]
+    fn test_simple_psp() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_psp.o")).unwrap();
+        let symbols = read_elf(Platform::Psp, &None, &elf_data).unwrap();
+        assert!(!symbols.is_empty());
+
+        let vfpu_1 = symbols.iter().find(|s| s.name == "vfpu_1").unwrap();
+        let vfpu_2 = symbols.iter().find(|s| s.name == "vfpu_2").unwrap();
+        let vfpu_3 = symbols.iter().find(|s| s.name == "vfpu_3").unwrap();
+        let vfpu_4 = symbols.iter().find(|s| s.name == "vfpu_4").unwrap();
+        let vfpu_5 = symbols.iter().find(|s| s.name == "vfpu_5").unwrap();
+        let vfpu_6 = symbols.iter().find(|s| s.name == "vfpu_6").unwrap();
+
+        // VFPU registers are hashed
+        assert_eq!(vfpu_1.opcode_hash, vfpu_2.opcode_hash);
+        assert_ne!(vfpu_1.equiv_hash, vfpu_2.equiv_hash);
+
+        // lv.s offsets are masked when relocated...
+        assert_eq!(vfpu_3.opcode_hash, vfpu_4.opcode_hash);
+        assert_eq!(vfpu_3.equiv_hash, vfpu_4.equiv_hash);
+        assert_ne!(vfpu_3.exact_hash, vfpu_4.exact_hash);
+
+        // ...and hashed otherwise
+        assert_eq!(vfpu_5.opcode_hash, vfpu_6.opcode_hash);
+        assert_ne!(vfpu_5.equiv_hash, vfpu_6.equiv_hash);
+    }
+
+    #[test]
+    fn test_raw_thumb_bl_pair() {
+        // push {lr}; bl <func>; pop {pc}
+        let bytes = [0x00, 0xB5, 0x00, 0xF0, 0x00, 0xF8, 0x00, 0xBD];
+
+        let offsets = arch::get_insn_offsets_raw(&bytes, Platform::Gba, Arch::Thumb);
+        assert_eq!(offsets, vec![0, 2, 6]);
+
+        let opcodes = arch::get_opcodes_raw(&bytes, Platform::Gba, Arch::Thumb);
+        assert_eq!(opcodes.len(), 3);
+    }
+
+    #[test]
+    fn test_raw_arm_operands() {
+        // mov r0, #1; bx lr
+        let thumb_r0 = [0x01, 0x20, 0x70, 0x47];
+        // mov r1, #1; bx lr
+        let thumb_r1 = [0x01, 0x21, 0x70, 0x47];
+
+        assert_eq!(
+            arch::get_opcodes_raw(&thumb_r0, Platform::Gba, Arch::Thumb),
+            arch::get_opcodes_raw(&thumb_r1, Platform::Gba, Arch::Thumb)
+        );
+        assert_ne!(
+            arch::get_equivalence_hashes_raw(&thumb_r0, 0, Platform::Gba, Arch::Thumb, None),
+            arch::get_equivalence_hashes_raw(&thumb_r1, 0, Platform::Gba, Arch::Thumb, None)
+        );
+
+        // moveq r0, #1; bx lr
+        let arm_eq = [0x01, 0x00, 0xA0, 0x03, 0x1E, 0xFF, 0x2F, 0xE1];
+        // movne r0, #1; bx lr
+        let arm_ne = [0x01, 0x00, 0xA0, 0x13, 0x1E, 0xFF, 0x2F, 0xE1];
+
+        assert_eq!(
+            arch::get_opcodes_raw(&arm_eq, Platform::Nds, Arch::Arm),
+            arch::get_opcodes_raw(&arm_ne, Platform::Nds, Arch::Arm)
+        );
+        assert_ne!(
+            arch::get_equivalence_hashes_raw(&arm_eq, 0, Platform::Nds, Arch::Arm, None),
+            arch::get_equivalence_hashes_raw(&arm_ne, 0, Platform::Nds, Arch::Arm, None)
+        );
+    }
+
+    #[test]
+    fn test_raw_arm_literal_pointers() {
+        // ldr r0, [pc, #0]; bx lr; .word literal
+        let thumb = |literal: u32| -> Vec<u8> {
+            [0x00, 0x48, 0x70, 0x47]
+                .into_iter()
+                .chain(literal.to_le_bytes())
+                .collect()
+        };
+        let thumb_hashes = |literal: u32| {
+            arch::get_equivalence_hashes_raw(&thumb(literal), 0, Platform::Gba, Arch::Thumb, None)
+        };
+
+        // pointers stand for the relocation they had, other literals are hashed by value
+        assert_eq!(thumb_hashes(0x0300_0010), thumb_hashes(0x0300_0020));
+        assert_ne!(thumb_hashes(0x1234), thumb_hashes(0x5678));
+        assert_ne!(thumb_hashes(0x0300_0010), thumb_hashes(0x1234));
+
+        // ldr r0, [pc, #0]; bx lr; .word literal
+        let arm = |literal: u32| -> Vec<u8> {
+            [0x00, 0x00, 0x9F, 0xE5, 0x1E, 0xFF, 0x2F, 0xE1]
+                .into_iter()
+                .chain(literal.to_le_bytes())
+                .collect()
+        };
+        let arm_hashes = |literal: u32| {
+            arch::get_equivalence_hashes_raw(&arm(literal), 0, Platform::Nds, Arch::Arm, None)
+        };
+
+        assert_eq!(arm_hashes(0x0200_0010), arm_hashes(0x0200_0020));
+        assert_ne!(arm_hashes(0x1234), arm_hashes(0x5678));
+        assert_eq!(
+            arch::get_reloc_targets_raw(&arm(0x0200_0010), 0, Platform::Nds, Arch::Arm, None)[2],
+            Some(stable_hash(&0x0200_0010u64))
+        );
+    }
+
+    #[test]
+    fn test_aarch64_opcodes() {
+        let words =
+            |words: &[u32]| -> Vec<u8> { words.iter().flat_map(|w| w.to_le_bytes()).collect() };
+
+        // b.eq, b.ne; bl; movz; movk; stp; ldp; fadd; fmul; add; sub; adrp; ldr; ret
+        let opcodes = arch::get_opcodes_raw(
+            &words(&[
+                0x54000040, 0x54000041, 0x94000000, 0xD2800020, 0xF2A00020, 0xA9BF7BFD, 0xA8C17BFD,
+                0x1E212800, 0x1E210800, 0x91008101, 0xD1000400, 0xB0000008, 0xB9401100, 0xD65F03C0,
+            ]),
+            Platform::Switch,
+            Arch::Aarch64,
+        );
+
+        // the condition isn't part of the opcode
+        assert_eq!(opcodes[0], opcodes[1]);
+
+        // every other instruction is told apart
+        let mut distinct = opcodes[1..].to_vec();
+        distinct.sort();
+        distinct.dedup();
+        assert_eq!(distinct.len(), opcodes.len() - 1);
+        assert!(distinct.iter().all(|op| *op < u16::MAX - 2));
+    }
+
+    #[test]
+    fn test_raw_aarch64_page_offsets() {
+        let words =
+            |words: &[u32]| -> Vec<u8> { words.iter().flat_map(|w| w.to_le_bytes()).collect() };
+        let relocs = |page: u64, offset: u64| -> HashMap<u64, PseudoReloc> {
+            HashMap::from([
+                (
+                    0,
+                    PseudoReloc {
+                        target: page + offset,
+                        kind: PseudoRelocKind::Hi,
+                    },
+                ),
+                (
+                    4,
+                    PseudoReloc {
+                        target: page + offset,
+                        kind: PseudoRelocKind::Lo,
+                    },
+                ),
+                (
+                    8,
+                    PseudoReloc {
+                        target: page + 0x20,
+                        kind: PseudoRelocKind::Lo,
+                    },
+                ),
+            ])
+        };
+
+        // adrp x8, #0x1000; ldr w0, [x8, #0x10]; add x1, x8, #0x20; ret
+        let page_1 = words(&[0xB0000008, 0xB9401100, 0x91008101, 0xD65F03C0]);
+        // adrp x8, #0x5000; ldr w0, [x8, #0x48]; add x1, x8, #0x30; ret
+        let page_5 = words(&[0xB0000028, 0xB9404900, 0x9100C101, 0xD65F03C0]);
+        // adrp x9, #0x1000; ldr w0, [x9, #0x10]; add x1, x9, #0x20; ret
+        let other_reg = words(&[0xB0000009, 0xB9401120, 0x91008121, 0xD65F03C0]);
+
+        let relocs_1 = relocs(0x1000, 0x10);
+        let relocs_5 = relocs(0x5000, 0x48);
+
+        // the page and the page offsets are masked when relocated...
+        assert_eq!(
+            arch::get_equivalence_hashes_raw(
+                &page_1,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&relocs_1)
+            ),
+            arch::get_equivalence_hashes_raw(
+                &page_5,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&relocs_5)
+            )
+        );
+        // ...but not otherwise
+        assert_ne!(
+            arch::get_equivalence_hashes_raw(
+                &page_1,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&HashMap::new())
+            ),
+            arch::get_equivalence_hashes_raw(
+                &page_5,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&HashMap::new())
+            )
+        );
+        // the registers are still hashed
+        assert_ne!(
+            arch::get_equivalence_hashes_raw(
+                &page_1,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&relocs_1)
+            ),
+            arch::get_equivalence_hashes_raw(
+                &other_reg,
+                0,
+                Platform::Switch,
+                Arch::Aarch64,
+                Some(&relocs_1)
+            )
+        );
+    }
+
+    #[test]
+    fn test_simple_sh() {
+        let d: PathBuf = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let elf_data = fs::read(d.join("../../test/simple_sh.o")).unwrap();
+        let symbols = read_elf(Platform::Saturn, &None, &elf_data).unwrap();
+
+        let lit_1 = symbols.iter().find(|s| s.name == "lit_1").unwrap();
+        let lit_2 = symbols.iter().find(|s| s.name == "lit_2").unwrap();
+        let lit_3 = symbols.iter().find(|s| s.name == "lit_3").unwrap();
+        let lit_4 = symbols.iter().find(|s| s.name == "lit_4").unwrap();
+
+        // the literal pool inside each function doesn't put its opcodes out of step with its
+        // other per-instruction data
+        for symbol in [lit_1, lit_2, lit_3, lit_4] {
+            assert_eq!(symbol.opcodes.len(), symbol.insn_equiv_hashes.len());
+            assert_eq!(symbol.opcodes.len(), symbol.insn_reloc_targets.len());
+        }
+
+        // relocated literals are hashed by their relocation...
+        assert_eq!(lit_1.opcode_hash, lit_2.opcode_hash);
+        assert_eq!(lit_1.insn_equiv_hashes[0], lit_2.insn_equiv_hashes[0]);
+        assert_eq!(lit_1.equiv_hash, lit_2.equiv_hash);
+
+        // ...and other literals by their value
+        assert_eq!(lit_1.opcode_hash, lit_3.opcode_hash);
+        assert_ne!(lit_1.insn_equiv_hashes[0], lit_3.insn_equiv_hashes[0]);
+        assert_ne!(lit_3.insn_equiv_hashes[0], lit_4.insn_equiv_hashes[0]);
+
+        // the same goes for linked code without relocations
+        let linked = |literal: u32| -> Vec<u8> {
+            [0xD101u16, 0x000B, 0x6012, 0x0009]
+                .iter()
+                .flat_map(|h| h.to_be_bytes())
+                .chain(literal.to_be_bytes())
+                .collect()
+        };
+        let hashes_3 = arch::get_equivalence_hashes_raw(
+            &linked(0x1234),
+            0,
+            Platform::Saturn,
+            Arch::SuperH,
+            None,
+        );
+        let hashes_4 = arch::get_equivalence_hashes_raw(
+            &linked(0x5678),
+            0,
+            Platform::Saturn,
+            Arch::SuperH,
+            None,
+        );
+        assert_eq!(hashes_3[1..4], hashes_4[1..4]);
+        assert_ne!(hashes_3[0], hashes_4[0]);
+
+        // ...except for pointers, which stand for the relocation they had
+        let hashes_1 = arch::get_equivalence_hashes_raw(
+            &linked(0x0600_1000),
+            0,
+            Platform::Saturn,
+            Arch::SuperH,
+            None,
+        );
+        let hashes_2 = arch::get_equivalence_hashes_raw(
+            &linked(0x0600_2000),
+            0,
+            Platform::Saturn,
+            Arch::SuperH,
+            None,
+        );
+        assert_eq!(hashes_1, hashes_2);
+        assert_ne!(hashes_1[0], hashes_3[0]);
+    }
+
+    #[test]
+    fn test_raw_x86_bitness() {
+        // mov ax, 1; ret in 16-bit code, the start of mov eax, imm32 in 32-bit code
+        let bytes = [0xB8, 0x01, 0x00, 0xC3, 0xCC];
+
+        assert_eq!(
+            arch::get_insn_offsets_raw(&bytes, Platform::MsDos, Arch::X86),
+            vec![0, 3, 4]
+        );
+        assert_eq!(
+            arch::get_insn_offsets_raw(&bytes, Platform::Win32, Arch::X86),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn test_raw_x86_padding() {
+        // mov eax, 0xCCCCCCCC; ret; int3; nop; int3
+        let raw = [0xB8, 0xCC, 0xCC, 0xCC, 0xCC, 0xC3, 0xCC, 0x90, 0xCC];
+        let symbol = read_raw_symbol(
+            Platform::Win32,
+            Arch::X86,
+            "func",
+            &raw,
+            0x401000,
+            None,
+            0,
+            false,
+            &HashMap::new(),
+            None,
+        );
+        assert_eq!(symbol.bytes, raw[..6]);
+
+        // mov eax, 0x90909090 with nothing after it
+        let raw = [0xB8, 0x90, 0x90, 0x90, 0x90];
+        assert_eq!(arch::trim_x86_padding(&raw, Platform::Win32), 5);
+    }
+
+    #[test]
+    fn test_raw_x86_relocs() {
+        // mov eax, [var]; call callee; mov eax, [ebx + field]; ret
+        let code = |vram: u32, var: u32, callee: u32, field: u8| -> Vec<u8> {
+            let mut bytes = vec![0xA1];
+            bytes.extend(var.to_le_bytes());
+            bytes.push(0xE8);
+            bytes.extend(callee.wrapping_sub(vram + 10).to_le_bytes());
+            bytes.extend([0x8B, 0x43, field, 0xC3]);
+            bytes
+        };
+        let hashes = |vram: u32, var: u32, callee: u32, field: u8| -> Vec<u64> {
+            arch::get_equivalence_hashes_raw(
+                &code(vram, var, callee, field),
+                vram as usize,
+                Platform::Win32,
+                Arch::X86,
+                None,
+            )
+        };
+
+        // absolute addresses and calls are treated as relocated...
+        assert_eq!(
+            hashes(0x401000, 0x405000, 0x402000, 0x10),
+            hashes(0x401100, 0x406000, 0x403000, 0x10)
+        );
+        // ...but displacements from a register are hashed
+        assert_ne!(
+            hashes(0x401000, 0x405000, 0x402000, 0x10),
+            hashes(0x401000, 0x405000, 0x402000, 0x14)
+        );
     }
 }
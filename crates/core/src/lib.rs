@@ -1,8 +1,24 @@
+pub mod align;
 pub mod arch;
+pub mod callgraph;
+pub mod cfg;
+pub mod cwmap;
+pub mod dol;
+pub mod executable;
+pub mod hash;
 pub mod ingest;
+pub mod n64;
+pub mod nds;
+mod pseudo_reloc;
+pub mod rel;
+pub mod schedule;
+pub mod semantic;
+pub mod significance;
+pub mod submatch;
+pub mod symbol_list;
 
 use anyhow::Result;
-use editdistancek::edit_distance_bounded;
+use hash::stable_hash;
 use objdiff_core::diff::display::DiffText;
 use objdiff_core::diff::{
     ArmArchVersion, ArmR9Usage, Demangler, DiffObjConfig, DiffSide, FunctionRelocDiffs, MipsAbi,
@@ -10,21 +26,32 @@ use objdiff_core::diff::{
 };
 use object::Endianness;
 use serde::Serialize;
-use std::hash::{DefaultHasher, Hash, Hasher};
+use std::collections::HashMap;
+use std::hash::Hash;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum Arch {
     Mips,
     Ppc,
     Thumb,
+    Arm,
+    Aarch64,
+    SuperH,
+    X86,
 }
 
 impl Arch {
+    /// The length of an instruction in bytes. x86 instructions are variable-length, so the
+    /// minimum length is returned for it.
     pub fn standard_insn_length(&self) -> usize {
         match self {
             Arch::Mips => 4,
             Arch::Ppc => 4,
             Arch::Thumb => 2,
+            Arch::Arm => 4,
+            Arch::Aarch64 => 4,
+            Arch::SuperH => 2,
+            Arch::X86 => 1,
         }
     }
 }
@@ -64,7 +91,12 @@ pub enum Platform {
     Nds,
     N3ds,
     Irix,
-    //Switch,
+    Switch,
+    Saturn,
+    Dreamcast,
+    Win32,
+    MsDos,
+    Xbox,
 }
 }
 
@@ -76,7 +108,12 @@ impl Platform {
             "ps2" => Some(Platform::Ps2),
             "gc_wii" => Some(Platform::GcWii),
             "psp" => Some(Platform::Psp),
-            //"switch" => Some(Platform::Switch),
+            "switch" => Some(Platform::Switch),
+            "saturn" => Some(Platform::Saturn),
+            "dreamcast" => Some(Platform::Dreamcast),
+            "win32" => Some(Platform::Win32),
+            "msdos" => Some(Platform::MsDos),
+            "xbox" => Some(Platform::Xbox),
             _ => None,
         }
     }
@@ -92,10 +129,11 @@ impl Platform {
             "nds_arm9" => Some(Platform::Nds),
             "n3ds" => Some(Platform::N3ds),
             "irix" => Some(Platform::Irix),
-            "switch" => None, //Some(Platform::Switch), // TODO: needs aarch64 support
-            "saturn" => None, // TODO: needs sh2 support
-            "win32" => None,  // :frull:
-            "msdos" => None,  // :frull:
+            "switch" => Some(Platform::Switch),
+            "saturn" => Some(Platform::Saturn),
+            "dreamcast" => Some(Platform::Dreamcast),
+            "win32" => Some(Platform::Win32),
+            "msdos" => Some(Platform::MsDos),
             "macosx" => None, // :frull:
             "macos9" => None, // :frull:
             _ => None,
@@ -113,7 +151,12 @@ impl Platform {
             Platform::Nds => Endianness::Little,
             Platform::N3ds => Endianness::Little,
             Platform::Irix => Endianness::Big,
-            //Platform::Switch => Endianness::Little,
+            Platform::Switch => Endianness::Little,
+            Platform::Saturn => Endianness::Big,
+            Platform::Dreamcast => Endianness::Little,
+            Platform::Win32 => Endianness::Little,
+            Platform::MsDos => Endianness::Little,
+            Platform::Xbox => Endianness::Little,
         }
     }
 
@@ -125,14 +168,32 @@ impl Platform {
             Platform::GcWii => Arch::Ppc,
             Platform::Psp => Arch::Mips,
             Platform::Gba => Arch::Thumb,
-            Platform::Nds => Arch::Thumb,
-            Platform::N3ds => Arch::Thumb,
+            Platform::Nds => Arch::Arm,
+            Platform::N3ds => Arch::Arm,
             Platform::Irix => Arch::Mips,
-            //Platform::Switch => Arch::Aarch64,
+            Platform::Switch => Arch::Aarch64,
+            Platform::Saturn => Arch::SuperH,
+            Platform::Dreamcast => Arch::SuperH,
+            Platform::Win32 => Arch::X86,
+            Platform::MsDos => Arch::X86,
+            Platform::Xbox => Arch::X86,
         }
     }
 
-    /// Get the ARM version for Thumb-based platforms
+    /// Get every instruction set code for the platform may be in, its usual one first
+    pub fn archs(&self) -> &'static [Arch] {
+        match self.arch() {
+            Arch::Thumb => &[Arch::Thumb, Arch::Arm],
+            Arch::Arm => &[Arch::Arm, Arch::Thumb],
+            Arch::Mips => &[Arch::Mips],
+            Arch::Ppc => &[Arch::Ppc],
+            Arch::Aarch64 => &[Arch::Aarch64],
+            Arch::SuperH => &[Arch::SuperH],
+            Arch::X86 => &[Arch::X86],
+        }
+    }
+
+    /// Get the ARM version for ARM and Thumb-based platforms
     pub fn arm_version(&self) -> unarm::Version {
         match self {
             Platform::Gba => unarm::Version::V4T,
@@ -141,6 +202,24 @@ impl Platform {
             _ => unreachable!("arm_version() called on non-ARM platform"),
         }
     }
+
+    /// Get the default operand and address size in bits for x86-based platforms
+    pub fn x86_bitness(&self) -> u32 {
+        match self {
+            Platform::MsDos => 16,
+            Platform::Win32 | Platform::Xbox => 32,
+            _ => unreachable!("x86_bitness() called on non-x86 platform"),
+        }
+    }
+}
+
+/// An object in a static library (`ar` archive)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LibraryMember {
+    /// the archive's file name
+    pub archive: String,
+    /// the member object's name
+    pub member: String,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -159,8 +238,27 @@ pub struct Symbol {
     pub opcode_hash: u64,
     /// the equivalent hash for the symbol
     pub equiv_hash: u64,
+    /// the equivalent hash of each of the symbol's instructions
+    pub insn_equiv_hashes: Vec<u64>,
+    /// what each of the symbol's instructions is relocated against, if anything
+    pub insn_reloc_targets: Vec<Option<u64>>,
+    /// the register-allocation-invariant hash for the symbol, on architectures whose registers
+    /// are renamed (MIPS and PPC)
+    pub regalloc_hash: Option<u64>,
+    /// the semantic hash for the symbol, if it refers to any well-known symbols
+    pub semantic_hash: Option<u64>,
     /// the exact hash for the symbol
     pub exact_hash: u64,
+    /// the symbol's control flow graph
+    pub cfg: cfg::Cfg,
+    /// what the symbol calls, in order
+    pub calls: Vec<callgraph::CallTarget>,
+    /// the order the symbol's instructions are compared in when tolerating scheduling differences
+    pub canonical_order: Vec<usize>,
+    /// the segment or overlay the symbol is in, if known. Overlays can share the same VRAM.
+    pub segment: Option<String>,
+    /// the static library and member object the symbol was read from, if any
+    pub library: Option<LibraryMember>,
     /// the symbol_idx of the symbol in the object
     pub symbol_idx: usize,
 }
@@ -181,10 +279,14 @@ pub struct Binary {
     pub symbols: Vec<Symbol>,
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A run of instructions two symbols have in common
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct InsnSeqMatch {
+    /// Offset of the run in the first symbol, in instructions
     pub offset1: usize,
+    /// Offset of the run in the second symbol, in instructions
     pub offset2: usize,
+    /// Length of the run, in instructions
     pub length: usize,
 }
 
@@ -219,81 +321,199 @@ pub fn get_hashes<T: Clone + Default + Hash>(data: &[T], window_size: usize) ->
         data.resize(window_size, Default::default());
     }
 
-    data.windows(window_size)
-        .map(|x| {
-            let mut hasher = DefaultHasher::new();
-            (*x).hash(&mut hasher);
-            hasher.finish()
-        })
-        .collect()
+    data.windows(window_size).map(stable_hash).collect()
 }
 
+/// Find every maximal run of matching windows between two symbols' window hashes. For searching
+/// many symbols at once, see [`submatch::SubmatchIndex`].
 pub fn get_submatches(hashes_1: &[u64], hashes_2: &[u64], window_size: usize) -> Vec<InsnSeqMatch> {
-    let mut matches = Vec::new();
+    let mut positions: HashMap<u64, Vec<usize>> = HashMap::new();
+    for (j, h) in hashes_2.iter().enumerate() {
+        positions.entry(*h).or_default().push(j);
+    }
 
-    let matching_hashes = hashes_1
+    let hits = hashes_1
         .iter()
         .enumerate()
-        .filter(|(_, h)| hashes_2.contains(h))
-        .map(|(i, h)| InsnSeqMatch {
-            offset1: i,
-            offset2: hashes_2.iter().position(|x| x == h).unwrap(),
-            length: 1,
-        })
-        .collect::<Vec<InsnSeqMatch>>();
+        .filter_map(|(i, h)| Some(positions.get(h)?.iter().map(move |&j| (i, j))))
+        .flatten()
+        .collect();
 
-    if matching_hashes.is_empty() {
-        return matches;
-    }
+    submatch::collect_runs(hits, window_size)
+}
 
-    let mut match_groups: Vec<Vec<InsnSeqMatch>> = Vec::new();
-    let mut cur_pos = matching_hashes[0].offset1;
-    for mh in matching_hashes {
-        if mh.offset1 == cur_pos + 1 {
-            match_groups.last_mut().unwrap().push(mh);
-        } else {
-            match_groups.push(vec![mh]);
-        }
-        cur_pos = mh.offset1;
-    }
+/// A component of a [`SimilarityReport`] to rank and filter matches by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SimilarityMetric {
+    #[default]
+    Opcode,
+    Equivalent,
+    LengthRatio,
+    Structural,
+    Containment,
+}
 
-    for group in match_groups {
-        matches.push(InsnSeqMatch {
-            offset1: group[0].offset1,
-            offset2: group[0].offset2,
-            length: group.len() + window_size,
-        });
-    }
+/// Where the shorter of two symbols was found within the longer one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Containment {
+    /// Whether the first symbol is the one contained in the second, rather than the other way
+    /// around
+    pub first_in_second: bool,
+    /// Index of the first instruction of the aligned region in the containing symbol
+    pub start: usize,
+    /// Index of the instruction after the aligned region in the containing symbol
+    pub end: usize,
+    /// Fraction of the contained symbol covered by the aligned region, from 0 to 1
+    pub score: f32,
+}
+
+/// How similar two symbols are, broken down by what's compared
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct SimilarityReport {
+    /// Similarity of the opcodes, from 0 (nothing in common) to 1 (identical). Edits are counted
+    /// against the lengths of both symbols together.
+    pub opcode: f32,
+    /// Like `opcode`, but instructions whose operands differ at the equivalent level also count
+    /// as edits, and edits are counted against the longer symbol's length
+    pub equivalent: f32,
+    /// Whether the bytes are identical
+    pub exact: bool,
+    /// Length of the shorter symbol divided by that of the longer one
+    pub length_ratio: f32,
+    /// Number of paired-up instructions that are relocated against different targets
+    pub reloc_diffs: usize,
+    /// Similarity of the control flow graphs, from 0 to 1, regardless of the order of the blocks
+    pub structural: f32,
+    /// Where the shorter symbol is contained in the longer one, if scored by containment
+    pub containment: Option<Containment>,
+    /// How unlikely the match is to be a coincidence, if measured against a corpus
+    pub significance: Option<significance::Significance>,
+}
 
-    matches
+impl SimilarityReport {
+    pub fn score(&self, metric: SimilarityMetric) -> f32 {
+        match metric {
+            SimilarityMetric::Opcode => self.opcode,
+            SimilarityMetric::Equivalent => self.equivalent,
+            SimilarityMetric::LengthRatio => self.length_ratio,
+            SimilarityMetric::Structural => self.structural,
+            SimilarityMetric::Containment => self.containment.map_or(0.0, |c| c.score),
+        }
+    }
 }
 
-pub fn diff_symbols(sym1: &Symbol, sym2: &Symbol, threshold: f32) -> f32 {
-    // The minimum edit distance for two strings of different lengths is `abs(l1 - l2)`
-    // Quickly check if it's possible to beat the threshold. If it isn't, return 0
+/// Compare two symbols. Returns `None` if they can't score above `threshold` for `metric`, which
+/// is found out without a full alignment where possible.
+pub fn diff_symbols(
+    sym1: &Symbol,
+    sym2: &Symbol,
+    metric: SimilarityMetric,
+    threshold: f32,
+) -> Option<SimilarityReport> {
     let l1 = sym1.opcodes.len();
     let l2 = sym2.opcodes.len();
 
-    let max_edit_dist = (l1 + l2) as f32;
-    if (l1.abs_diff(l2) as f32 / max_edit_dist) > (1.0 - threshold) {
-        return 0.0;
+    let longest = l1.max(l2);
+    let length_ratio = if longest == 0 {
+        1.0
+    } else {
+        l1.min(l2) as f32 / longest as f32
+    };
+
+    // Reordered code can be structurally similar however far apart the alignment is, so the
+    // structural score is checked on its own instead
+    let structural = if metric == SimilarityMetric::Structural {
+        let structural = cfg::structural_similarity(sym1, sym2);
+        if structural <= threshold {
+            return None;
+        }
+        Some(structural)
+    } else {
+        None
+    };
+
+    // A small symbol can be contained in a much bigger one, so containment is also checked on its
+    // own, by aligning the shorter symbol within the longer one
+    let containment = if metric == SimilarityMetric::Containment {
+        let (needle, haystack) = if l1 <= l2 { (sym1, sym2) } else { (sym2, sym1) };
+        let needle_len = needle.opcodes.len() as f32;
+        let bound = (needle_len - (needle_len * threshold)) as usize;
+        let (start, end, edits) = align::align_within(&needle.opcodes, &haystack.opcodes, bound)?;
+
+        let score = if needle.opcodes.is_empty() {
+            0.0
+        } else {
+            1.0 - edits as f32 / needle_len
+        };
+        if score <= threshold {
+            return None;
+        }
+        Some(Containment {
+            first_in_second: l1 <= l2,
+            start,
+            end,
+            score,
+        })
+    } else {
+        None
+    };
+
+    // The minimum edit distance for two sequences of different lengths is `abs(l1 - l2)`, which
+    // bounds the alignment scores. Opcode similarity counts edits against both lengths together and
+    // the others against the longer one. Quickly check if it's possible to beat the threshold
+    let best_alignment_score = match metric {
+        SimilarityMetric::Opcode if l1 + l2 > 0 => 1.0 - l1.abs_diff(l2) as f32 / (l1 + l2) as f32,
+        _ => length_ratio,
+    };
+    if structural.is_none() && containment.is_none() && best_alignment_score <= threshold {
+        return None;
     }
 
-    let sym1_insns_u8: Vec<u8> = sym1.opcodes.iter().flat_map(|&x| x.to_be_bytes()).collect();
-    let sym2_insns_u8: Vec<u8> = sym2.opcodes.iter().flat_map(|&x| x.to_be_bytes()).collect();
-
-    let bound = (max_edit_dist - (max_edit_dist * threshold)) as usize;
-    if let Some(edit_distance) = edit_distance_bounded(&sym1_insns_u8, &sym2_insns_u8, bound) {
-        let edit_dist = edit_distance as f32;
-        let normalized_edit_dist = (max_edit_dist - edit_dist) / max_edit_dist;
-
-        if normalized_edit_dist == 1.0 && sym1.exact_hash != sym2.exact_hash {
-            return 0.9999;
+    let alignment = match metric {
+        SimilarityMetric::Opcode => {
+            let total = (l1 + l2) as f32;
+            let bound = (total - (total * threshold)) as usize;
+            align::align_symbols_bounded(sym1, sym2, bound)?
+        }
+        SimilarityMetric::Equivalent => {
+            let longest = longest as f32;
+            let bound = (longest - (longest * threshold)) as usize;
+            align::align_symbols_bounded(sym1, sym2, bound)?
         }
-        normalized_edit_dist
+        SimilarityMetric::LengthRatio
+        | SimilarityMetric::Structural
+        | SimilarityMetric::Containment => align::align_symbols(sym1, sym2),
+    };
+
+    let equivalent = if longest == 0 {
+        1.0
     } else {
-        0.0
-    }
+        1.0 - (alignment.edits + alignment.operand_mismatches()) as f32 / longest as f32
+    };
+
+    let reloc_diffs = alignment
+        .rows
+        .iter()
+        .filter_map(|r| Some((r.left?, r.right?)))
+        .filter(|(i, j)| {
+            let target1 = sym1.insn_reloc_targets.get(*i).copied().flatten();
+            let target2 = sym2.insn_reloc_targets.get(*j).copied().flatten();
+            target1 != target2
+        })
+        .count();
+
+    let report = SimilarityReport {
+        opcode: alignment.similarity(),
+        equivalent,
+        exact: sym1.exact_hash == sym2.exact_hash,
+        length_ratio,
+        reloc_diffs,
+        structural: structural.unwrap_or_else(|| cfg::structural_similarity(sym1, sym2)),
+        containment,
+        significance: None,
+    };
+
+    (report.score(metric) > threshold).then_some(report)
 }
 
 #[derive(Debug, Clone, Serialize)]
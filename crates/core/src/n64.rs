@@ -0,0 +1,53 @@
+//! N64 ROM images.
+//!
+//! ROMs are dumped in one of three byte orders, told apart by the first word of the header: `.z64`
+//! is big-endian like the console, `.v64` has each pair of bytes swapped and `.n64` has each word
+//! reversed. Everything else expects big-endian code, so ROMs are put in that order first.
+
+use anyhow::{Result, anyhow};
+
+/// The first word of the header, in big-endian order
+const MAGIC: [u8; 4] = [0x80, 0x37, 0x12, 0x40];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RomByteOrder {
+    /// `.z64`
+    BigEndian,
+    /// `.v64`
+    ByteSwapped,
+    /// `.n64`
+    LittleEndian,
+}
+
+/// Get the byte order of a ROM from its header, if it has one
+pub fn detect_byte_order(rom: &[u8]) -> Option<RomByteOrder> {
+    let [a, b, c, d] = MAGIC;
+    match rom.get(..4)? {
+        x if x == [a, b, c, d] => Some(RomByteOrder::BigEndian),
+        x if x == [b, a, d, c] => Some(RomByteOrder::ByteSwapped),
+        x if x == [d, c, b, a] => Some(RomByteOrder::LittleEndian),
+        _ => None,
+    }
+}
+
+/// Put a ROM in big-endian order. Data without a ROM header (e.g. a single segment) is left
+/// as it is.
+pub fn to_big_endian(mut rom: Vec<u8>) -> Result<Vec<u8>> {
+    let chunk_size = match detect_byte_order(&rom) {
+        None | Some(RomByteOrder::BigEndian) => return Ok(rom),
+        Some(RomByteOrder::ByteSwapped) => 2,
+        Some(RomByteOrder::LittleEndian) => 4,
+    };
+
+    if !rom.len().is_multiple_of(chunk_size) {
+        return Err(anyhow!(
+            "ROM size {:#x} isn't a multiple of {}",
+            rom.len(),
+            chunk_size
+        ));
+    }
+    for chunk in rom.chunks_exact_mut(chunk_size) {
+        chunk.reverse();
+    }
+    Ok(rom)
+}
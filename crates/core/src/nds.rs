@@ -0,0 +1,320 @@
+//! Nintendo DS ROMs.
+//!
+//! The cartridge header locates the ARM9 and ARM7 binaries and their overlay tables. Each overlay
+//! table entry gives the overlay's load address and its file, found through the file allocation
+//! table (FAT). Overlays of the same CPU are loaded over each other, so they share VRAM and are
+//! kept as separate segments.
+//!
+//! The ARM9 binary and overlays may be compressed with BLZ, a backwards LZ77 variant that's
+//! decompressed in place from the end. Part of the ARM9 binary (the autoloads, usually ITCM and
+//! DTCM) is copied elsewhere at startup, and becomes segments of its own.
+
+use anyhow::{Result, anyhow};
+
+const HEADER_SIZE: usize = 0x200;
+/// CRC of the Nintendo logo in the header, the same in every licensed ROM
+const LOGO_CRC: u16 = 0xCF56;
+/// Ends the ARM9 module parameters, as `0xDEC00621, 0x2106C0DE` in little-endian
+const NITROCODE: [u8; 8] = [0x21, 0x06, 0xC0, 0xDE, 0xDE, 0xC0, 0x06, 0x21];
+const OVERLAY_ENTRY_SIZE: usize = 0x20;
+
+#[derive(Debug, Clone)]
+pub struct NdsSegment {
+    /// `arm9`, `arm7`, `autoload_<n>` or `ov<id>` (`arm7_ov<id>` for ARM7 overlays)
+    pub name: String,
+    /// Where the segment is loaded
+    pub address: u32,
+    /// The segment's contents, decompressed
+    pub data: Vec<u8>,
+    pub overlay_id: Option<u32>,
+    pub arm7: bool,
+    /// Where the segment's file is in the ROM, and its size there
+    pub rom_offset: u32,
+    pub rom_size: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct NdsRom {
+    pub title: String,
+    pub segments: Vec<NdsSegment>,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("NDS data is truncated at {:#x}", offset))
+}
+
+fn slice(bytes: &[u8], offset: u32, size: u32) -> Result<&[u8]> {
+    bytes
+        .get(offset as usize..offset as usize + size as usize)
+        .ok_or_else(|| {
+            anyhow!(
+                "NDS data at {:#x}..{:#x} is out of bounds",
+                offset,
+                offset as u64 + size as u64
+            )
+        })
+}
+
+/// Whether the data looks like an NDS ROM
+pub fn is_nds(bytes: &[u8]) -> bool {
+    bytes.len() >= HEADER_SIZE && u16::from_le_bytes([bytes[0x15C], bytes[0x15D]]) == LOGO_CRC
+}
+
+/// Decompress BLZ data. The footer gives the size of the compressed part at the end of the data,
+/// which is read backwards and expands to fill the output from its end. Anything before it is
+/// stored as it is.
+pub fn blz_decompress(data: &[u8]) -> Result<Vec<u8>> {
+    let len = data.len();
+    if len < 8 {
+        return Err(anyhow!("BLZ data is smaller than its footer"));
+    }
+    let inc_len = read_u32(data, len - 4)? as usize;
+    if inc_len == 0 {
+        return Ok(data[..len - 4].to_vec());
+    }
+
+    let header_len = data[len - 5] as usize;
+    let enc_len = (read_u32(data, len - 8)? & 0x00FF_FFFF) as usize;
+    if !(8..=0xB).contains(&header_len) || enc_len < header_len || enc_len > len {
+        return Err(anyhow!("Invalid BLZ footer"));
+    }
+    let dec_len = len - enc_len;
+    let out_len = enc_len + inc_len;
+
+    let mut compressed = data[dec_len..len - header_len].iter().rev().copied();
+    let mut out: Vec<u8> = Vec::with_capacity(out_len);
+    'decode: while out.len() < out_len {
+        let Some(flags) = compressed.next() else {
+            break;
+        };
+        for bit in (0..8).rev() {
+            if out.len() >= out_len {
+                break 'decode;
+            }
+            if flags & (1 << bit) == 0 {
+                let Some(byte) = compressed.next() else {
+                    break 'decode;
+                };
+                out.push(byte);
+            } else {
+                let (Some(hi), Some(lo)) = (compressed.next(), compressed.next()) else {
+                    break 'decode;
+                };
+                let pos = ((hi as usize) << 8) | lo as usize;
+                let count = ((pos >> 12) + 3).min(out_len - out.len());
+                let distance = (pos & 0xFFF) + 3;
+                if distance > out.len() {
+                    return Err(anyhow!("Invalid BLZ back-reference"));
+                }
+                for _ in 0..count {
+                    out.push(out[out.len() - distance]);
+                }
+            }
+        }
+    }
+
+    // Whatever wasn't filled is at the start of the output
+    out.resize(out_len, 0);
+    out.reverse();
+
+    let mut ret = data[..dec_len].to_vec();
+    ret.extend(out);
+    Ok(ret)
+}
+
+/// Read the overlays listed in an overlay table
+fn read_overlays(rom: &[u8], table: &[u8], fat_offset: u32, arm7: bool) -> Result<Vec<NdsSegment>> {
+    let mut ret = vec![];
+    for entry in table.chunks_exact(OVERLAY_ENTRY_SIZE) {
+        let id = read_u32(entry, 0x00)?;
+        let address = read_u32(entry, 0x04)?;
+        let file_id = read_u32(entry, 0x18)?;
+        let flags = read_u32(entry, 0x1C)?;
+
+        let start = read_u32(rom, fat_offset as usize + file_id as usize * 8)?;
+        let end = read_u32(rom, fat_offset as usize + file_id as usize * 8 + 4)?;
+        let file = slice(rom, start, end.saturating_sub(start))?;
+
+        let data = if flags & (1 << 24) != 0 {
+            let compressed_size = (flags & 0x00FF_FFFF) as usize;
+            blz_decompress(&file[..compressed_size.min(file.len())])?
+        } else {
+            file.to_vec()
+        };
+
+        ret.push(NdsSegment {
+            name: if arm7 {
+                format!("arm7_ov{id:03}")
+            } else {
+                format!("ov{id:03}")
+            },
+            address,
+            data,
+            overlay_id: Some(id),
+            arm7,
+            rom_offset: start,
+            rom_size: file.len() as u32,
+        });
+    }
+    Ok(ret)
+}
+
+/// Split the ARM9 binary into its main part and its autoloads, decompressing it first if needed.
+/// Both are described by the module parameters, if they can be found.
+fn read_arm9(arm9: &[u8], address: u32, rom_offset: u32) -> Result<Vec<NdsSegment>> {
+    let segment = |name: String, address: u32, data: Vec<u8>| NdsSegment {
+        name,
+        address,
+        data,
+        overlay_id: None,
+        arm7: false,
+        rom_offset,
+        rom_size: arm9.len() as u32,
+    };
+
+    let Some(params) = arm9
+        .windows(NITROCODE.len())
+        .position(|w| w == NITROCODE)
+        .and_then(|pos| pos.checked_sub(0x1C))
+    else {
+        return Ok(vec![segment("arm9".to_string(), address, arm9.to_vec())]);
+    };
+
+    let compressed_end = read_u32(arm9, params + 0x14)?;
+    let arm9 = if compressed_end != 0 {
+        let split = (compressed_end.wrapping_sub(address) as usize).min(arm9.len());
+        let mut data = blz_decompress(&arm9[..split])?;
+        data.extend_from_slice(&arm9[split..]);
+        data
+    } else {
+        arm9.to_vec()
+    };
+
+    let list_start = read_u32(&arm9, params)?.wrapping_sub(address) as usize;
+    let list_end = read_u32(&arm9, params + 0x04)?.wrapping_sub(address) as usize;
+    let autoload_start = read_u32(&arm9, params + 0x08)?.wrapping_sub(address) as usize;
+    if list_start > list_end || list_end > arm9.len() || autoload_start > arm9.len() {
+        return Ok(vec![segment("arm9".to_string(), address, arm9)]);
+    }
+
+    let mut ret = vec![segment(
+        "arm9".to_string(),
+        address,
+        arm9[..autoload_start].to_vec(),
+    )];
+    let mut offset = autoload_start;
+    for (i, entry) in arm9[list_start..list_end].chunks_exact(12).enumerate() {
+        let autoload_address = read_u32(entry, 0)?;
+        let size = read_u32(entry, 4)? as usize;
+        let Some(data) = arm9.get(offset..offset + size) else {
+            break;
+        };
+        ret.push(segment(
+            format!("autoload_{i}"),
+            autoload_address,
+            data.to_vec(),
+        ));
+        offset += size;
+    }
+    Ok(ret)
+}
+
+impl NdsRom {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if !is_nds(bytes) {
+            return Err(anyhow!("Not an NDS ROM"));
+        }
+
+        let title = String::from_utf8_lossy(&bytes[..12])
+            .trim_end_matches('\0')
+            .to_string();
+
+        let arm9_offset = read_u32(bytes, 0x20)?;
+        let arm9_address = read_u32(bytes, 0x28)?;
+        let arm9_size = read_u32(bytes, 0x2C)?;
+        let arm7_offset = read_u32(bytes, 0x30)?;
+        let arm7_address = read_u32(bytes, 0x38)?;
+        let arm7_size = read_u32(bytes, 0x3C)?;
+        let fat_offset = read_u32(bytes, 0x48)?;
+        let arm9_ovt_offset = read_u32(bytes, 0x50)?;
+        let arm9_ovt_size = read_u32(bytes, 0x54)?;
+        let arm7_ovt_offset = read_u32(bytes, 0x58)?;
+        let arm7_ovt_size = read_u32(bytes, 0x5C)?;
+
+        let mut segments = read_arm9(
+            slice(bytes, arm9_offset, arm9_size)?,
+            arm9_address,
+            arm9_offset,
+        )?;
+        if arm7_size != 0 {
+            segments.push(NdsSegment {
+                name: "arm7".to_string(),
+                address: arm7_address,
+                data: slice(bytes, arm7_offset, arm7_size)?.to_vec(),
+                overlay_id: None,
+                arm7: true,
+                rom_offset: arm7_offset,
+                rom_size: arm7_size,
+            });
+        }
+        if arm9_ovt_size != 0 {
+            let table = slice(bytes, arm9_ovt_offset, arm9_ovt_size)?;
+            segments.extend(read_overlays(bytes, table, fat_offset, false)?);
+        }
+        if arm7_ovt_size != 0 {
+            let table = slice(bytes, arm7_ovt_offset, arm7_ovt_size)?;
+            segments.extend(read_overlays(bytes, table, fat_offset, true)?);
+        }
+
+        Ok(Self { title, segments })
+    }
+
+    /// Find the segment a symbol is in. Its ROM offset picks between overlays that share VRAM,
+    /// otherwise the main binaries and their autoloads are preferred over overlays.
+    pub fn segment_at(&self, vram: u32, rom: Option<u32>) -> Option<&NdsSegment> {
+        let contains =
+            |s: &&NdsSegment| vram >= s.address && ((vram - s.address) as usize) < s.data.len();
+
+        if let Some(rom) = rom {
+            return self
+                .segments
+                .iter()
+                .filter(contains)
+                .find(|s| rom >= s.rom_offset && rom - s.rom_offset < s.rom_size);
+        }
+        let mut candidates = self.segments.iter().filter(contains);
+        let first = candidates.clone().next();
+        candidates.find(|s| s.overlay_id.is_none()).or(first)
+    }
+
+    /// Find the segment a map's segment refers to, by name (e.g. `ov002`, `overlay_2`, `arm7`)
+    /// or, for the main binaries, by where one of its symbols is
+    pub fn segment_for_map_name(&self, name: &str, vram: u32) -> Option<&NdsSegment> {
+        let name = name.to_lowercase();
+        if let Some(segment) = self.segments.iter().find(|s| s.name == name) {
+            return Some(segment);
+        }
+
+        let arm7 = name.contains("arm7");
+        if name.contains("ov") {
+            let id: u32 = name
+                .rsplit(|c: char| !c.is_ascii_digit())
+                .next()?
+                .parse()
+                .ok()?;
+            return self
+                .segments
+                .iter()
+                .find(|s| s.overlay_id == Some(id) && s.arm7 == arm7);
+        }
+
+        self.segments.iter().find(|s| {
+            s.overlay_id.is_none()
+                && vram >= s.address
+                && ((vram - s.address) as usize) < s.data.len()
+        })
+    }
+}
@@ -0,0 +1,407 @@
+//! Recovery of relocations from code that has already been linked.
+//!
+//! Linked ELFs and ROMs no longer carry the relocations that the equivalence hash relies on, so
+//! the instructions that would have been relocated are found by looking for the address-forming
+//! patterns compilers emit. The results are keyed by target address, which partitions the
+//! instructions the same way the relocations' target symbols do in a relocatable object.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use iced_x86::{Decoder, DecoderOptions, FlowControl, OpKind, Register};
+
+use crate::arch::get_literals_raw;
+use crate::{Arch, Platform};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum PseudoRelocKind {
+    /// Upper half of an address (`%hi`, `@ha`)
+    Hi,
+    /// Lower half of an address (`%lo`, `@l`)
+    Lo,
+    /// Direct jump or call
+    Jump,
+    /// Offset from the global pointer (`%gp_rel`)
+    GpRel,
+    /// A whole address (`dir32`), or a pointer in a literal pool
+    Absolute,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct PseudoReloc {
+    pub target: u64,
+    pub kind: PseudoRelocKind,
+}
+
+/// Find the instructions that would have carried a relocation before linking, keyed by address.
+/// `loaded` is where the image's sections are, if that's known.
+pub(crate) fn find_pseudo_relocs(
+    bytes: &[u8],
+    vram: u64,
+    platform: Platform,
+    arch: Arch,
+    loaded: &[Range<u64>],
+) -> HashMap<u64, PseudoReloc> {
+    let words = || -> Vec<u32> {
+        bytes
+            .chunks_exact(4)
+            .map(|c| platform.endianness().read_u32_bytes(c.try_into().unwrap()))
+            .collect()
+    };
+
+    match arch {
+        Arch::Mips => find_mips_pseudo_relocs(&words(), vram),
+        Arch::Ppc => find_ppc_pseudo_relocs(&words(), vram),
+        Arch::Thumb | Arch::Arm | Arch::SuperH => {
+            find_literal_pseudo_relocs(bytes, vram, platform, arch)
+        }
+        Arch::X86 => find_x86_pseudo_relocs(bytes, vram, platform, loaded),
+        Arch::Aarch64 => HashMap::new(),
+    }
+}
+
+/// The relocations of linked code if they're known (e.g. from a REL module's relocation table),
+/// otherwise the ones recovered from it
+pub(crate) fn known_or_recovered<'a>(
+    bytes: &[u8],
+    vram: u64,
+    platform: Platform,
+    arch: Arch,
+    relocs: Option<&'a HashMap<u64, PseudoReloc>>,
+) -> Cow<'a, HashMap<u64, PseudoReloc>> {
+    match relocs {
+        Some(relocs) => Cow::Borrowed(relocs),
+        None => Cow::Owned(find_pseudo_relocs(bytes, vram, platform, arch, &[])),
+    }
+}
+
+fn sign_extend_16(word: u32) -> u32 {
+    word as u16 as i16 as i32 as u32
+}
+
+/// Record a hi/lo pair. A `lui`/`lis` can be shared by several accesses (e.g. a load and a store
+/// of the same variable), in which case the hi half goes with the first one, like the assembler
+/// pairs them.
+fn add_pair(relocs: &mut HashMap<u64, PseudoReloc>, hi_address: u64, lo_address: u64, target: u64) {
+    relocs.entry(hi_address).or_insert(PseudoReloc {
+        target,
+        kind: PseudoRelocKind::Hi,
+    });
+    relocs.insert(
+        lo_address,
+        PseudoReloc {
+            target,
+            kind: PseudoRelocKind::Lo,
+        },
+    );
+}
+
+fn find_mips_pseudo_relocs(words: &[u32], vram: u64) -> HashMap<u64, PseudoReloc> {
+    const GP: usize = 28;
+
+    let mut relocs = HashMap::new();
+    // (address of the lui, value it loaded) for each register
+    let mut pending_hi: [Option<(u64, u32)>; 32] = [None; 32];
+
+    for (i, word) in words.iter().enumerate() {
+        let address = vram + i as u64 * 4;
+        let op = word >> 26;
+        let rs = ((word >> 21) & 0x1F) as usize;
+        let rt = ((word >> 16) & 0x1F) as usize;
+        let rd = ((word >> 11) & 0x1F) as usize;
+
+        match op {
+            // lui
+            0x0F => {
+                pending_hi[rt] = Some((address, (word & 0xFFFF) << 16));
+                continue;
+            }
+            // j, jal
+            0x02 | 0x03 => {
+                let target = ((address + 4) & 0xF000_0000) | ((word & 0x03FF_FFFF) << 2) as u64;
+                relocs.insert(
+                    address,
+                    PseudoReloc {
+                        target,
+                        kind: PseudoRelocKind::Jump,
+                    },
+                );
+            }
+            // addiu, loads and stores
+            0x09
+            | 0x1E
+            | 0x1F
+            | 0x20..=0x27
+            | 0x28..=0x2B
+            | 0x2E
+            | 0x31
+            | 0x32
+            | 0x35
+            | 0x36
+            | 0x37
+            | 0x39
+            | 0x3A
+            | 0x3D
+            | 0x3E
+            | 0x3F => {
+                let offset = sign_extend_16(*word);
+                if rs == GP {
+                    relocs.insert(
+                        address,
+                        PseudoReloc {
+                            target: offset as u64,
+                            kind: PseudoRelocKind::GpRel,
+                        },
+                    );
+                } else if let Some((hi_address, hi)) = pending_hi[rs] {
+                    add_pair(
+                        &mut relocs,
+                        hi_address,
+                        address,
+                        hi.wrapping_add(offset) as u64,
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        // Forget about any hi half whose register gets overwritten
+        let written = match op {
+            // addu/daddu onto the register holding the hi half is indexing (e.g. jump tables)
+            0x00 if matches!(word & 0x3F, 0x21 | 0x2D) && (rd == rs || rd == rt) => None,
+            0x00 => Some(rd),
+            // addi, addiu, slti, sltiu, andi, ori, xori, daddi, daddiu, loads
+            0x08..=0x0E | 0x18 | 0x19 | 0x1E | 0x20..=0x27 | 0x37 => Some(rt),
+            // mfc1, dmfc1, cfc1
+            0x11 if rs <= 2 => Some(rt),
+            _ => None,
+        };
+        if let Some(reg) = written {
+            pending_hi[reg] = None;
+        }
+    }
+
+    relocs
+}
+
+fn find_ppc_pseudo_relocs(words: &[u32], vram: u64) -> HashMap<u64, PseudoReloc> {
+    let mut relocs = HashMap::new();
+    // (address of the lis, value it loaded) for each register
+    let mut pending_hi: [Option<(u64, u32)>; 32] = [None; 32];
+    let end = vram + words.len() as u64 * 4;
+
+    for (i, word) in words.iter().enumerate() {
+        let address = vram + i as u64 * 4;
+        let op = word >> 26;
+        let rd = ((word >> 21) & 0x1F) as usize;
+        let ra = ((word >> 16) & 0x1F) as usize;
+
+        match op {
+            // lis (addis rD, 0, imm)
+            15 if ra == 0 => {
+                pending_hi[rd] = Some((address, (word & 0xFFFF) << 16));
+                continue;
+            }
+            // b, bl
+            18 => {
+                let mut offset = word & 0x03FF_FFFC;
+                if offset & 0x0200_0000 != 0 {
+                    offset |= 0xFC00_0000;
+                }
+                let absolute = word & 2 != 0;
+                let link = word & 1 != 0;
+                let target = if absolute {
+                    offset as u64
+                } else {
+                    (address as u32).wrapping_add(offset) as u64
+                };
+
+                // Branches within the function aren't relocated, calls and tail calls are
+                if link || absolute || target < vram || target >= end {
+                    relocs.insert(
+                        address,
+                        PseudoReloc {
+                            target,
+                            kind: PseudoRelocKind::Jump,
+                        },
+                    );
+                }
+            }
+            // addi, loads and stores (paired-single loads and stores have a 12-bit offset, so
+            // they can't be the lower half of an address). rA = 0 stands for 0 rather than r0,
+            // e.g. in `li`.
+            14 | 32..=55 if ra != 0 => {
+                if let Some((hi_address, hi)) = pending_hi[ra] {
+                    add_pair(
+                        &mut relocs,
+                        hi_address,
+                        address,
+                        hi.wrapping_add(sign_extend_16(*word)) as u64,
+                    );
+                }
+            }
+            _ => {}
+        }
+
+        // Forget about any hi half whose register gets overwritten
+        let written = match op {
+            // mulli, subfic, addic, addic., addi, addis, loads
+            7 | 8 | 12..=15 | 32..=35 | 40..=43 | 46 => Some(rd),
+            // rotates and logical immediates write rA
+            20..=29 => Some(ra),
+            31 => match (word >> 1) & 0x3FF {
+                // logical ops (`or` being `mr`), shifts, cntlzw, extsb and extsh write rA
+                24 | 26 | 28 | 60 | 124 | 284 | 316 | 412 | 444 | 476 | 536 | 792 | 824 | 922
+                | 954 => Some(ra),
+                _ => Some(rd),
+            },
+            _ => None,
+        };
+        if let Some(reg) = written {
+            pending_hi[reg] = None;
+        }
+        // update-form loads and stores write back to rA
+        if matches!(op, 33 | 35 | 37 | 39 | 41 | 43 | 45 | 49 | 51 | 53 | 55) {
+            pending_hi[ra] = None;
+        }
+    }
+
+    relocs
+}
+
+/// ARM, Thumb and SuperH code loads addresses from literal pools. A loaded word is taken to be an
+/// address if it lies in the platform's memory map, and is keyed by where it is in the pool.
+fn find_literal_pseudo_relocs(
+    bytes: &[u8],
+    vram: u64,
+    platform: Platform,
+    arch: Arch,
+) -> HashMap<u64, PseudoReloc> {
+    let mut relocs = HashMap::new();
+
+    for (literal_address, size) in get_literals_raw(bytes, vram, platform, arch) {
+        if size != 4 {
+            continue;
+        }
+        let Some(literal_bytes) = literal_address
+            .checked_sub(vram)
+            .and_then(|offset| bytes.get(offset as usize..offset as usize + 4))
+        else {
+            continue;
+        };
+        let literal = platform
+            .endianness()
+            .read_u32_bytes(literal_bytes.try_into().unwrap());
+
+        if is_memory_address(platform, literal) {
+            relocs.insert(
+                literal_address,
+                PseudoReloc {
+                    target: literal as u64,
+                    kind: PseudoRelocKind::Absolute,
+                },
+            );
+        }
+    }
+
+    relocs
+}
+
+/// Whether a value lies in the memory map of the platform, so is likely an address: its RAM, ROM
+/// and I/O registers, with the SuperH platforms' cached and uncached mirrors
+fn is_memory_address(platform: Platform, value: u32) -> bool {
+    match platform {
+        Platform::Gba => (0x0200_0000..0x0E01_0000).contains(&value),
+        Platform::Nds => (0x01FF_8000..0x0A01_0000).contains(&value),
+        Platform::N3ds => (0x0010_0000..0x2000_0000).contains(&value),
+        Platform::Saturn => {
+            let value = value & 0x0FFF_FFFF;
+            (0x0020_0000..0x0030_0000).contains(&value)
+                || (0x0580_0000..0x0610_0000).contains(&value)
+        }
+        Platform::Dreamcast => {
+            let value = value & 0x1FFF_FFFF;
+            (0x005F_0000..0x0100_0000).contains(&value)
+                || (0x0400_0000..0x0600_0000).contains(&value)
+                || (0x0C00_0000..0x1000_0000).contains(&value)
+        }
+        _ => false,
+    }
+}
+
+/// x86 code refers to memory by its address, so a memory operand with neither a base nor an
+/// index register is an address. Immediates (e.g. `push offset x`) can only be told apart from
+/// plain constants by where they point, so they're taken as addresses if they land in one of the
+/// image's loaded sections.
+fn find_x86_pseudo_relocs(
+    bytes: &[u8],
+    vram: u64,
+    platform: Platform,
+    loaded: &[Range<u64>],
+) -> HashMap<u64, PseudoReloc> {
+    let mut relocs = HashMap::new();
+    let end = vram + bytes.len() as u64;
+    let mut decoder = Decoder::with_ip(platform.x86_bitness(), bytes, vram, DecoderOptions::NONE);
+
+    for ins in &mut decoder {
+        let is_near_branch = matches!(
+            ins.op0_kind(),
+            OpKind::NearBranch16 | OpKind::NearBranch32 | OpKind::NearBranch64
+        );
+        let has_absolute_operand = (0..ins.op_count()).any(|i| ins.op_kind(i) == OpKind::Memory)
+            && ins.memory_base() == Register::None
+            && ins.memory_index() == Register::None;
+
+        // Branches within the function aren't relocated, calls and tail calls are
+        if is_near_branch
+            && matches!(
+                ins.flow_control(),
+                FlowControl::Call | FlowControl::UnconditionalBranch
+            )
+            && (ins.near_branch_target() < vram || ins.near_branch_target() >= end)
+        {
+            relocs.insert(
+                ins.ip(),
+                PseudoReloc {
+                    target: ins.near_branch_target(),
+                    kind: PseudoRelocKind::Jump,
+                },
+            );
+        } else if has_absolute_operand {
+            relocs.insert(
+                ins.ip(),
+                PseudoReloc {
+                    target: ins.memory_displacement64(),
+                    kind: PseudoRelocKind::Absolute,
+                },
+            );
+        } else if let Some(target) = x86_address_immediate(&ins, loaded) {
+            relocs.insert(
+                ins.ip(),
+                PseudoReloc {
+                    target,
+                    kind: PseudoRelocKind::Absolute,
+                },
+            );
+        }
+    }
+
+    relocs
+}
+
+/// An immediate operand wide enough to hold an address, if it points into a loaded section
+fn x86_address_immediate(ins: &iced_x86::Instruction, loaded: &[Range<u64>]) -> Option<u64> {
+    (0..ins.op_count())
+        .filter(|i| {
+            matches!(
+                ins.op_kind(*i),
+                OpKind::Immediate16
+                    | OpKind::Immediate32
+                    | OpKind::Immediate32to64
+                    | OpKind::Immediate64
+            )
+        })
+        .map(|i| ins.immediate(i))
+        .find(|value| loaded.iter().any(|range| range.contains(value)))
+}
@@ -0,0 +1,257 @@
+//! GameCube/Wii REL modules.
+//!
+//! A REL is a relocatable module the game loads at runtime, linked against the DOL and other
+//! modules through its own relocation table. The table is applied to the code the way the game's
+//! loader would, and is also kept as the code's relocations, so REL code hashes like a relocatable
+//! object rather than relying on relocations recovered from address-forming patterns.
+//!
+//! Modules aren't loaded anywhere fixed, so each section is given its offset in the file as its
+//! address.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::pseudo_reloc::{PseudoReloc, PseudoRelocKind};
+
+const R_PPC_ADDR32: u8 = 1;
+const R_PPC_ADDR24: u8 = 2;
+const R_PPC_ADDR16: u8 = 3;
+const R_PPC_ADDR16_LO: u8 = 4;
+const R_PPC_ADDR16_HI: u8 = 5;
+const R_PPC_ADDR16_HA: u8 = 6;
+const R_PPC_ADDR14: u8 = 7;
+const R_PPC_REL24: u8 = 10;
+const R_PPC_REL14: u8 = 11;
+const R_PPC_EMB_SDA21: u8 = 109;
+/// Advances the position without relocating anything
+const R_DOLPHIN_NOP: u8 = 201;
+/// Switches to relocating another section, from its start
+const R_DOLPHIN_SECTION: u8 = 202;
+const R_DOLPHIN_END: u8 = 203;
+
+#[derive(Debug, Clone)]
+pub struct RelSection {
+    /// Offset of the section in the file, or 0 for uninitialized sections
+    pub offset: u32,
+    pub size: u32,
+    pub executable: bool,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelRelocation {
+    /// Index of the section being relocated
+    pub section: u8,
+    /// Offset of the relocated field in the section
+    pub offset: u32,
+    pub kind: u8,
+    /// Module the target is in. Module 0 is the DOL.
+    pub module: u32,
+    /// Section of the target within its module, unused for the DOL
+    pub target_section: u8,
+    /// Offset of the target in its section, or its address in the DOL
+    pub addend: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rel {
+    pub module_id: u32,
+    pub version: u32,
+    pub sections: Vec<RelSection>,
+    pub relocations: Vec<RelRelocation>,
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    bytes
+        .get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("REL is truncated at {:#x}", offset))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    bytes
+        .get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes(b.try_into().unwrap()))
+        .ok_or_else(|| anyhow!("REL is truncated at {:#x}", offset))
+}
+
+/// Whether the data looks like a REL module rather than a DOL. A DOL has a section offset where a
+/// REL has its version, which is always small.
+pub fn is_rel(bytes: &[u8]) -> bool {
+    let (Ok(num_sections), Ok(section_info), Ok(version)) = (
+        read_u32(bytes, 0x0C),
+        read_u32(bytes, 0x10),
+        read_u32(bytes, 0x1C),
+    ) else {
+        return false;
+    };
+
+    (1..=3).contains(&version)
+        && (1..=64).contains(&num_sections)
+        && section_info >= 0x40
+        && section_info as u64 + num_sections as u64 * 8 <= bytes.len() as u64
+}
+
+impl Rel {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if !is_rel(bytes) {
+            return Err(anyhow!("Not a REL module"));
+        }
+
+        let module_id = read_u32(bytes, 0x00)?;
+        let num_sections = read_u32(bytes, 0x0C)? as usize;
+        let section_info = read_u32(bytes, 0x10)? as usize;
+        let version = read_u32(bytes, 0x1C)?;
+        let imp_offset = read_u32(bytes, 0x28)? as usize;
+        let imp_size = read_u32(bytes, 0x2C)? as usize;
+
+        let mut sections = Vec::with_capacity(num_sections);
+        for i in 0..num_sections {
+            let info = read_u32(bytes, section_info + i * 8)?;
+            let size = read_u32(bytes, section_info + i * 8 + 4)?;
+            let offset = info & !1;
+            let data = if offset == 0 {
+                vec![]
+            } else {
+                bytes
+                    .get(offset as usize..offset as usize + size as usize)
+                    .ok_or_else(|| anyhow!("REL section {} is out of bounds", i))?
+                    .to_vec()
+            };
+            sections.push(RelSection {
+                offset,
+                size,
+                executable: info & 1 != 0,
+                data,
+            });
+        }
+
+        let mut relocations = Vec::new();
+        for imp in (imp_offset..imp_offset + imp_size).step_by(8) {
+            let module = read_u32(bytes, imp)?;
+            let mut entry = read_u32(bytes, imp + 4)? as usize;
+            let mut section = 0;
+            let mut position: u32 = 0;
+
+            loop {
+                let addend = read_u32(bytes, entry + 4)?;
+                let offset = read_u16(bytes, entry)?;
+                let (kind, target_section) = (bytes[entry + 2], bytes[entry + 3]);
+                entry += 8;
+
+                position = position.wrapping_add(offset as u32);
+                match kind {
+                    R_DOLPHIN_NOP => {}
+                    R_DOLPHIN_SECTION => {
+                        section = target_section;
+                        position = 0;
+                    }
+                    R_DOLPHIN_END => break,
+                    _ => relocations.push(RelRelocation {
+                        section,
+                        offset: position,
+                        kind,
+                        module,
+                        target_section,
+                        addend,
+                    }),
+                }
+            }
+        }
+
+        Ok(Self {
+            module_id,
+            version,
+            sections,
+            relocations,
+        })
+    }
+
+    /// Where a relocation points, if it's in the DOL or in this module's initialized sections
+    fn target_address(&self, reloc: &RelRelocation) -> Option<u32> {
+        if reloc.module == 0 {
+            return Some(reloc.addend);
+        }
+        if reloc.module != self.module_id {
+            return None;
+        }
+        let section = self.sections.get(reloc.target_section as usize)?;
+        (section.offset != 0).then(|| section.offset.wrapping_add(reloc.addend))
+    }
+
+    /// Patch the relocations whose targets are known into the sections, like the game's loader
+    /// does. References to other modules are left as they are.
+    pub fn apply_relocations(&mut self) {
+        for reloc in self.relocations.clone() {
+            let Some(target) = self.target_address(&reloc) else {
+                continue;
+            };
+            let Some(section) = self.sections.get_mut(reloc.section as usize) else {
+                continue;
+            };
+            let place = section.offset.wrapping_add(reloc.offset);
+            let start = reloc.offset as usize;
+
+            let patch_u16 = |data: &mut Vec<u8>, value: u32| {
+                if let Some(field) = data.get_mut(start..start + 2) {
+                    field.copy_from_slice(&(value as u16).to_be_bytes());
+                }
+            };
+            let patch_u32 = |data: &mut Vec<u8>, mask: u32, value: u32| {
+                if let Some(field) = data.get_mut(start..start + 4) {
+                    let word = u32::from_be_bytes((&*field).try_into().unwrap());
+                    field.copy_from_slice(&((word & !mask) | (value & mask)).to_be_bytes());
+                }
+            };
+
+            let data = &mut section.data;
+            match reloc.kind {
+                R_PPC_ADDR32 => patch_u32(data, u32::MAX, target),
+                R_PPC_ADDR24 => patch_u32(data, 0x03FF_FFFC, target),
+                R_PPC_ADDR16 | R_PPC_ADDR16_LO => patch_u16(data, target),
+                R_PPC_ADDR16_HI => patch_u16(data, target >> 16),
+                R_PPC_ADDR16_HA => patch_u16(data, target.wrapping_add(0x8000) >> 16),
+                R_PPC_ADDR14 => patch_u32(data, 0xFFFC, target),
+                R_PPC_REL24 => patch_u32(data, 0x03FF_FFFC, target.wrapping_sub(place)),
+                R_PPC_REL14 => patch_u32(data, 0xFFFC, target.wrapping_sub(place)),
+                _ => {}
+            }
+        }
+    }
+
+    /// The relocations of the module's code, keyed by the address of the relocated instruction.
+    /// Targets outside the DOL and this module's initialized sections are identified by module,
+    /// section and offset, above the 32-bit address space.
+    pub(crate) fn code_relocs(&self) -> HashMap<u64, PseudoReloc> {
+        let mut ret = HashMap::new();
+        for reloc in &self.relocations {
+            let Some(section) = self.sections.get(reloc.section as usize) else {
+                continue;
+            };
+            if !section.executable {
+                continue;
+            }
+
+            let kind = match reloc.kind {
+                R_PPC_ADDR16_HA | R_PPC_ADDR16_HI => PseudoRelocKind::Hi,
+                R_PPC_ADDR16_LO | R_PPC_ADDR16 => PseudoRelocKind::Lo,
+                R_PPC_ADDR24 | R_PPC_ADDR14 | R_PPC_REL24 | R_PPC_REL14 => PseudoRelocKind::Jump,
+                R_PPC_EMB_SDA21 => PseudoRelocKind::GpRel,
+                _ => continue,
+            };
+            let target = match self.target_address(reloc) {
+                Some(address) => address as u64,
+                None => {
+                    (reloc.module as u64) << 40
+                        | (reloc.target_section as u64) << 32
+                        | reloc.addend as u64
+                }
+            };
+
+            let address = section.offset.wrapping_add(reloc.offset) & !3;
+            ret.insert(address as u64, PseudoReloc { target, kind });
+        }
+        ret
+    }
+}
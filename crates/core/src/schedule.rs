@@ -0,0 +1,223 @@
+//! Scheduling-tolerant comparison.
+//!
+//! Compilers are free to reorder instructions that don't depend on each other, and to move an
+//! instruction into a branch's delay slot or leave a nop there instead. IDO and GCC often differ in
+//! nothing else, which aligning instruction by instruction counts as edits. Each symbol gets a
+//! canonical order of its instructions: delay slots are moved in front of their branch where that
+//! doesn't change what the code does, delay slot nops are dropped, and the instructions between
+//! branches are sorted as far as their dependencies allow. Symbols put in that order compare
+//! equal when they only differ in scheduling.
+
+use std::collections::BTreeSet;
+
+use crate::cfg::{Cfg, CfgInsn, InsnFlow};
+use crate::hash::stable_hash;
+use crate::{Arch, Platform, Symbol, arch};
+
+const HI: u32 = 64;
+const LO: u32 = 65;
+/// The floating point condition flag, set by comparisons and read by `bc1f`/`bc1t`
+const FCC: u32 = 66;
+
+/// The registers and memory an instruction reads and writes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct DefUse {
+    /// Registers written, as a bit set: GPRs, then FPRs, then HI, LO and the FP condition flag
+    pub defs: u128,
+    /// Registers read, in the same bit set
+    pub uses: u128,
+    pub load: bool,
+    pub store: bool,
+    /// The instruction has effects that aren't tracked (coprocessor control, syscalls, unknown
+    /// instructions), so nothing can be moved across it
+    pub barrier: bool,
+    /// The delay slot of a branch likely only executes when the branch is taken
+    pub branch_likely: bool,
+    pub nop: bool,
+}
+
+impl DefUse {
+    pub(crate) fn gpr(reg: u32) -> u128 {
+        // $zero is always zero, writing it does nothing
+        if reg == 0 { 0 } else { 1 << reg }
+    }
+
+    /// FPRs are tracked in even/odd pairs, as doubles take up both
+    pub(crate) fn fpr(reg: u32) -> u128 {
+        0b11 << (32 + (reg & !1))
+    }
+
+    pub(crate) fn hi() -> u128 {
+        1 << HI
+    }
+
+    pub(crate) fn lo() -> u128 {
+        1 << LO
+    }
+
+    pub(crate) fn fcc() -> u128 {
+        1 << FCC
+    }
+
+    /// Whether this instruction has to stay after an earlier one
+    fn depends_on(&self, earlier: &DefUse) -> bool {
+        self.barrier
+            || earlier.barrier
+            || self.uses & earlier.defs != 0
+            || self.defs & earlier.uses != 0
+            || self.defs & earlier.defs != 0
+            || (self.store && (earlier.load || earlier.store))
+            || (self.load && earlier.store)
+    }
+}
+
+/// Get the order a symbol's instructions are compared in when tolerating differences in
+/// scheduling, as indices into its instructions. Instructions left out of the order (delay slot
+/// nops) are ignored by the comparison. Only MIPS is reordered, other architectures keep their
+/// instructions as they are.
+pub(crate) fn canonical_order(
+    bytes: &[u8],
+    platform: Platform,
+    opcodes: &[u16],
+    insn_equiv_hashes: &[u64],
+    flows: &[CfgInsn],
+    cfg: &Cfg,
+) -> Vec<usize> {
+    let identity = (0..opcodes.len()).collect();
+    if platform.arch() != Arch::Mips {
+        return identity;
+    }
+
+    let def_uses = arch::get_mips_def_uses(bytes, platform);
+    if def_uses.len() != opcodes.len() || flows.len() != opcodes.len() {
+        return identity;
+    }
+
+    let leaders: BTreeSet<usize> = cfg.blocks.iter().map(|b| b.start).collect();
+    let ends_segment = |i: usize| !matches!(flows[i].flow, InsnFlow::Next) || def_uses[i].barrier;
+
+    // Fill each delay slot from before its branch where possible
+    let mut filled: Vec<usize> = Vec::with_capacity(opcodes.len());
+    let mut i = 0;
+    while i < opcodes.len() {
+        let slot = i + 1;
+        if !flows[i].delay_slot || slot >= opcodes.len() {
+            filled.push(i);
+            i += 1;
+            continue;
+        }
+
+        let (branch, filler) = (&def_uses[i], &def_uses[slot]);
+        if filler.nop {
+            filled.push(i);
+        } else if !branch.branch_likely
+            && !leaders.contains(&i)
+            && !leaders.contains(&slot)
+            && !filler.depends_on(branch)
+        {
+            filled.extend([slot, i]);
+        } else {
+            filled.extend([i, slot]);
+        }
+        i += 2;
+    }
+
+    // Sort the instructions between branches, barriers and branch targets
+    let sort = |segment: &[usize]| sort_segment(segment, opcodes, insn_equiv_hashes, &def_uses);
+    let mut order = Vec::with_capacity(filled.len());
+    let mut segment: Vec<usize> = Vec::new();
+    for &i in &filled {
+        if leaders.contains(&i) && !segment.is_empty() {
+            order.extend(sort(&segment));
+            segment.clear();
+        }
+        if ends_segment(i) {
+            // The branch, call or barrier itself stays at the end
+            order.extend(sort(&segment));
+            order.push(i);
+            segment.clear();
+        } else {
+            segment.push(i);
+        }
+    }
+    order.extend(sort(&segment));
+
+    order
+}
+
+/// Order instructions by opcode, then by their equivalent hash, as far as their dependencies on
+/// each other allow
+fn sort_segment(
+    segment: &[usize],
+    opcodes: &[u16],
+    insn_equiv_hashes: &[u64],
+    def_uses: &[DefUse],
+) -> Vec<usize> {
+    let n = segment.len();
+    let mut successors: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut in_degrees = vec![0; n];
+    for (later, &i) in segment.iter().enumerate() {
+        for (earlier, &j) in segment[..later].iter().enumerate() {
+            if def_uses[i].depends_on(&def_uses[j]) {
+                successors[earlier].push(later);
+                in_degrees[later] += 1;
+            }
+        }
+    }
+
+    let key = |pos: usize| {
+        let i = segment[pos];
+        (opcodes[i], insn_equiv_hashes[i], pos)
+    };
+
+    let mut ready: BTreeSet<_> = (0..n).filter(|&p| in_degrees[p] == 0).map(key).collect();
+    let mut ret = Vec::with_capacity(n);
+    while let Some((_, _, pos)) = ready.pop_first() {
+        ret.push(segment[pos]);
+        for &next in &successors[pos] {
+            in_degrees[next] -= 1;
+            if in_degrees[next] == 0 {
+                ready.insert(key(next));
+            }
+        }
+    }
+    ret
+}
+
+impl Symbol {
+    /// A copy of the symbol with its instructions in their canonical order, so that comparing it
+    /// tolerates differences in scheduling. Its bytes are left as they are.
+    pub fn rescheduled(&self) -> Symbol {
+        let order = &self.canonical_order;
+        let opcodes: Vec<u16> = order.iter().map(|&i| self.opcodes[i]).collect();
+        let insn_equiv_hashes: Vec<u64> =
+            order.iter().map(|&i| self.insn_equiv_hashes[i]).collect();
+        let insn_reloc_targets = order.iter().map(|&i| self.insn_reloc_targets[i]).collect();
+
+        // Instructions only move within their block, so blocks just shrink by what was dropped
+        let mut kept = vec![false; self.opcodes.len()];
+        for &i in order {
+            kept[i] = true;
+        }
+        let mut new_index = vec![0];
+        for kept in kept {
+            new_index.push(new_index.last().unwrap() + usize::from(kept));
+        }
+        let mut cfg = self.cfg.clone();
+        for block in &mut cfg.blocks {
+            block.start = new_index[block.start];
+            block.end = new_index[block.end];
+        }
+
+        Symbol {
+            opcode_hash: stable_hash(&opcodes),
+            equiv_hash: stable_hash(&insn_equiv_hashes),
+            canonical_order: (0..opcodes.len()).collect(),
+            opcodes,
+            insn_equiv_hashes,
+            insn_reloc_targets,
+            cfg,
+            ..self.clone()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! The semantic level: equivalent code that refers to the same well-known symbols.
+//!
+//! The equivalent level numbers relocations by the order their targets first appear in, so a
+//! function calling `osSetIntMask` hashes the same as one calling `bzero` at the same spot. The
+//! semantic hash also covers the names of what the code refers to, but only names that mean the
+//! same thing across projects. Placeholders derived from an address, like splat's
+//! `func_80012345`, name the same function differently in every project and are left out.
+
+use crate::hash::stable_hash;
+
+/// Prefixes that tools put in front of an address to name symbols they know nothing about
+const PLACEHOLDER_PREFIXES: &[&str] = &[
+    "func_", "D_", "B_", "jtbl_", "jpt_", "lbl_", "fn_", "sub_", "loc_", "off_", "unk_", "FUN_",
+    "DAT_", "LAB_", "PTR_", "L",
+];
+
+/// Placeholders are a prefix and an address, optionally followed by more (e.g. a ROM offset)
+fn is_placeholder(name: &str) -> bool {
+    PLACEHOLDER_PREFIXES.iter().any(|prefix| {
+        name.strip_prefix(prefix).is_some_and(|rest| {
+            let digits = rest.chars().take_while(|c| c.is_ascii_hexdigit()).count();
+            digits >= 6
+                && rest[digits..]
+                    .chars()
+                    .next()
+                    .is_none_or(|c| c == '_' || c == '.')
+        })
+    })
+}
+
+/// Whether a symbol name is likely to refer to the same thing in other projects
+pub fn is_well_known_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with(['.', '$', '@']) && !is_placeholder(name)
+}
+
+/// Hash a symbol's instructions at the equivalent level together with the well-known names of
+/// what each refers to. Returns `None` if it refers to no well-known symbols, as the semantic
+/// level would then say no more than the equivalent one.
+pub(crate) fn semantic_hash(
+    insn_equiv_hashes: &[u64],
+    target_names: &[Option<String>],
+) -> Option<u64> {
+    let names: Vec<Option<&str>> = target_names
+        .iter()
+        .map(|name| name.as_deref().filter(|name| is_well_known_name(name)))
+        .collect();
+
+    if names.iter().all(Option::is_none) {
+        return None;
+    }
+
+    Some(stable_hash(&(insn_equiv_hashes, names)))
+}
@@ -0,0 +1,117 @@
+//! How likely a match is to have happened by chance.
+//!
+//! A similarity score alone says nothing about length: a five-instruction getter matches dozens of
+//! unrelated functions perfectly. Matches are instead scored by how much information the matching
+//! instructions carry, given how often each opcode occurs across a corpus of symbols, and by the
+//! number of matches that would be expected by chance in a search of that size (an e-value, as in
+//! sequence search tools like BLAST).
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::Symbol;
+use crate::align::{Alignment, AlignmentKind, align_symbols};
+
+/// How often each opcode occurs across a corpus of symbols
+#[derive(Debug, Clone, Default)]
+pub struct OpcodeBackground {
+    counts: HashMap<u16, u64>,
+    total: u64,
+    /// Average information of an instruction in bits, which each edit costs
+    edit_cost: f64,
+}
+
+impl OpcodeBackground {
+    pub fn new<'a>(symbols: impl IntoIterator<Item = &'a Symbol>) -> Self {
+        let mut counts: HashMap<u16, u64> = HashMap::new();
+        for opcode in symbols.into_iter().flat_map(|s| &s.opcodes) {
+            *counts.entry(*opcode).or_default() += 1;
+        }
+        Self::from_counts(counts)
+    }
+
+    pub fn from_counts(counts: HashMap<u16, u64>) -> Self {
+        let mut ret = Self {
+            total: counts.values().sum(),
+            counts,
+            edit_cost: 0.0,
+        };
+
+        if ret.total > 0 {
+            ret.edit_cost = ret
+                .counts
+                .iter()
+                .map(|(opcode, count)| *count as f64 / ret.total as f64 * ret.opcode_bits(*opcode))
+                .sum();
+        }
+        ret
+    }
+
+    /// Number of times each opcode occurs
+    pub fn counts(&self) -> &HashMap<u16, u64> {
+        &self.counts
+    }
+
+    /// Information in bits of an instruction having this opcode. Each opcode is counted once more
+    /// than it occurs, so that unseen ones are rare rather than impossible.
+    pub fn opcode_bits(&self, opcode: u16) -> f64 {
+        let count = self.counts.get(&opcode).copied().unwrap_or(0);
+        let p = (count + 1) as f64 / (self.total + self.counts.len() as u64 + 1) as f64;
+        -p.log2()
+    }
+
+    /// Information in bits of a sequence of opcodes, which is what an identical match is worth
+    pub fn sequence_bits(&self, opcodes: &[u16]) -> f64 {
+        opcodes.iter().map(|opcode| self.opcode_bits(*opcode)).sum()
+    }
+
+    /// Information in bits of the instructions an alignment pairs up, less the cost of the edits
+    /// between them. Edits before the first or after the last matching instruction are free, so
+    /// a function contained in a bigger one isn't penalized for the rest of it.
+    pub fn alignment_bits(&self, sym1: &Symbol, alignment: &Alignment) -> f64 {
+        let is_match = |kind: AlignmentKind| kind == AlignmentKind::Match;
+        let Some(first) = alignment.rows.iter().position(|r| is_match(r.kind)) else {
+            return 0.0;
+        };
+        let last = alignment
+            .rows
+            .iter()
+            .rposition(|r| is_match(r.kind))
+            .unwrap();
+
+        let bits: f64 = alignment.rows[first..=last]
+            .iter()
+            .map(|r| match (r.kind, r.left) {
+                (AlignmentKind::Match, Some(i)) => self.opcode_bits(sym1.opcodes[i]),
+                _ => -self.edit_cost,
+            })
+            .sum();
+        bits.max(0.0)
+    }
+
+    /// How significant a match between two symbols is, when searching among `search_space` symbols
+    pub fn significance(&self, sym1: &Symbol, sym2: &Symbol, search_space: usize) -> Significance {
+        let bits = self.alignment_bits(sym1, &align_symbols(sym1, sym2));
+        Significance::new(bits, search_space)
+    }
+}
+
+/// How unlikely a match is to have happened by chance
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Significance {
+    /// Information in the match, in bits
+    pub bits: f32,
+    /// Number of matches at least this good expected by chance in the search. Lower is more
+    /// significant, and values near or above 1 are likely coincidences.
+    pub evalue: f64,
+}
+
+impl Significance {
+    pub fn new(bits: f64, search_space: usize) -> Self {
+        Self {
+            bits: bits as f32,
+            evalue: search_space as f64 * (-bits).exp2(),
+        }
+    }
+}
@@ -0,0 +1,470 @@
+//! Finding runs of instructions that symbols have in common.
+//!
+//! Every window of `window_size` opcodes is hashed, and two windows with the same hash at
+//! offsets `i` and `j` are a hit on the diagonal `j - i`. Consecutive hits on the same diagonal
+//! are merged into a single maximal run, which covers the windows' instructions.
+//!
+//! Gapped submatching then uses those runs as seeds: runs close enough to one another are chained
+//! together across the instructions between them, and the result is extended outwards, as long as
+//! no more than a set number of instructions are mismatched or inserted along the way.
+
+use std::collections::HashMap;
+
+use crate::{Binary, InsnSeqMatch, Symbol};
+
+/// Merge hits into the maximal runs along each diagonal. `hits` are `(offset1, offset2)` pairs of
+/// matching windows.
+pub(crate) fn collect_runs(mut hits: Vec<(usize, usize)>, window_size: usize) -> Vec<InsnSeqMatch> {
+    // Group by diagonal, then walk down each one
+    hits.sort_by_key(|&(i, j)| (j as isize - i as isize, i));
+
+    let mut runs: Vec<InsnSeqMatch> = Vec::new();
+    let mut prev: Option<(usize, usize)> = None;
+
+    for (i, j) in hits {
+        match (prev, runs.last_mut()) {
+            (Some((prev_i, prev_j)), Some(run)) if i == prev_i + 1 && j == prev_j + 1 => {
+                run.length += 1;
+            }
+            _ => runs.push(InsnSeqMatch {
+                offset1: i,
+                offset2: j,
+                length: window_size,
+            }),
+        }
+        prev = Some((i, j));
+    }
+
+    runs.sort_by_key(|r| (r.offset1, r.offset2));
+    runs
+}
+
+/// How gapped submatches are scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GapScoring {
+    /// Score for each matching instruction
+    pub match_score: i32,
+    /// Penalty for each substituted instruction
+    pub mismatch_penalty: i32,
+    /// Penalty for each inserted or deleted instruction
+    pub gap_penalty: i32,
+    /// Most mismatching or inserted instructions a region can span
+    pub max_edits: usize,
+}
+
+impl Default for GapScoring {
+    fn default() -> Self {
+        Self {
+            match_score: 1,
+            mismatch_penalty: 1,
+            gap_penalty: 2,
+            max_edits: 4,
+        }
+    }
+}
+
+impl GapScoring {
+    /// Score for skipping `len1` instructions in the first symbol and `len2` in the second,
+    /// assuming none of them match
+    fn skip_score(&self, len1: usize, len2: usize) -> i32 {
+        -(len1.min(len2) as i32 * self.mismatch_penalty
+            + len1.abs_diff(len2) as i32 * self.gap_penalty)
+    }
+}
+
+/// A region two symbols have in common, allowing for some differences
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GappedMatch {
+    /// Offset of the region in the first symbol, in instructions
+    pub offset1: usize,
+    /// Length of the region in the first symbol, in instructions
+    pub length1: usize,
+    /// Offset of the region in the second symbol, in instructions
+    pub offset2: usize,
+    /// Length of the region in the second symbol, in instructions
+    pub length2: usize,
+    /// Number of instructions that match
+    pub matches: usize,
+    /// Number of substituted, inserted or deleted instructions
+    pub edits: usize,
+}
+
+impl GappedMatch {
+    fn from_run(run: &InsnSeqMatch) -> Self {
+        Self {
+            offset1: run.offset1,
+            length1: run.length,
+            offset2: run.offset2,
+            length2: run.length,
+            matches: run.length,
+            edits: 0,
+        }
+    }
+
+    /// Share of the region's aligned instructions that match, from 0 to 1
+    pub fn identity(&self) -> f32 {
+        if self.matches + self.edits == 0 {
+            return 0.0;
+        }
+        self.matches as f32 / (self.matches + self.edits) as f32
+    }
+
+    fn contains(&self, other: &GappedMatch) -> bool {
+        self.offset1 <= other.offset1
+            && other.offset1 + other.length1 <= self.offset1 + self.length1
+            && self.offset2 <= other.offset2
+            && other.offset2 + other.length2 <= self.offset2 + self.length2
+    }
+}
+
+/// Chain exact runs into gapped regions, without looking at the instructions between them. The
+/// instructions skipped between two runs are all assumed to differ, which makes this usable
+/// where only the runs are known.
+pub fn chain_runs(runs: &[InsnSeqMatch], scoring: &GapScoring) -> Vec<GappedMatch> {
+    let mut runs = runs.to_vec();
+    runs.sort_by_key(|r| (r.offset1, r.offset2));
+
+    let mut chains: Vec<GappedMatch> = Vec::new();
+
+    for run in &runs {
+        // Attach the run to whichever chain it continues most cheaply
+        let best = chains
+            .iter()
+            .enumerate()
+            .filter_map(|(i, chain)| {
+                let skipped1 = run.offset1.checked_sub(chain.offset1 + chain.length1)?;
+                let skipped2 = run.offset2.checked_sub(chain.offset2 + chain.length2)?;
+                let edits = skipped1.max(skipped2);
+
+                let gain = run.length as i32 * scoring.match_score
+                    + scoring.skip_score(skipped1, skipped2);
+                (chain.edits + edits <= scoring.max_edits && gain > 0).then_some((i, edits, gain))
+            })
+            .max_by_key(|(_, _, gain)| *gain);
+
+        match best {
+            Some((i, edits, _)) => {
+                let chain = &mut chains[i];
+                chain.length1 = run.offset1 + run.length - chain.offset1;
+                chain.length2 = run.offset2 + run.length - chain.offset2;
+                chain.matches += run.length;
+                chain.edits += edits;
+            }
+            None => chains.push(GappedMatch::from_run(run)),
+        }
+    }
+
+    chains
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Extension {
+    length1: usize,
+    length2: usize,
+    matches: usize,
+    edits: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    score: i32,
+    matches: usize,
+    edits: usize,
+}
+
+/// Align `x` against `y` from their starts, with at most `max_edits` edits. With `to_end`, both
+/// are aligned in full if that's possible. Otherwise, the alignment ends wherever it scores best.
+fn align_prefix(
+    x: &[u16],
+    y: &[u16],
+    scoring: &GapScoring,
+    max_edits: usize,
+    to_end: bool,
+) -> Option<Extension> {
+    let (n, m) = (x.len(), y.len());
+    let k = max_edits;
+    let width = 2 * k + 1;
+    let idx = |p: usize, q: usize| p * width + (q + k - p);
+    let in_band = |p: usize, q: usize| p.abs_diff(q) <= k;
+
+    let mut cells: Vec<Option<Cell>> = vec![None; (n + 1) * width];
+    cells[idx(0, 0)] = Some(Cell {
+        score: 0,
+        matches: 0,
+        edits: 0,
+    });
+
+    let mut best = Extension::default();
+    let mut best_score = 0;
+
+    for p in 0..=n {
+        let mut reachable = p == 0;
+
+        for q in p.saturating_sub(k)..=(p + k).min(m) {
+            if p == 0 && q == 0 {
+                continue;
+            }
+
+            let mut cell: Option<Cell> = None;
+            let mut consider = |from: Option<Cell>, score: i32, matched: bool| {
+                let Some(from) = from else {
+                    return;
+                };
+                let edits = from.edits + usize::from(!matched);
+                if edits > max_edits {
+                    return;
+                }
+                let candidate = Cell {
+                    score: from.score + score,
+                    matches: from.matches + usize::from(matched),
+                    edits,
+                };
+                if cell.is_none_or(|c| {
+                    candidate.score > c.score
+                        || (candidate.score == c.score && candidate.edits < c.edits)
+                }) {
+                    cell = Some(candidate);
+                }
+            };
+
+            if p > 0 && q > 0 {
+                let matched = x[p - 1] == y[q - 1];
+                let score = if matched {
+                    scoring.match_score
+                } else {
+                    -scoring.mismatch_penalty
+                };
+                consider(cells[idx(p - 1, q - 1)], score, matched);
+            }
+            if p > 0 && in_band(p - 1, q) {
+                consider(cells[idx(p - 1, q)], -scoring.gap_penalty, false);
+            }
+            if q > 0 && in_band(p, q - 1) {
+                consider(cells[idx(p, q - 1)], -scoring.gap_penalty, false);
+            }
+
+            cells[idx(p, q)] = cell;
+
+            if let Some(c) = cell {
+                reachable = true;
+                if !to_end && c.score > best_score {
+                    best_score = c.score;
+                    best = Extension {
+                        length1: p,
+                        length2: q,
+                        matches: c.matches,
+                        edits: c.edits,
+                    };
+                }
+            }
+        }
+
+        // Every alignment has run out of edits
+        if !reachable {
+            break;
+        }
+    }
+
+    if !to_end {
+        return Some(best);
+    }
+    if !in_band(n, m) {
+        return None;
+    }
+    cells[idx(n, m)].map(|c| Extension {
+        length1: n,
+        length2: m,
+        matches: c.matches,
+        edits: c.edits,
+    })
+}
+
+/// Find the regions two sequences of opcodes have in common, starting from the exact runs
+/// between them. Runs are chained as by [`chain_runs`], then the chains are realigned against
+/// the actual instructions and extended outwards with whatever edits they have left.
+pub fn extend_runs(
+    opcodes1: &[u16],
+    opcodes2: &[u16],
+    runs: &[InsnSeqMatch],
+    scoring: &GapScoring,
+) -> Vec<GappedMatch> {
+    let mut regions: Vec<GappedMatch> = chain_runs(runs, scoring)
+        .into_iter()
+        .map(|mut region| {
+            // The skipped instructions may not all differ
+            if region.edits > 0
+                && let Some(aligned) = align_prefix(
+                    &opcodes1[region.offset1..region.offset1 + region.length1],
+                    &opcodes2[region.offset2..region.offset2 + region.length2],
+                    scoring,
+                    region.edits,
+                    true,
+                )
+            {
+                region.matches = aligned.matches;
+                region.edits = aligned.edits;
+            }
+
+            let before1: Vec<u16> = opcodes1[..region.offset1].iter().rev().copied().collect();
+            let before2: Vec<u16> = opcodes2[..region.offset2].iter().rev().copied().collect();
+            if let Some(left) = align_prefix(
+                &before1,
+                &before2,
+                scoring,
+                scoring.max_edits - region.edits,
+                false,
+            ) {
+                region.offset1 -= left.length1;
+                region.offset2 -= left.length2;
+                region.length1 += left.length1;
+                region.length2 += left.length2;
+                region.matches += left.matches;
+                region.edits += left.edits;
+            }
+
+            let end1 = region.offset1 + region.length1;
+            let end2 = region.offset2 + region.length2;
+            if let Some(right) = align_prefix(
+                &opcodes1[end1..],
+                &opcodes2[end2..],
+                scoring,
+                scoring.max_edits - region.edits,
+                false,
+            ) {
+                region.length1 += right.length1;
+                region.length2 += right.length2;
+                region.matches += right.matches;
+                region.edits += right.edits;
+            }
+
+            region
+        })
+        .collect();
+
+    // Chains that were extended into one another are reported once
+    regions.sort_by_key(|r| (r.offset1, r.offset2, std::cmp::Reverse(r.length1)));
+    let mut ret: Vec<GappedMatch> = Vec::new();
+    for region in regions {
+        if !ret.iter().any(|r| r.contains(&region)) {
+            ret.push(region);
+        }
+    }
+    ret
+}
+
+/// The runs or regions a query has in common with one indexed symbol
+#[derive(Debug)]
+pub struct SymbolSubmatches<'a, M = InsnSeqMatch> {
+    pub binary: &'a Binary,
+    pub symbol: &'a Symbol,
+    pub matches: Vec<M>,
+}
+
+/// An index from each window of opcodes to everywhere it occurs, across any number of binaries.
+/// Symbols shorter than the window aren't indexed.
+pub struct SubmatchIndex<'a> {
+    window_size: usize,
+    symbols: Vec<(&'a Binary, &'a Symbol)>,
+    /// window hash -> (index into `symbols`, offset of the window)
+    positions: HashMap<u64, Vec<(usize, usize)>>,
+}
+
+impl<'a> SubmatchIndex<'a> {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            window_size,
+            symbols: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    pub fn add_binary(&mut self, binary: &'a Binary) {
+        for symbol in binary
+            .symbols
+            .iter()
+            .filter(|s| s.opcodes.len() >= self.window_size)
+        {
+            let symbol_id = self.symbols.len();
+            self.symbols.push((binary, symbol));
+
+            for (offset, hash) in symbol
+                .get_opcode_hashes(self.window_size)
+                .into_iter()
+                .enumerate()
+            {
+                self.positions
+                    .entry(hash)
+                    .or_default()
+                    .push((symbol_id, offset));
+            }
+        }
+    }
+
+    pub fn window_size(&self) -> usize {
+        self.window_size
+    }
+
+    /// Find every maximal run the query has in common with the indexed symbols, grouped by
+    /// symbol in the order they were indexed. The query itself is included if it's indexed.
+    pub fn query(&self, query: &Symbol) -> Vec<SymbolSubmatches<'a>> {
+        if query.opcodes.len() < self.window_size {
+            return Vec::new();
+        }
+
+        let mut hits: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+
+        for (offset1, hash) in query
+            .get_opcode_hashes(self.window_size)
+            .into_iter()
+            .enumerate()
+        {
+            let Some(positions) = self.positions.get(&hash) else {
+                continue;
+            };
+
+            let window = &query.opcodes[offset1..offset1 + self.window_size];
+            for &(symbol_id, offset2) in positions {
+                // Guard against hash collisions
+                let (_, symbol) = self.symbols[symbol_id];
+                if symbol.opcodes[offset2..offset2 + self.window_size] == *window {
+                    hits.entry(symbol_id).or_default().push((offset1, offset2));
+                }
+            }
+        }
+
+        let mut ret: Vec<(usize, SymbolSubmatches)> = hits
+            .into_iter()
+            .map(|(symbol_id, hits)| {
+                let (binary, symbol) = self.symbols[symbol_id];
+                let matches = collect_runs(hits, self.window_size);
+                (
+                    symbol_id,
+                    SymbolSubmatches {
+                        binary,
+                        symbol,
+                        matches,
+                    },
+                )
+            })
+            .collect();
+
+        ret.sort_by_key(|(symbol_id, _)| *symbol_id);
+        ret.into_iter().map(|(_, m)| m).collect()
+    }
+
+    /// Like [`Self::query`], but the runs are chained and extended into gapped regions
+    pub fn query_gapped(
+        &self,
+        query: &Symbol,
+        scoring: &GapScoring,
+    ) -> Vec<SymbolSubmatches<'a, GappedMatch>> {
+        self.query(query)
+            .into_iter()
+            .map(|m| SymbolSubmatches {
+                binary: m.binary,
+                symbol: m.symbol,
+                matches: extend_runs(&query.opcodes, &m.symbol.opcodes, &m.matches, scoring),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,119 @@
+//! Symbol lists in the format splat reads (`symbol_addrs.txt`), one symbol per line:
+//!
+//! ```text
+//! func_80012345 = 0x80012345; // type:func size:0x40
+//! D_800A0000 = 0x800A0000; // type:s32
+//! ```
+//!
+//! The comment's attributes are optional. Symbols without a type are taken to be functions if
+//! they're in code. `rom:` gives where a symbol is in the ROM, to tell apart symbols of overlays
+//! that share VRAM.
+//!
+//! ds-decomp's `symbols.txt` is read too, where a function's kind gives its instruction set:
+//!
+//! ```text
+//! func_02000800 kind:function(thumb,size=0x50) addr:0x02000800
+//! data_020C3000 kind:data(any) addr:0x020c3000
+//! ```
+
+use crate::Arch;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListedSymbol {
+    pub name: String,
+    pub vram: u32,
+    pub size: Option<u32>,
+    pub rom: Option<u32>,
+    /// Whether the symbol is a function, or has no type and may be one
+    pub is_function: bool,
+    /// The instruction set of an ARM function, if the list gives it
+    pub arch: Option<Arch>,
+}
+
+fn parse_number(token: &str) -> Option<u32> {
+    match token
+        .strip_prefix("0x")
+        .or_else(|| token.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => token.parse().ok(),
+    }
+}
+
+fn parse_line(line: &str) -> Option<ListedSymbol> {
+    let (definition, attributes) = line.split_once("//").unwrap_or((line, ""));
+    let (name, value) = definition.split_once('=')?;
+    let name = name.trim();
+    let vram = parse_number(value.trim().trim_end_matches(';').trim())?;
+    if name.is_empty() {
+        return None;
+    }
+
+    let mut size = None;
+    let mut rom = None;
+    let mut is_function = true;
+    for attribute in attributes.split_whitespace() {
+        match attribute.split_once(':') {
+            Some(("size", value)) => size = parse_number(value),
+            Some(("rom", value)) => rom = parse_number(value),
+            Some(("type", value)) => is_function = value == "func",
+            _ => {}
+        }
+    }
+
+    Some(ListedSymbol {
+        name: name.to_string(),
+        vram,
+        size,
+        rom,
+        is_function,
+        arch: None,
+    })
+}
+
+fn parse_ds_decomp_line(line: &str) -> Option<ListedSymbol> {
+    let mut tokens = line.split_whitespace();
+    let name = tokens.next()?;
+
+    let mut kind = None;
+    let mut vram = None;
+    for token in tokens {
+        match token.split_once(':') {
+            Some(("kind", value)) => kind = Some(value),
+            Some(("addr", value)) => vram = parse_number(value),
+            _ => {}
+        }
+    }
+
+    // e.g. function(arm,size=0x50,unknown)
+    let kind = kind?;
+    let (kind, args) = kind.split_once('(').unwrap_or((kind, ""));
+    let mut size = None;
+    let mut arch = None;
+    for arg in args.trim_end_matches(')').split(',') {
+        match arg.split_once('=') {
+            Some(("size", value)) => size = parse_number(value),
+            None if arg == "arm" => arch = Some(Arch::Arm),
+            None if arg == "thumb" => arch = Some(Arch::Thumb),
+            _ => {}
+        }
+    }
+
+    let is_function = kind == "function";
+    Some(ListedSymbol {
+        name: name.to_string(),
+        vram: vram?,
+        size,
+        rom: None,
+        is_function,
+        arch: if is_function { arch } else { None },
+    })
+}
+
+/// Parse a symbol list, skipping lines that don't define a symbol
+pub fn parse(list_str: &str) -> Vec<ListedSymbol> {
+    list_str
+        .lines()
+        .filter_map(|line| parse_line(line).or_else(|| parse_ds_decomp_line(line)))
+        .collect()
+}
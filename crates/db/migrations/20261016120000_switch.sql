@@ -0,0 +1,2 @@
+INSERT INTO versions (name, platform, project_id)
+VALUES ('switch', 9, (SELECT id FROM projects WHERE name = 'decomp.me'));
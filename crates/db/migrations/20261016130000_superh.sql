@@ -0,0 +1,5 @@
+INSERT INTO versions (name, platform, project_id)
+VALUES ('saturn', 10, (SELECT id FROM projects WHERE name = 'decomp.me'));
+
+INSERT INTO versions (name, platform, project_id)
+VALUES ('dreamcast', 11, (SELECT id FROM projects WHERE name = 'decomp.me'));
@@ -0,0 +1,5 @@
+INSERT INTO versions (name, platform, project_id)
+VALUES ('win32', 12, (SELECT id FROM projects WHERE name = 'decomp.me'));
+
+INSERT INTO versions (name, platform, project_id)
+VALUES ('msdos', 13, (SELECT id FROM projects WHERE name = 'decomp.me'));
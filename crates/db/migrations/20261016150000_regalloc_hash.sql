@@ -0,0 +1,5 @@
+-- Registers are only renamed on MIPS and PPC platforms, so the hash is null elsewhere. It's also
+-- null for symbols added before it existed, until they're reindexed
+ALTER TABLE symbols ADD COLUMN regalloc_hash BIGINT;
+
+CREATE INDEX IF NOT EXISTS regalloc_hash_idx ON symbols (regalloc_hash);
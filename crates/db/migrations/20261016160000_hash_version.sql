@@ -0,0 +1,2 @@
+-- Symbols added before hashes were versioned were hashed with std's DefaultHasher
+ALTER TABLE symbols ADD COLUMN hash_version INT NOT NULL DEFAULT 0;
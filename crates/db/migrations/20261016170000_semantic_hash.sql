@@ -0,0 +1,4 @@
+-- Null for symbols that don't refer to any well-known symbols, and for those not reindexed yet
+ALTER TABLE symbols ADD COLUMN semantic_hash BIGINT;
+
+CREATE INDEX IF NOT EXISTS semantic_hash_idx ON symbols (semantic_hash);
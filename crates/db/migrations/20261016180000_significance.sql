@@ -0,0 +1,14 @@
+-- How often each opcode occurs in each source, which symbols' information content is measured
+-- against across all sources of a platform
+CREATE TABLE IF NOT EXISTS source_opcodes
+(
+    source_id BIGINT NOT NULL,
+    opcode    INT    NOT NULL,
+    count     BIGINT NOT NULL,
+    PRIMARY KEY (source_id, opcode),
+    FOREIGN KEY (source_id) REFERENCES sources (id) ON DELETE CASCADE
+);
+
+-- Information content of a symbol's opcodes in bits, which is what an identical match is worth.
+-- Null for symbols not reindexed yet
+ALTER TABLE symbols ADD COLUMN opcode_bits REAL;
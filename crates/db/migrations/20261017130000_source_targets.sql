@@ -0,0 +1,4 @@
+-- Retail binaries imported without a build are read with their map (or symbol list) rather than
+-- as objects, so both are kept for reindexing. The map is null for executables imported without one
+ALTER TABLE sources ADD COLUMN is_target BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE sources ADD COLUMN map_object_id BIGINT REFERENCES objects (id) ON DELETE SET NULL;
@@ -0,0 +1,3 @@
+-- Instructions aren't all the same width on every platform (ARM and Thumb on the DS), so the count
+-- is kept rather than worked out from the length. Null for symbols not reindexed yet
+ALTER TABLE symbols ADD COLUMN num_insns INTEGER;
@@ -1,11 +1,15 @@
 pub mod decompme;
 pub mod objects;
+pub mod opcodes;
 pub mod projects;
 pub mod sources;
 pub mod symbols;
 
 use anyhow::Result;
-use coddog_core::Platform;
+use coddog_core::significance::Significance;
+use coddog_core::submatch::{GapScoring, chain_runs};
+use coddog_core::{InsnSeqMatch, Platform};
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use sqlx::{PgPool, Pool, Postgres, Transaction, migrate::MigrateDatabase};
 use std::fmt::{Display, Formatter};
@@ -43,6 +47,8 @@ pub struct DBSource {
     pub object_id: i64,
     pub version_id: Option<i64>,
     pub project_id: i64,
+    pub is_target: bool,
+    pub map_object_id: Option<i64>,
 }
 
 #[derive(Clone, Debug, Serialize)]
@@ -67,12 +73,17 @@ pub struct DBSymbol {
     pub id: i64,
     pub slug: String,
     pub len: i32,
+    pub num_insns: Option<i32>,
     pub name: String,
     pub is_decompiled: bool,
     pub symbol_idx: i32,
     pub opcode_hash: i64,
     pub equiv_hash: i64,
+    pub regalloc_hash: Option<i64>,
+    pub semantic_hash: Option<i64>,
     pub exact_hash: i64,
+    pub opcode_bits: Option<f32>,
+    pub hash_version: i32,
     pub source_id: i64,
     pub source_name: String,
     pub object_path: String,
@@ -85,6 +96,15 @@ pub struct DBSymbol {
     pub platform: i32,
 }
 
+/// A symbol's instruction count. Symbols stored before the count was kept are taken to be made up
+/// of the platform's usual width of instruction until they're reindexed.
+fn num_insns(len: i32, num_insns: Option<i32>, platform: i32) -> i32 {
+    num_insns.unwrap_or_else(|| {
+        let platform: Platform = platform.try_into().expect("Unexpected platform ID");
+        len / platform.arch().standard_insn_length() as i32
+    })
+}
+
 impl Display for DBSymbol {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
@@ -96,8 +116,13 @@ impl Display for DBSymbol {
 
 impl DBSymbol {
     pub fn get_num_insns(&self) -> i32 {
-        let platform: Platform = self.platform.try_into().expect("Unexpected platform ID");
-        self.len / platform.arch().standard_insn_length() as i32
+        num_insns(self.len, self.num_insns, self.platform)
+    }
+
+    /// How likely the symbol is to match an identical one by chance among `search_space` symbols
+    pub fn significance(&self, search_space: i64) -> Option<Significance> {
+        self.opcode_bits
+            .map(|bits| Significance::new(bits as f64, search_space as usize))
     }
 }
 
@@ -115,6 +140,8 @@ pub struct SymbolMetadata {
     pub project_name: String,
     pub project_repo: Option<String>,
     pub platform: i32,
+    /// How likely the symbol is to match an identical one by chance, where known
+    pub significance: Option<Significance>,
 }
 
 impl SymbolMetadata {
@@ -132,6 +159,7 @@ impl SymbolMetadata {
             project_name: symbol.project_name.clone(),
             project_repo: symbol.project_repo.clone(),
             platform: symbol.platform,
+            significance: None,
         }
     }
 }
@@ -141,11 +169,16 @@ pub struct DBWindow {
     pub query_start: i32,
     pub match_start: i32,
     pub len: i64,
+    /// Length in the matching symbol, which differs from `len` for gapped matches
+    pub match_len: i64,
+    /// Share of the aligned instructions that match, which is 1 for exact matches
+    pub identity: f32,
     pub symbol_id: i64,
     pub symbol_slug: String,
     pub symbol_name: String,
     pub symbol_is_decompiled: bool,
     pub symbol_len: i32,
+    pub symbol_num_insns: Option<i32>,
     pub object_symbol_idx: i32,
     pub version_id: Option<i64>,
     pub version_name: Option<String>,
@@ -170,18 +203,18 @@ pub struct SubmatchResult {
     pub query_start: i64,
     pub match_start: i64,
     pub len: i64,
+    pub match_len: i64,
+    pub identity: f32,
 }
 
 impl SubmatchResult {
     pub fn from_db_window(window: &DBWindow) -> Self {
-        let platform: Platform = window.platform.try_into().expect("Unexpected platform ID");
-        let num_insns = window.symbol_len / platform.arch().standard_insn_length() as i32;
         Self {
             symbol: SymbolMetadata {
                 slug: window.symbol_slug.clone(),
                 name: window.symbol_name.clone(),
                 is_decompiled: window.symbol_is_decompiled,
-                len: num_insns,
+                len: num_insns(window.symbol_len, window.symbol_num_insns, window.platform),
                 source_id: window.source_id,
                 source_name: window.source_name.clone(),
                 version_id: window.version_id,
@@ -190,10 +223,13 @@ impl SubmatchResult {
                 project_name: window.project_name.clone(),
                 project_repo: window.project_repo.clone(),
                 platform: window.platform,
+                significance: None,
             },
             query_start: window.query_start as i64,
             match_start: window.match_start as i64,
             len: window.len,
+            match_len: window.match_len,
+            identity: window.identity,
         }
     }
 }
@@ -257,6 +293,15 @@ pub async fn get_versions_for_project(
     Ok(rows)
 }
 
+/// Delete a version, along with its sources and their symbols
+pub async fn delete_version(tx: &mut Transaction<'_, Postgres>, id: i64) -> Result<()> {
+    sqlx::query!("DELETE FROM versions WHERE id = $1", id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
 pub async fn count_versions(conn: Pool<Postgres>) -> Result<i64> {
     let rec = sqlx::query!("SELECT COUNT(*) as count FROM versions")
         .fetch_one(&conn)
@@ -296,6 +341,17 @@ pub async fn create_symbol_window_hashes(
     Ok(())
 }
 
+pub async fn delete_symbol_window_hashes(
+    tx: &mut Transaction<'_, Postgres>,
+    symbol_id: i64,
+) -> Result<()> {
+    sqlx::query!("DELETE FROM windows WHERE symbol_id = $1", symbol_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
+}
+
 #[derive(Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum SubmatchResultOrder {
@@ -320,15 +376,101 @@ pub struct QueryWindowsRequest {
     pub page: i64,
     pub sort_by: SubmatchResultOrder,
     pub sort_direction: SortDirection,
+    /// Chain runs into gapped matches spanning up to this many differing instructions
+    pub max_edits: Option<usize>,
 }
 
 pub async fn query_windows_by_symbol_id(
     conn: Pool<Postgres>,
     request: QueryWindowsRequest,
 ) -> Result<DBWindowResults> {
-    let min_seq_len = request.window_size - request.db_window_size;
-    let offset = request.page * request.limit;
+    match request.max_edits {
+        Some(max_edits) => query_gapped_windows(conn, &request, max_edits).await,
+        None => {
+            let min_seq_len = request.window_size - request.db_window_size;
+            let offset = request.page * request.limit;
+            query_window_runs(conn, &request, min_seq_len, request.limit, offset).await
+        }
+    }
+}
+
+/// Chain every run of at least one window into gapped matches, then page through those which
+/// are long enough. Runs are chained as they are in
+/// [`coddog_core::submatch::chain_runs`], so the instructions between them are counted as
+/// differing.
+async fn query_gapped_windows(
+    conn: Pool<Postgres>,
+    request: &QueryWindowsRequest,
+    max_edits: usize,
+) -> Result<DBWindowResults> {
+    let runs = query_window_runs(conn, request, 1, i64::MAX, 0).await?;
+
+    let scoring = GapScoring {
+        max_edits,
+        ..Default::default()
+    };
+
+    let mut windows: Vec<DBWindow> = Vec::new();
+    for symbol_runs in runs
+        .windows
+        .into_iter()
+        .into_group_map_by(|w| w.symbol_id)
+        .into_values()
+    {
+        let seeds: Vec<InsnSeqMatch> = symbol_runs
+            .iter()
+            .map(|w| InsnSeqMatch {
+                offset1: w.query_start as usize,
+                offset2: w.match_start as usize,
+                length: w.len as usize,
+            })
+            .collect();
+
+        for chain in chain_runs(&seeds, &scoring)
+            .iter()
+            .filter(|c| c.length1 as i64 >= request.window_size)
+        {
+            windows.push(DBWindow {
+                query_start: chain.offset1 as i32,
+                match_start: chain.offset2 as i32,
+                len: chain.length1 as i64,
+                match_len: chain.length2 as i64,
+                identity: chain.identity(),
+                ..symbol_runs[0].clone()
+            });
+        }
+    }
 
+    windows.sort_by(|a, b| {
+        b.len
+            .cmp(&a.len)
+            .then(a.project_id.cmp(&b.project_id))
+            .then(a.source_id.cmp(&b.source_id))
+            .then(a.symbol_id.cmp(&b.symbol_id))
+            .then(a.query_start.cmp(&b.query_start))
+            .then(a.match_start.cmp(&b.match_start))
+    });
+
+    let total_count = windows.len() as i64;
+    let windows = windows
+        .into_iter()
+        .skip((request.page * request.limit) as usize)
+        .take(request.limit as usize)
+        .collect();
+
+    Ok(DBWindowResults {
+        windows,
+        total_count,
+    })
+}
+
+async fn query_window_runs(
+    conn: Pool<Postgres>,
+    request: &QueryWindowsRequest,
+    min_seq_len: i64,
+    limit: i64,
+    offset: i64,
+) -> Result<DBWindowResults> {
     let _sort_by = match request.sort_by {
         SubmatchResultOrder::Length => "length",
         SubmatchResultOrder::QueryStart => "start_query_pos",
@@ -350,7 +492,10 @@ potential_matches AS (
         a.hash,
         (a.pos - b.pos) AS pos_diff
     FROM windows a
+    JOIN symbols query_symbol ON query_symbol.id = a.symbol_id
     JOIN windows b ON a.hash = b.hash
+    JOIN symbols match_symbol ON match_symbol.id = b.symbol_id
+        AND match_symbol.hash_version = query_symbol.hash_version
     WHERE a.pos >= $5 AND a.pos <= $6 AND a.symbol_id = $1 AND a.symbol_id != b.symbol_id
 ),
 sequence_groups AS (
@@ -384,6 +529,7 @@ joined_sequences AS (
         symbols.is_decompiled,
         symbols.slug AS symbol_slug,
         symbols.len AS symbol_len,
+        symbols.num_insns AS symbol_num_insns,
         symbols.symbol_idx AS object_symbol_idx,
         versions.id AS \"version_id?\",
         versions.name AS \"version_name?\",
@@ -406,7 +552,7 @@ SELECT *
 FROM joined_sequences
 ORDER BY length DESC, project_id, source_id, symbol_id, start_query_pos, start_match_pos
 LIMIT $3 OFFSET $4
-",request.symbol_id, min_seq_len, request.limit, offset, request.start, request.end
+",request.symbol_id, min_seq_len, limit, offset, request.start, request.end
     )
     .fetch_all(&conn)
     .await?;
@@ -417,11 +563,14 @@ LIMIT $3 OFFSET $4
             query_start: row.start_query_pos.unwrap(),
             match_start: row.start_match_pos.unwrap(),
             len: row.length.unwrap() + request.db_window_size - 1,
+            match_len: row.length.unwrap() + request.db_window_size - 1,
+            identity: 1.0,
             symbol_id: row.symbol_id,
             symbol_slug: row.symbol_slug.clone(),
             symbol_name: row.symbol_name.clone(),
             symbol_is_decompiled: row.is_decompiled,
             symbol_len: row.symbol_len,
+            symbol_num_insns: row.symbol_num_insns,
             object_symbol_idx: row.object_symbol_idx,
             source_id: row.source_id,
             source_name: row.source_name.clone(),
@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use clap::{Parser, Subcommand, ValueEnum};
-use coddog_core::ingest::read_elf;
+use coddog_core::executable::is_executable;
+use coddog_core::hash::HASH_VERSION;
+use coddog_core::ingest::{archive_members, read_object, read_target};
 use coddog_core::{Platform, Symbol};
 use coddog_db::decompme::DecompMeScratch;
 use coddog_db::projects::CreateProjectRequest;
@@ -12,7 +14,7 @@ use glob::glob;
 use inquire::{Confirm, Select};
 use itertools::Itertools;
 use pbr::ProgressBar;
-use sqlx::{PgPool, Pool, Postgres};
+use sqlx::{PgPool, Pool, Postgres, Transaction};
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::path::{Path, PathBuf};
@@ -24,6 +26,11 @@ pub enum MatchType {
     Opcode,
     /// Opcodes and some operands are compared
     Equivalent,
+    /// Like equivalent, but registers are compared by the order they're first used in (MIPS and
+    /// PPC only)
+    Regalloc,
+    /// Like equivalent, but the names of well-known symbols referred to are compared too
+    Semantic,
     /// Exact bytes are compared
     Exact,
 }
@@ -49,6 +56,17 @@ async fn db_search_symbol_by_name(conn: Pool<Postgres>, name: &str) -> anyhow::R
     }
 }
 
+/// Symbols are only ever compared with symbols hashed by the same version, so a symbol from an
+/// older version won't match anything added since
+fn warn_if_outdated_hashes(symbol: &DBSymbol) {
+    if symbol.hash_version != HASH_VERSION {
+        eprintln!(
+            "Warning: {} has hash version {} (current: {}) and only matches symbols of that version",
+            symbol.name, symbol.hash_version, HASH_VERSION
+        );
+    }
+}
+
 async fn db_search_project_by_name(conn: Pool<Postgres>, name: &str) -> anyhow::Result<i64> {
     let projects = coddog_db::projects::query_by_name(conn, name).await?;
 
@@ -110,6 +128,7 @@ impl SubmatchResults {
                                 query_start: h.query_start,
                                 match_start: h.match_start,
                                 length: h.len,
+                                identity: h.identity,
                             })
                             .collect(),
                     };
@@ -142,11 +161,15 @@ impl SubmatchResults {
                     result.push_str(&format!("\t\t{}:\n", symbol_map.get(&symbol.id).unwrap()));
                     for slice in &symbol.slices {
                         result.push_str(&format!(
-                            "\t\t\t[{}/{}] ({} insns)\n",
+                            "\t\t\t[{}/{}] ({} insns",
                             slice.query_start,
                             slice.match_start,
                             slice.length as usize + window_size - 1
                         ));
+                        if slice.identity < 1.0 {
+                            result.push_str(&format!(", {:.2}% identical", slice.identity * 100.0));
+                        }
+                        result.push_str(")\n");
                     }
                 }
             }
@@ -174,6 +197,7 @@ struct SubmatchSliceResults {
     query_start: i32,
     match_start: i32,
     length: i64,
+    identity: f32,
 }
 
 enum AddProjectOption {
@@ -196,6 +220,71 @@ impl Display for AddProjectOption {
     }
 }
 
+enum AddLibraryOption {
+    AddToExisting,
+    CreateNew,
+    Cancel,
+}
+
+impl Display for AddLibraryOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddLibraryOption::AddToExisting => write!(f, "Add to Existing Project"),
+            AddLibraryOption::CreateNew => write!(f, "Create New Project"),
+            AddLibraryOption::Cancel => write!(f, "Cancel"),
+        }
+    }
+}
+
+/// Symbols added or reindexed by a command, whose information content is measured once every
+/// source's opcode counts are written, so it doesn't depend on the order sources were added in
+#[derive(Default)]
+struct PendingOpcodeBits {
+    symbols: HashMap<i32, Vec<(i64, Vec<u16>)>>,
+}
+
+impl PendingOpcodeBits {
+    fn add(&mut self, platform: Platform, id: i64, symbol: &Symbol) {
+        self.symbols
+            .entry(platform as i32)
+            .or_default()
+            .push((id, symbol.opcodes.clone()));
+    }
+
+    async fn fill(self, tx: &mut Transaction<'_, Postgres>) -> Result<()> {
+        for (platform, symbols) in self.symbols {
+            let background = coddog_db::opcodes::query_background(tx, platform).await?;
+            coddog_db::symbols::set_opcode_bits(tx, &symbols, &background).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Store the symbols read from a source, along with their window hashes
+async fn add_source_symbols(
+    tx: &mut Transaction<'_, Postgres>,
+    source_id: i64,
+    platform: Platform,
+    symbols: &[Symbol],
+    window_size: usize,
+    pending_bits: &mut PendingOpcodeBits,
+) -> Result<()> {
+    if symbols.is_empty() {
+        return Ok(());
+    }
+
+    coddog_db::opcodes::set_source_counts(tx, source_id, symbols).await?;
+
+    let symbol_ids = coddog_db::symbols::create_many(tx, source_id, symbols).await;
+
+    for (symbol, id) in symbols.iter().zip(symbol_ids) {
+        let opcode_hashes = symbol.get_opcode_hashes(window_size);
+        coddog_db::create_symbol_window_hashes(tx, &opcode_hashes, id).await?;
+        pending_bits.add(platform, id, symbol);
+    }
+    Ok(())
+}
+
 fn get_full_path(base_dir: &Path, config_path: Option<PathBuf>) -> Option<PathBuf> {
     config_path.map(|path| {
         if path.is_relative() {
@@ -213,6 +302,20 @@ pub enum DbCommands {
         /// Path to the project's repo
         repo: PathBuf,
     },
+    /// Add a static library (e.g. an SDK's) to the database. Each release is a version of the
+    /// library's project, with a source per member object. Adding a release again replaces it.
+    AddLibrary {
+        /// Path to the library (.a or .lib)
+        path: PathBuf,
+        /// Platform the library is for
+        platform: String,
+        /// Name of the library's project, by default its file name
+        #[arg(long)]
+        name: Option<String>,
+        /// Name of this release of the library, e.g. 2.0L
+        #[arg(long, default_value = "default")]
+        release: String,
+    },
     /// Delete a project from the database, removing its sources, symbols, and hashes
     DeleteProject {
         /// Name of the project to delete
@@ -233,6 +336,25 @@ pub enum DbCommands {
         query: String,
         /// Window size (smaller values will find more matches but take longer)
         window_size: usize,
+        /// Chain matches across instructions that differ, rather than only finding exact runs
+        #[arg(long)]
+        gapped: bool,
+        /// Most mismatching or inserted instructions a gapped match can span
+        #[arg(long, default_value = "4")]
+        max_edits: usize,
+    },
+    /// Recompute the hashes and windows of symbols from their stored objects, or for retail
+    /// binaries from the binary and its stored map
+    Reindex {
+        /// Only reindex sources of this project
+        #[arg(long)]
+        project: Option<String>,
+        /// Only reindex sources of this platform
+        #[arg(long)]
+        platform: Option<String>,
+        /// Only reindex the source with this slug
+        #[arg(long)]
+        source: Option<String>,
     },
     /// Import data from a locally-loaded decomp.me database
     ImportDecompme {},
@@ -298,6 +420,7 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 handle_add_project_choice(&config, &mut tx, &existing_projects, res).await?
             };
 
+            let mut pending_bits = PendingOpcodeBits::default();
             for version in &config.versions {
                 let version_id = coddog_db::create_version(
                     &mut tx,
@@ -307,18 +430,20 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 )
                 .await?;
 
-                let obj_files: Vec<PathBuf> = glob(&format!(
-                    "{}/**/*.o",
-                    get_full_path(
-                        yaml.parent().unwrap(),
-                        Some(version.paths.build_dir.clone())
-                    )
-                    .unwrap()
-                    .to_str()
-                    .unwrap()
-                ))?
-                .filter_map(Result::ok)
-                .collect();
+                let build_dir = get_full_path(
+                    yaml.parent().unwrap(),
+                    Some(version.paths.build_dir.clone()),
+                )
+                .unwrap();
+
+                // ELF objects are .o, COFF objects from MSVC are .obj
+                let mut obj_files: Vec<PathBuf> = Vec::new();
+                for ext in ["o", "obj"] {
+                    obj_files.extend(
+                        glob(&format!("{}/**/*.{}", build_dir.to_str().unwrap(), ext))?
+                            .filter_map(Result::ok),
+                    );
+                }
 
                 let mut pb = ProgressBar::new(obj_files.len() as u64);
                 pb.format("[=>-]");
@@ -339,25 +464,204 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                     )
                     .await?;
 
-                    let symbols = read_elf(platform, &None, &obj_bytes)?;
-
-                    if !symbols.is_empty() {
-                        let symbol_ids =
-                            coddog_db::symbols::create_many(&mut tx, source_id, &symbols).await;
+                    let symbols = read_object(platform, &None, &obj_bytes)?;
+                    add_source_symbols(
+                        &mut tx,
+                        source_id,
+                        platform,
+                        &symbols,
+                        window_size,
+                        &mut pending_bits,
+                    )
+                    .await?;
+                }
 
-                        for (symbol, id) in symbols.iter().zip(symbol_ids) {
-                            let opcode_hashes = symbol.get_opcode_hashes(window_size);
+                // Without a build, a retail binary can still be indexed using its map
+                if obj_files.is_empty() {
+                    let base_dir = yaml.parent().unwrap();
+                    let target =
+                        get_full_path(base_dir, Some(version.paths.target.clone())).unwrap();
+                    let map = get_full_path(base_dir, Some(version.paths.map.clone())).unwrap();
+
+                    // PlayStation executables can be imported without a map
+                    let target_bytes = if target.exists() {
+                        Some(std::fs::read(&target)?)
+                    } else {
+                        None
+                    };
+                    let map_str = if map.exists() {
+                        Some(std::fs::read_to_string(&map)?)
+                    } else {
+                        None
+                    };
 
-                            coddog_db::create_symbol_window_hashes(&mut tx, &opcode_hashes, id)
-                                .await?;
-                        }
+                    if let Some(target_bytes) = target_bytes
+                        && (map_str.is_some() || is_executable(&target_bytes))
+                    {
+                        pb.message(format!("Importing binary ({}) ", version.fullname).as_str());
+
+                        let object_id = coddog_db::objects::create(&mut tx, &target_bytes).await?;
+                        let source_id = coddog_db::sources::create(
+                            &mut tx,
+                            target.file_name().unwrap().to_str().unwrap(),
+                            &config.repo,
+                            0,
+                            object_id,
+                            Option::from(version_id),
+                            project_id,
+                        )
+                        .await?;
+
+                        // The map is kept along with the binary so the source can be reindexed
+                        let map_object_id = match &map_str {
+                            Some(map_str) => {
+                                Some(coddog_db::objects::create(&mut tx, map_str.as_bytes()).await?)
+                            }
+                            None => None,
+                        };
+                        coddog_db::sources::set_target(&mut tx, source_id, map_object_id).await?;
+
+                        let symbols =
+                            read_target(platform, None, target_bytes, map_str.as_deref())?;
+                        add_source_symbols(
+                            &mut tx,
+                            source_id,
+                            platform,
+                            &symbols,
+                            window_size,
+                            &mut pending_bits,
+                        )
+                        .await?;
                     }
                 }
                 println!();
             }
+            pending_bits.fill(&mut tx).await?;
             tx.commit().await?;
             println!("Imported project {} ", config.name);
         }
+        DbCommands::AddLibrary {
+            path,
+            platform,
+            name,
+            release,
+        } => {
+            let platform = Platform::from_name(platform)
+                .or_else(|| Platform::from_decompme_name(platform))
+                .ok_or_else(|| anyhow!("Unknown platform '{}'", platform))?;
+            let window_size = std::env::var("DB_WINDOW_SIZE")
+                .expect("DB_WINDOW_SIZE must be set")
+                .parse::<usize>()?;
+
+            let lib_name = path.file_name().unwrap().to_string_lossy().to_string();
+            let name = name.clone().unwrap_or_else(|| lib_name.clone());
+            let archive_bytes = std::fs::read(path)?;
+            let members = archive_members(&archive_bytes)?;
+
+            let pool = coddog_db::init().await?;
+            let mut tx = pool.begin().await?;
+
+            // Releases of a library are kept together, as versions of one project. A game's
+            // project may have the same name, so one is only added to once it's been picked
+            let existing_projects = coddog_db::projects::query_by_name(pool.clone(), &name).await?;
+            let choice = if existing_projects.is_empty() {
+                AddLibraryOption::CreateNew
+            } else {
+                Select::new(
+                    "A project with this name already exists. What would you like to do?",
+                    vec![
+                        AddLibraryOption::AddToExisting,
+                        AddLibraryOption::CreateNew,
+                        AddLibraryOption::Cancel,
+                    ],
+                )
+                .prompt()?
+            };
+            let project_id = match choice {
+                AddLibraryOption::AddToExisting if existing_projects.len() == 1 => {
+                    existing_projects[0].id
+                }
+                AddLibraryOption::AddToExisting => {
+                    Select::new(
+                        "Which project do you want to add the library to?",
+                        existing_projects.to_vec(),
+                    )
+                    .prompt()?
+                    .id
+                }
+                AddLibraryOption::CreateNew => {
+                    coddog_db::projects::create(
+                        &mut tx,
+                        &CreateProjectRequest {
+                            name: name.clone(),
+                            repo: None,
+                        },
+                    )
+                    .await?
+                }
+                AddLibraryOption::Cancel => return Err(anyhow::Error::msg("Cancelled")),
+            };
+
+            // Importing a release again replaces it, rather than adding its members twice
+            let versions = coddog_db::get_versions_for_project(pool.clone(), project_id).await?;
+            if let Some(version) = versions.iter().find(|v| v.name == *release) {
+                let confirm = Confirm::new(&format!(
+                    "Release '{}' of '{}' (ID: {}) already exists. Do you want to replace it? This will delete its sources.",
+                    release, name, version.id
+                ))
+                .with_default(false)
+                .prompt()?;
+                if !confirm {
+                    return Err(anyhow::Error::msg("Cancelled"));
+                }
+                coddog_db::delete_version(&mut tx, version.id).await?;
+            }
+
+            let version_id =
+                coddog_db::create_version(&mut tx, release, platform as i32, project_id).await?;
+
+            let mut pb = ProgressBar::new(members.len() as u64);
+            pb.format("[=>-]");
+            pb.message(format!("Importing {lib_name} ").as_str());
+
+            let mut pending_bits = PendingOpcodeBits::default();
+            for (member, data) in members {
+                pb.inc();
+                let symbols = match read_object(platform, &None, data) {
+                    Ok(symbols) => symbols,
+                    Err(e) => {
+                        eprintln!("Failed to read {} in {}: {}", member, lib_name, e);
+                        continue;
+                    }
+                };
+
+                let object_id = coddog_db::objects::create(&mut tx, data).await?;
+                let source_id = coddog_db::sources::create(
+                    &mut tx,
+                    &member,
+                    &None,
+                    0,
+                    object_id,
+                    Option::from(version_id),
+                    project_id,
+                )
+                .await?;
+                add_source_symbols(
+                    &mut tx,
+                    source_id,
+                    platform,
+                    &symbols,
+                    window_size,
+                    &mut pending_bits,
+                )
+                .await?;
+            }
+            println!();
+
+            pending_bits.fill(&mut tx).await?;
+            tx.commit().await?;
+            println!("Imported library {name} ({release})");
+        }
         DbCommands::DeleteProject { name } => {
             let pool = coddog_db::init().await?;
             let mut tx = pool.begin().await?;
@@ -411,6 +715,7 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
             let pool = coddog_db::init().await?;
 
             let symbol = db_search_symbol_by_name(pool.clone(), query).await?;
+            warn_if_outdated_hashes(&symbol);
 
             let matches = match match_type {
                 MatchType::Opcode => {
@@ -419,11 +724,26 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 MatchType::Equivalent => {
                     coddog_db::symbols::query_by_equiv_hash(pool.clone(), &symbol).await?
                 }
+                MatchType::Regalloc => {
+                    coddog_db::symbols::query_by_regalloc_hash(pool.clone(), &symbol).await?
+                }
+                MatchType::Semantic => {
+                    coddog_db::symbols::query_by_semantic_hash(pool.clone(), &symbol).await?
+                }
                 MatchType::Exact => {
                     coddog_db::symbols::query_by_exact_hash(pool.clone(), &symbol).await?
                 }
             };
 
+            let search_space =
+                coddog_db::opcodes::count_symbols(pool.clone(), symbol.platform).await?;
+            if let Some(significance) = symbol.significance(search_space) {
+                println!(
+                    "E-value {:.2e} ({:.1} bits) among {} symbols",
+                    significance.evalue, significance.bits, search_space
+                );
+            }
+
             if matches.is_empty() {
                 println!("No matches found");
             } else {
@@ -432,7 +752,12 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 }
             }
         }
-        DbCommands::Submatch { query, window_size } => {
+        DbCommands::Submatch {
+            query,
+            window_size,
+            gapped,
+            max_edits,
+        } => {
             let db_window_size = std::env::var("DB_WINDOW_SIZE")
                 .expect("DB_WINDOW_SIZE must be set")
                 .parse::<usize>()?;
@@ -444,6 +769,7 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
             let pool = coddog_db::init().await?;
 
             let symbol = db_search_symbol_by_name(pool.clone(), query).await?;
+            warn_if_outdated_hashes(&symbol);
 
             let before_time = SystemTime::now();
             let matching_hashes = coddog_db::query_windows_by_symbol_id(
@@ -458,6 +784,7 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                     page: 0,
                     sort_by: SubmatchResultOrder::Length,
                     sort_direction: SortDirection::Desc,
+                    max_edits: gapped.then_some(*max_edits),
                 },
             )
             .await?;
@@ -492,6 +819,135 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 results.to_string(*window_size, &project_map, &source_map, &symbol_map)
             );
         }
+        DbCommands::Reindex {
+            project,
+            platform,
+            source,
+        } => {
+            let window_size = std::env::var("DB_WINDOW_SIZE")
+                .expect("DB_WINDOW_SIZE must be set")
+                .parse::<usize>()?;
+
+            let platform = match platform {
+                Some(name) => Some(
+                    Platform::from_name(name)
+                        .or_else(|| Platform::from_decompme_name(name))
+                        .ok_or_else(|| anyhow!("Unknown platform '{}'", name))?,
+                ),
+                None => None,
+            };
+
+            let pool = coddog_db::init().await?;
+
+            let project_id = match project {
+                Some(name) => Some(db_search_project_by_name(pool.clone(), name).await?),
+                None => None,
+            };
+
+            let sources = coddog_db::sources::query_for_reindex(
+                pool.clone(),
+                project_id,
+                platform.map(|p| p as i32),
+                source.as_deref(),
+            )
+            .await?;
+
+            let mut pb = ProgressBar::new(sources.len() as u64);
+            pb.format("[=>-]");
+            pb.message("Reindexing sources ");
+
+            let mut reindexed = 0;
+            let mut missing_objects = 0;
+            let mut unreadable_objects = 0;
+            let mut missing_symbols = 0;
+            let mut pending_bits = PendingOpcodeBits::default();
+
+            for source in sources {
+                pb.inc();
+
+                let platform: Platform = source
+                    .platform
+                    .try_into()
+                    .map_err(|_| anyhow!("Unexpected platform ID {}", source.platform))?;
+
+                let Ok(obj_bytes) = std::fs::read(&source.object_path) else {
+                    missing_objects += 1;
+                    continue;
+                };
+
+                let symbols = if source.is_target {
+                    let map_str = match &source.map_path {
+                        Some(map_path) => match std::fs::read_to_string(map_path) {
+                            Ok(map_str) => Some(map_str),
+                            Err(_) => {
+                                missing_objects += 1;
+                                continue;
+                            }
+                        },
+                        None => None,
+                    };
+                    read_target(platform, None, obj_bytes, map_str.as_deref())
+                } else {
+                    read_object(platform, &None, &obj_bytes)
+                };
+
+                let symbols = match symbols {
+                    Ok(symbols) => symbols,
+                    Err(e) => {
+                        eprintln!("Error reading object for source {}: {}", source.name, e);
+                        unreadable_objects += 1;
+                        continue;
+                    }
+                };
+
+                // Each source is committed on its own, so an interrupted reindex keeps the
+                // progress it made
+                let mut tx = pool.begin().await?;
+
+                coddog_db::opcodes::set_source_counts(&mut tx, source.id, &symbols).await?;
+
+                for db_sym in coddog_db::symbols::query_by_source(&mut tx, source.id).await? {
+                    // Pair symbols up by index, falling back to the name in case the symbol
+                    // order changed
+                    let symbol = symbols
+                        .iter()
+                        .find(|s| s.symbol_idx as i32 == db_sym.symbol_idx && s.name == db_sym.name)
+                        .or_else(|| {
+                            symbols
+                                .iter()
+                                .filter(|s| s.name == db_sym.name)
+                                .exactly_one()
+                                .ok()
+                        });
+
+                    let Some(symbol) = symbol else {
+                        missing_symbols += 1;
+                        continue;
+                    };
+
+                    coddog_db::symbols::update_hashes(&mut tx, db_sym.id, symbol).await?;
+                    coddog_db::delete_symbol_window_hashes(&mut tx, db_sym.id).await?;
+                    let opcode_hashes = symbol.get_opcode_hashes(window_size);
+                    coddog_db::create_symbol_window_hashes(&mut tx, &opcode_hashes, db_sym.id)
+                        .await?;
+                    pending_bits.add(platform, db_sym.id, symbol);
+                    reindexed += 1;
+                }
+
+                tx.commit().await?;
+            }
+
+            // Information content is measured against the counts of every reindexed source
+            let mut tx = pool.begin().await?;
+            pending_bits.fill(&mut tx).await?;
+            tx.commit().await?;
+            pb.finish_print("Reindexed sources successfully");
+
+            println!("Reindexed {} symbols", reindexed);
+            println!("Sources with missing objects: {}", missing_objects);
+            println!("Sources with unreadable objects: {}", unreadable_objects);
+            println!("Symbols not found in their object: {}", missing_symbols);
+        }
         DbCommands::ImportDecompme {} => {
             let decompme_db_url = std::env::var("DECOMPME_DATABASE_URL")
                 .expect("DECOMPME_DATABASE_URL must be set")
@@ -546,6 +1002,7 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
             let mut no_symbols = 0;
             let mut cant_find_symbol = 0;
             let mut no_bytes = 0;
+            let mut pending_bits = PendingOpcodeBits::default();
 
             for scratch in scratches {
                 pb.inc();
@@ -569,10 +1026,10 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 )
                 .await?;
 
-                let symbols = read_elf(platform, &None, &elf_object.elf_object);
+                let symbols = read_object(platform, &None, &elf_object.elf_object);
 
                 if let Err(e) = symbols {
-                    println!("Error reading ELF for scratch {}: {}", scratch.slug, e);
+                    println!("Error reading object for scratch {}: {}", scratch.slug, e);
                     continue;
                 }
                 let symbols = symbols.unwrap();
@@ -645,14 +1102,23 @@ async fn handle_db_command(cmd: &DbCommands) -> Result<()> {
                 )
                 .await?;
 
+                coddog_db::opcodes::set_source_counts(
+                    &mut tx,
+                    source_id,
+                    std::slice::from_ref(&matched_sym),
+                )
+                .await?;
+
                 let symbol_id =
                     coddog_db::symbols::create_one(&mut tx, source_id, &matched_sym).await;
 
                 let opcode_hashes = matched_sym.get_opcode_hashes(window_size);
                 coddog_db::create_symbol_window_hashes(&mut tx, &opcode_hashes, symbol_id).await?;
+                pending_bits.add(platform, symbol_id, &matched_sym);
                 imported += 1;
             }
 
+            pending_bits.fill(&mut tx).await?;
             tx.commit().await?;
             pb.finish_print("Imported scratches successfully");
 
@@ -0,0 +1,81 @@
+use anyhow::Result;
+use coddog_core::Symbol;
+use coddog_core::significance::OpcodeBackground;
+use sqlx::{Pool, Postgres, Transaction};
+use std::collections::HashMap;
+
+/// Record how often each opcode occurs in a source's symbols, replacing any previous counts
+pub async fn set_source_counts(
+    tx: &mut Transaction<'_, Postgres>,
+    source_id: i64,
+    symbols: &[Symbol],
+) -> Result<()> {
+    let mut counts: HashMap<u16, i64> = HashMap::new();
+    for opcode in symbols.iter().flat_map(|s| &s.opcodes) {
+        *counts.entry(*opcode).or_default() += 1;
+    }
+    let (opcodes, counts): (Vec<i32>, Vec<i64>) = counts
+        .into_iter()
+        .map(|(opcode, count)| (opcode as i32, count))
+        .unzip();
+
+    sqlx::query!("DELETE FROM source_opcodes WHERE source_id = $1", source_id)
+        .execute(&mut **tx)
+        .await?;
+
+    sqlx::query!(
+        "
+            INSERT INTO source_opcodes (source_id, opcode, count)
+            SELECT $1::bigint, * FROM UNNEST($2::int[], $3::bigint[])
+        ",
+        source_id,
+        &opcodes,
+        &counts,
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// How often each opcode occurs across every source of a platform
+pub async fn query_background(
+    tx: &mut Transaction<'_, Postgres>,
+    platform: i32,
+) -> Result<OpcodeBackground> {
+    let rows = sqlx::query!(
+        "
+    SELECT source_opcodes.opcode, SUM(source_opcodes.count)::bigint AS count
+    FROM source_opcodes
+    INNER JOIN sources ON sources.id = source_opcodes.source_id
+    INNER JOIN versions ON versions.id = sources.version_id
+    WHERE versions.platform = $1
+    GROUP BY source_opcodes.opcode",
+        platform
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(OpcodeBackground::from_counts(
+        rows.into_iter()
+            .map(|r| (r.opcode as u16, r.count.unwrap_or(0) as u64))
+            .collect(),
+    ))
+}
+
+/// Number of symbols of a platform, which is the search space when matching against the database
+pub async fn count_symbols(conn: Pool<Postgres>, platform: i32) -> Result<i64> {
+    let rec = sqlx::query!(
+        "
+    SELECT COUNT(*) AS count
+    FROM symbols
+    INNER JOIN sources ON sources.id = symbols.source_id
+    INNER JOIN versions ON versions.id = sources.version_id
+    WHERE versions.platform = $1",
+        platform
+    )
+    .fetch_one(&conn)
+    .await?;
+
+    Ok(rec.count.unwrap_or(0))
+}
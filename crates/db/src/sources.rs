@@ -33,6 +33,23 @@ pub async fn create(
     }
 }
 
+/// Mark a source as a retail binary, to be read with the given map object rather than as an object
+pub async fn set_target(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    map_object_id: Option<i64>,
+) -> Result<()> {
+    sqlx::query!(
+        "UPDATE sources SET is_target = TRUE, map_object_id = $2 WHERE id = $1",
+        id,
+        map_object_id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn query_by_id(
     tx: &mut Transaction<'_, Postgres>,
     id: i64,
@@ -56,6 +73,49 @@ pub async fn query_by_slug(conn: Pool<Postgres>, query: &str) -> anyhow::Result<
     Ok(sym)
 }
 
+/// A source whose object can be re-read, along with the platform of its version. Retail binaries
+/// are flagged as targets, with the path of their map if they were imported with one
+#[derive(Clone, Debug)]
+pub struct ReindexSource {
+    pub id: i64,
+    pub name: String,
+    pub object_path: String,
+    pub platform: i32,
+    pub is_target: bool,
+    pub map_path: Option<String>,
+}
+
+/// Query the sources to reindex, optionally narrowed down by project, platform and source slug.
+/// Sources without a version are left out, as their platform isn't known.
+pub async fn query_for_reindex(
+    conn: Pool<Postgres>,
+    project_id: Option<i64>,
+    platform: Option<i32>,
+    slug: Option<&str>,
+) -> Result<Vec<ReindexSource>> {
+    let rows = sqlx::query_as!(
+        ReindexSource,
+        "
+    SELECT sources.id, sources.name, objects.local_path AS object_path, versions.platform,
+        sources.is_target, maps.local_path AS \"map_path?\"
+    FROM sources
+    INNER JOIN objects ON objects.id = sources.object_id
+    INNER JOIN versions ON versions.id = sources.version_id
+    LEFT JOIN objects maps ON maps.id = sources.map_object_id
+    WHERE ($1::bigint IS NULL OR sources.project_id = $1)
+      AND ($2::int IS NULL OR versions.platform = $2)
+      AND ($3::text IS NULL OR sources.slug = $3)
+    ORDER BY sources.id",
+        project_id,
+        platform,
+        slug
+    )
+    .fetch_all(&conn)
+    .await?;
+
+    Ok(rows)
+}
+
 pub async fn count(conn: Pool<Postgres>) -> Result<i64> {
     let rec = sqlx::query!("SELECT COUNT(*) as count FROM sources")
         .fetch_one(&conn)
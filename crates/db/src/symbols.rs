@@ -1,15 +1,20 @@
 use crate::{CHUNK_SIZE, DBSymbol};
 use coddog_core::Symbol;
+use coddog_core::hash::HASH_VERSION;
+use coddog_core::significance::OpcodeBackground;
 use serde::Deserialize;
 use sqlx::{Pool, Postgres, Transaction};
 
 type BulkSymbolData = (
     Vec<i64>,
+    Vec<i32>,
     Vec<String>,
     Vec<bool>,
     Vec<i64>,
     Vec<i64>,
     Vec<i64>,
+    Vec<Option<i64>>,
+    Vec<Option<i64>>,
     Vec<i64>,
 );
 
@@ -32,24 +37,31 @@ pub async fn create_many(
 
     for chunk in symbols.chunks(CHUNK_SIZE) {
         let source_ids = vec![source_id; chunk.len()];
+        let hash_versions = vec![HASH_VERSION; chunk.len()];
         let (
             lens,
+            num_insns,
             names,
             is_decompileds,
             symbol_idxes,
             opcode_hashes,
             equiv_hashes,
+            regalloc_hashes,
+            semantic_hashes,
             exact_hashes,
         ): BulkSymbolData = chunk
             .iter()
             .map(|s| {
                 (
                     s.bytes.len() as i64,
+                    s.opcodes.len() as i32,
                     s.name.clone(),
                     s.is_decompiled,
                     s.symbol_idx as i64,
                     s.opcode_hash as i64,
                     s.equiv_hash as i64,
+                    s.regalloc_hash.map(|h| h as i64),
+                    s.semantic_hash.map(|h| h as i64),
                     s.exact_hash as i64,
                 )
             })
@@ -57,18 +69,22 @@ pub async fn create_many(
 
         let rows = sqlx::query!(
             "
-                INSERT INTO symbols (len, name, is_decompiled, symbol_idx, opcode_hash, equiv_hash, exact_hash, source_id)
-                SELECT * FROM UNNEST($1::bigint[], $2::text[], $3::boolean[], $4::bigint[], $5::bigint[], $6::bigint[], $7::bigint[], $8::bigint[])
+                INSERT INTO symbols (len, num_insns, name, is_decompiled, symbol_idx, opcode_hash, equiv_hash, regalloc_hash, semantic_hash, exact_hash, source_id, hash_version)
+                SELECT * FROM UNNEST($1::bigint[], $2::int[], $3::text[], $4::boolean[], $5::bigint[], $6::bigint[], $7::bigint[], $8::bigint[], $9::bigint[], $10::bigint[], $11::bigint[], $12::int[])
                 RETURNING id
         ",
             &lens as &[i64],
+            &num_insns as &[i32],
             &names,
             &is_decompileds,
             &symbol_idxes,
             &opcode_hashes,
             &equiv_hashes,
+            &regalloc_hashes as &[Option<i64>],
+            &semantic_hashes as &[Option<i64>],
             &exact_hashes,
             &source_ids as &[i64],
+            &hash_versions as &[i32],
         )
             .fetch_all(&mut **tx)
             .await
@@ -89,18 +105,22 @@ pub async fn create_one(
 ) -> i64 {
     let row = sqlx::query!(
             "
-                INSERT INTO symbols (len, name, is_decompiled, symbol_idx, opcode_hash, equiv_hash, exact_hash, source_id)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                INSERT INTO symbols (len, num_insns, name, is_decompiled, symbol_idx, opcode_hash, equiv_hash, regalloc_hash, semantic_hash, exact_hash, source_id, hash_version)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
                 RETURNING id
         ",
         symbol.bytes.len() as i64,
+        symbol.opcodes.len() as i32,
         symbol.name.clone(),
         symbol.is_decompiled,
         symbol.symbol_idx as i64,
         symbol.opcode_hash as i64,
         symbol.equiv_hash as i64,
+        symbol.regalloc_hash.map(|h| h as i64),
+        symbol.semantic_hash.map(|h| h as i64),
         symbol.exact_hash as i64,
-        source_id
+        source_id,
+        HASH_VERSION
         )
         .fetch_one(&mut **tx)
         .await
@@ -109,12 +129,93 @@ pub async fn create_one(
     row.id
 }
 
+/// A symbol as stored for a source, enough to pair it up with the symbol read from the object
+#[derive(Clone, Debug)]
+pub struct SourceSymbol {
+    pub id: i64,
+    pub name: String,
+    pub symbol_idx: i32,
+}
+
+pub async fn query_by_source(
+    tx: &mut Transaction<'_, Postgres>,
+    source_id: i64,
+) -> anyhow::Result<Vec<SourceSymbol>> {
+    let rows = sqlx::query_as!(
+        SourceSymbol,
+        "SELECT id, name, symbol_idx FROM symbols WHERE source_id = $1 ORDER BY symbol_idx",
+        source_id
+    )
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Replace a symbol's hashes with freshly computed ones
+pub async fn update_hashes(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+    symbol: &Symbol,
+) -> anyhow::Result<()> {
+    sqlx::query!(
+        "
+            UPDATE symbols
+            SET len = $1, num_insns = $2, opcode_hash = $3, equiv_hash = $4, regalloc_hash = $5, semantic_hash = $6, exact_hash = $7, hash_version = $8
+            WHERE id = $9
+        ",
+        symbol.bytes.len() as i32,
+        symbol.opcodes.len() as i32,
+        symbol.opcode_hash as i64,
+        symbol.equiv_hash as i64,
+        symbol.regalloc_hash.map(|h| h as i64),
+        symbol.semantic_hash.map(|h| h as i64),
+        symbol.exact_hash as i64,
+        HASH_VERSION,
+        id
+    )
+    .execute(&mut **tx)
+    .await?;
+
+    Ok(())
+}
+
+/// Measure the information content of symbols against a platform's background, given each
+/// symbol's id and opcodes
+pub async fn set_opcode_bits(
+    tx: &mut Transaction<'_, Postgres>,
+    symbols: &[(i64, Vec<u16>)],
+    background: &OpcodeBackground,
+) -> anyhow::Result<()> {
+    for chunk in symbols.chunks(CHUNK_SIZE) {
+        let (ids, bits): (Vec<i64>, Vec<f32>) = chunk
+            .iter()
+            .map(|(id, opcodes)| (*id, background.sequence_bits(opcodes) as f32))
+            .unzip();
+
+        sqlx::query!(
+            "
+                UPDATE symbols
+                SET opcode_bits = bits.opcode_bits
+                FROM UNNEST($1::bigint[], $2::real[]) AS bits(id, opcode_bits)
+                WHERE symbols.id = bits.id
+            ",
+            &ids,
+            &bits as &[f32],
+        )
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
+
 pub async fn query_by_id(conn: Pool<Postgres>, query: i64) -> anyhow::Result<Option<DBSymbol>> {
     let sym = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash, symbols.source_id,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version, symbols.source_id,
            symbols.symbol_idx,
            sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
@@ -138,9 +239,9 @@ pub async fn query_by_slug(conn: Pool<Postgres>, query: &str) -> anyhow::Result<
     let sym = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
            symbols.symbol_idx,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash, symbols.source_id,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version, symbols.source_id,
             sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
            projects.name AS project_name, projects.id AS project_id,
@@ -166,9 +267,9 @@ pub async fn query_by_name(
     let sym = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
            symbols.symbol_idx,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash, symbols.source_id,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version, symbols.source_id,
             sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
            projects.name AS project_name, projects.id AS project_id,
@@ -195,9 +296,9 @@ pub async fn query_by_opcode_hash(
     let syms = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
            symbols.symbol_idx,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version,
            symbols.source_id,
             sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
@@ -208,9 +309,10 @@ pub async fn query_by_opcode_hash(
     INNER JOIN objects ON objects.id = sources.object_id
     INNER JOIN versions ON versions.id = sources.version_id
     INNER JOIN projects on sources.project_id = projects.id
-    WHERE symbols.opcode_hash = $1 AND NOT symbols.id = $2",
+    WHERE symbols.opcode_hash = $1 AND symbols.hash_version = $3 AND NOT symbols.id = $2",
         symbol.opcode_hash as i64,
-        symbol.id as i64
+        symbol.id as i64,
+        symbol.hash_version
     )
     .fetch_all(&conn)
     .await?;
@@ -225,9 +327,9 @@ pub async fn query_by_equiv_hash(
     let syms = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
            symbols.symbol_idx,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version,
            symbols.source_id,
             sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
@@ -238,9 +340,86 @@ pub async fn query_by_equiv_hash(
     INNER JOIN objects ON objects.id = sources.object_id
     INNER JOIN versions ON versions.id = sources.version_id
     INNER JOIN projects on sources.project_id = projects.id
-    WHERE symbols.equiv_hash = $1 AND NOT symbols.id = $2",
+    WHERE symbols.equiv_hash = $1 AND symbols.hash_version = $3 AND NOT symbols.id = $2",
         symbol.equiv_hash as i64,
-        symbol.id as i64
+        symbol.id as i64,
+        symbol.hash_version
+    )
+    .fetch_all(&conn)
+    .await?;
+
+    Ok(syms)
+}
+
+pub async fn query_by_regalloc_hash(
+    conn: Pool<Postgres>,
+    symbol: &DBSymbol,
+) -> anyhow::Result<Vec<DBSymbol>> {
+    // Registers are only renamed on some architectures, and symbols hashed by an older version
+    // may not have a regalloc hash worth comparing
+    let Some(regalloc_hash) = symbol.regalloc_hash else {
+        return Ok(vec![]);
+    };
+    if symbol.hash_version != HASH_VERSION {
+        return Ok(vec![]);
+    }
+
+    let syms = sqlx::query_as!(
+        DBSymbol,
+        "
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
+           symbols.symbol_idx,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version,
+           symbols.source_id,
+            sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
+           versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
+            projects.name AS project_name, projects.id as project_id,
+           projects.repo AS project_repo
+        FROM symbols
+    INNER JOIN sources ON sources.id = symbols.source_id
+    INNER JOIN objects ON objects.id = sources.object_id
+    INNER JOIN versions ON versions.id = sources.version_id
+    INNER JOIN projects on sources.project_id = projects.id
+    WHERE symbols.regalloc_hash = $1 AND symbols.hash_version = $3 AND NOT symbols.id = $2",
+        regalloc_hash,
+        symbol.id as i64,
+        HASH_VERSION
+    )
+    .fetch_all(&conn)
+    .await?;
+
+    Ok(syms)
+}
+
+pub async fn query_by_semantic_hash(
+    conn: Pool<Postgres>,
+    symbol: &DBSymbol,
+) -> anyhow::Result<Vec<DBSymbol>> {
+    // Symbols without a semantic hash don't refer to anything to compare by
+    let Some(semantic_hash) = symbol.semantic_hash else {
+        return Ok(vec![]);
+    };
+
+    let syms = sqlx::query_as!(
+        DBSymbol,
+        "
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
+           symbols.symbol_idx,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version,
+           symbols.source_id,
+            sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
+           versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
+            projects.name AS project_name, projects.id as project_id,
+           projects.repo AS project_repo
+        FROM symbols
+    INNER JOIN sources ON sources.id = symbols.source_id
+    INNER JOIN objects ON objects.id = sources.object_id
+    INNER JOIN versions ON versions.id = sources.version_id
+    INNER JOIN projects on sources.project_id = projects.id
+    WHERE symbols.semantic_hash = $1 AND symbols.hash_version = $3 AND NOT symbols.id = $2",
+        semantic_hash,
+        symbol.id as i64,
+        symbol.hash_version
     )
     .fetch_all(&conn)
     .await?;
@@ -255,9 +434,9 @@ pub async fn query_by_exact_hash(
     let syms = sqlx::query_as!(
         DBSymbol,
         "
-    SELECT symbols.id, symbols.slug, symbols.len, symbols.name, symbols.is_decompiled,
+    SELECT symbols.id, symbols.slug, symbols.len, symbols.num_insns, symbols.name, symbols.is_decompiled,
            symbols.symbol_idx,
-           symbols.opcode_hash, symbols.equiv_hash, symbols.exact_hash, symbols.source_id,
+           symbols.opcode_hash, symbols.equiv_hash, symbols.regalloc_hash, symbols.semantic_hash, symbols.exact_hash, symbols.opcode_bits, symbols.hash_version, symbols.source_id,
             sources.name AS source_name, objects.local_path AS object_path, symbols.symbol_idx AS object_symbol_idx,
            versions.id AS \"version_id?\", versions.name AS \"version_name?\", versions.platform,
             projects.name AS project_name, projects.id as project_id,
@@ -267,9 +446,10 @@ pub async fn query_by_exact_hash(
     INNER JOIN objects ON objects.id = sources.object_id
     INNER JOIN versions ON versions.id = sources.version_id
     INNER JOIN projects on sources.project_id = projects.id
-    WHERE symbols.exact_hash = $1 AND NOT symbols.id = $2",
+    WHERE symbols.exact_hash = $1 AND symbols.hash_version = $3 AND NOT symbols.id = $2",
         symbol.exact_hash as i64,
-        symbol.id as i64
+        symbol.id as i64,
+        symbol.hash_version
     )
     .fetch_all(&conn)
     .await?;
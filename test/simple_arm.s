@@ -0,0 +1,52 @@
+	.syntax unified
+	.text
+
+	.arm
+	.global	arm_1
+	.type	arm_1, %function
+arm_1:
+	ldr	r0, =0x1234
+	add	r0, r0, #1
+	bx	lr
+	.ltorg
+	.size	arm_1, .-arm_1
+
+	.global	arm_2
+	.type	arm_2, %function
+arm_2:
+	ldr	r0, =0x5678
+	add	r0, r0, #1
+	bx	lr
+	.ltorg
+	.size	arm_2, .-arm_2
+
+	.thumb
+	.global	thumb_1
+	.type	thumb_1, %function
+	.thumb_func
+thumb_1:
+	ldr	r0, =0x1234
+	adds	r0, r0, #1
+	bx	lr
+	.ltorg
+	.size	thumb_1, .-thumb_1
+
+	.global	thumb_2
+	.type	thumb_2, %function
+	.thumb_func
+thumb_2:
+	ldr	r0, =0x5678
+	adds	r0, r0, #1
+	bx	lr
+	.ltorg
+	.size	thumb_2, .-thumb_2
+
+	.global	thumb_3
+	.type	thumb_3, %function
+	.thumb_func
+thumb_3:
+	ldr	r0, =0x1234
+	adds	r0, r0, #2
+	bx	lr
+	.ltorg
+	.size	thumb_3, .-thumb_3
@@ -0,0 +1,86 @@
+# Code that addresses data with lis/addi pairs, as the ELF compilers for GameCube/Wii do, and
+# code with the patterns that look like them but aren't. Assembled with LINKED defined, the data
+# is placed and calls are resolved, like a linker would.
+
+	.ifdef LINKED
+	.set	cat, 0x80004000
+	.set	dog, 0x80008004
+	.else
+	.globl	test_1, test_2, math_op_1, math_op_2, float_bits, mr_clobber
+	.endif
+
+	.text
+
+	.type	test_1, @function
+test_1:
+	stwu	1, -16(1)
+	mflr	0
+	stw	0, 20(1)
+	lis	3, cat@ha
+	lwz	3, cat@l(3)
+	lis	4, dog@ha
+	lwz	4, dog@l(4)
+	bl	math_op_1
+	lwz	0, 20(1)
+	mtlr	0
+	addi	1, 1, 16
+	blr
+	.size	test_1, .-test_1
+
+	.type	test_2, @function
+test_2:
+	stwu	1, -16(1)
+	mflr	0
+	stw	0, 20(1)
+	lis	3, cat@ha
+	lwz	3, cat@l(3)
+	lis	4, dog@ha
+	lwz	4, dog@l(4)
+	bl	math_op_2
+	lwz	0, 20(1)
+	mtlr	0
+	addi	1, 1, 16
+	blr
+	.size	test_2, .-test_2
+
+	.type	math_op_1, @function
+math_op_1:
+	add	3, 3, 4
+	lis	5, cat@ha
+	stw	3, cat@l(5)
+	blr
+	.size	math_op_1, .-math_op_1
+
+	.type	math_op_2, @function
+math_op_2:
+	subf	3, 4, 3
+	blr
+	.size	math_op_2, .-math_op_2
+
+	# li is addi from r0, which doesn't read r0
+	.type	float_bits, @function
+float_bits:
+	lis	0, 0x4330
+	stw	0, 8(1)
+	li	3, 5
+	blr
+	.size	float_bits, .-float_bits
+
+	# mr writes its first operand
+	.type	mr_clobber, @function
+mr_clobber:
+	lis	4, 0x1234
+	stw	4, 8(1)
+	mr	4, 5
+	addi	3, 4, 8
+	blr
+	.size	mr_clobber, .-mr_clobber
+
+	.ifndef LINKED
+	.data
+	.globl	cat, dog
+cat:
+	.long	1
+dog:
+	.long	5
+	.endif
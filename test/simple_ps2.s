@@ -0,0 +1,35 @@
+    .set noreorder
+    .set noat
+    .text
+
+    .globl vu_1
+    .type vu_1, @function
+vu_1:
+    .word 0x4BE31068 # vadd.xyzw $vf1, $vf2, $vf3
+    jr $ra
+    nop
+    .size vu_1, .-vu_1
+
+    .globl vu_2
+    .type vu_2, @function
+vu_2:
+    .word 0x4BC31068 # vadd.xyz $vf1, $vf2, $vf3
+    jr $ra
+    nop
+    .size vu_2, .-vu_2
+
+    .globl vu_3
+    .type vu_3, @function
+vu_3:
+    .word 0x4BE31128 # vadd.xyzw $vf4, $vf2, $vf3
+    jr $ra
+    nop
+    .size vu_3, .-vu_3
+
+    .globl vu_4
+    .type vu_4, @function
+vu_4:
+    .word 0x4BE31068 # vadd.xyzw $vf1, $vf2, $vf3
+    jr $ra
+    nop
+    .size vu_4, .-vu_4
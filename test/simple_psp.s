@@ -0,0 +1,53 @@
+    .set noreorder
+    .set noat
+    .text
+
+    .globl vfpu_1
+    .type vfpu_1, @function
+vfpu_1:
+    .word 0x60402000 # vadd.s S000, S001, S002
+    jr $ra
+    nop
+    .size vfpu_1, .-vfpu_1
+
+    .globl vfpu_2
+    .type vfpu_2, @function
+vfpu_2:
+    .word 0x60402001 # vadd.s S010, S001, S002
+    jr $ra
+    nop
+    .size vfpu_2, .-vfpu_2
+
+    .globl vfpu_3
+    .type vfpu_3, @function
+vfpu_3:
+    lui $a0, %hi(vfpu_data)
+    lwc2 $0, %lo(vfpu_data)($a0) # lv.s S000, %lo(vfpu_data)($a0)
+    jr $ra
+    nop
+    .size vfpu_3, .-vfpu_3
+
+    .globl vfpu_4
+    .type vfpu_4, @function
+vfpu_4:
+    lui $a0, %hi(vfpu_data+8)
+    lwc2 $0, %lo(vfpu_data+8)($a0) # lv.s S000, %lo(vfpu_data+8)($a0)
+    jr $ra
+    nop
+    .size vfpu_4, .-vfpu_4
+
+    .globl vfpu_5
+    .type vfpu_5, @function
+vfpu_5:
+    .word 0xC8800008 # lv.s S000, 8($a0)
+    jr $ra
+    nop
+    .size vfpu_5, .-vfpu_5
+
+    .globl vfpu_6
+    .type vfpu_6, @function
+vfpu_6:
+    .word 0xC8800010 # lv.s S000, 16($a0)
+    jr $ra
+    nop
+    .size vfpu_6, .-vfpu_6
@@ -0,0 +1,39 @@
+    .set noreorder
+    .set noat
+    .text
+
+    .globl gte_1
+    .type gte_1, @function
+gte_1:
+    .word 0x4A480012 # mvmva 1, 0, 0, 0, 0
+    jr $ra
+    nop
+    .size gte_1, .-gte_1
+
+    .globl gte_2
+    .type gte_2, @function
+gte_2:
+    .word 0x4A400012 # mvmva 0, 0, 0, 0, 0
+    jr $ra
+    nop
+    .size gte_2, .-gte_2
+
+    .globl gte_3
+    .type gte_3, @function
+gte_3:
+    lui $a0, %hi(gte_data)
+    lwc2 $0, %lo(gte_data)($a0)
+    .word 0x4A480012 # mvmva 1, 0, 0, 0, 0
+    jr $ra
+    nop
+    .size gte_3, .-gte_3
+
+    .globl gte_4
+    .type gte_4, @function
+gte_4:
+    lui $a0, %hi(gte_data+8)
+    lwc2 $0, %lo(gte_data+8)($a0)
+    .word 0x4A480012 # mvmva 1, 0, 0, 0, 0
+    jr $ra
+    nop
+    .size gte_4, .-gte_4
@@ -0,0 +1,60 @@
+    .set noreorder
+    .set noat
+    .text
+
+    .globl regalloc_1
+    .type regalloc_1, @function
+regalloc_1:
+    lui $t0, %hi(regalloc_data)
+    lw $t1, %lo(regalloc_data)($t0)
+    addu $t2, $t1, $a0
+    sll $v0, $t2, 2
+    lwc1 $f4, 0($a1)
+    add.s $f0, $f4, $f12
+    jr $ra
+    nop
+    .size regalloc_1, .-regalloc_1
+
+    .globl regalloc_2
+    .type regalloc_2, @function
+regalloc_2:
+    lui $t3, %hi(regalloc_data)
+    lw $t4, %lo(regalloc_data)($t3)
+    addu $t5, $t4, $a0
+    sll $v0, $t5, 2
+    lwc1 $f6, 0($a1)
+    add.s $f0, $f6, $f12
+    jr $ra
+    nop
+    .size regalloc_2, .-regalloc_2
+
+    .globl regalloc_3
+    .type regalloc_3, @function
+regalloc_3:
+    lui $t0, %hi(regalloc_data)
+    lw $t1, %lo(regalloc_data)($t0)
+    addu $t2, $t1, $t1
+    sll $v0, $t2, 2
+    lwc1 $f4, 0($a1)
+    add.s $f0, $f4, $f12
+    jr $ra
+    nop
+    .size regalloc_3, .-regalloc_3
+
+    .globl regalloc_4
+    .type regalloc_4, @function
+regalloc_4:
+    lui $t0, %hi(regalloc_data)
+    lw $t1, %lo(regalloc_data)($t0)
+    addu $t2, $t1, $a0
+    sll $v0, $t2, 2
+    lwc1 $f4, 0($sp)
+    add.s $f0, $f4, $f12
+    jr $ra
+    nop
+    .size regalloc_4, .-regalloc_4
+
+    .data
+    .globl regalloc_data
+regalloc_data:
+    .word 0
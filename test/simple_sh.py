@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+# Writes simple_sh.o, a big-endian SH-2 relocatable object. There's no SH assembler at hand, so
+# the object is put together directly. Each function loads a word from its literal pool:
+#
+#     mov.l   .Lpool, r1
+#     rts
+#     mov.l   @r1, r0
+#     nop
+# .Lpool:
+#     .long   <literal>
+#
+# lit_1 and lit_2 load the addresses of var_a and var_b through R_SH_DIR32 relocations, lit_3
+# and lit_4 load plain constants.
+
+import struct
+
+FUNCS = [
+    ("lit_1", "var_a"),
+    ("lit_2", "var_b"),
+    ("lit_3", 0x00001234),
+    ("lit_4", 0x00005678),
+]
+DATA = ["var_a", "var_b"]
+
+R_SH_DIR32 = 1
+
+text = b""
+relocs = []  # (offset, symbol name)
+for _, literal in FUNCS:
+    offset = len(text)
+    text += struct.pack(">HHHH", 0xD101, 0x000B, 0x6012, 0x0009)
+    if isinstance(literal, str):
+        relocs.append((len(text), literal))
+        literal = 0
+    text += struct.pack(">I", literal)
+data = b"\0" * 4 * len(DATA)
+
+strtab = b"\0"
+symbols = [struct.pack(">IIIBBH", 0, 0, 0, 0, 0, 0)]
+sym_index = {}
+
+
+def add_symbol(name, value, size, kind, shndx):
+    global strtab
+    sym_index[name] = len(symbols)
+    symbols.append(struct.pack(">IIIBBH", len(strtab), value, size, (1 << 4) | kind, 0, shndx))
+    strtab += name.encode() + b"\0"
+
+
+for i, (name, _) in enumerate(FUNCS):
+    add_symbol(name, i * 12, 12, 2, 1)
+for i, name in enumerate(DATA):
+    add_symbol(name, i * 4, 4, 1, 3)
+
+rela = b"".join(
+    struct.pack(">IIi", offset, (sym_index[name] << 8) | R_SH_DIR32, 0) for offset, name in relocs
+)
+symtab = b"".join(symbols)
+
+shstrtab = b"\0"
+names = {}
+for name in [".text", ".rela.text", ".data", ".symtab", ".strtab", ".shstrtab"]:
+    names[name] = len(shstrtab)
+    shstrtab += name.encode() + b"\0"
+
+# (name, type, flags, data, link, info, align, entsize)
+sections = [
+    (".text", 1, 0x6, text, 0, 0, 4, 0),
+    (".rela.text", 4, 0x40, rela, 4, 1, 4, 12),
+    (".data", 1, 0x3, data, 0, 0, 4, 0),
+    (".symtab", 2, 0, symtab, 5, 1, 4, 16),
+    (".strtab", 3, 0, strtab, 0, 0, 1, 0),
+    (".shstrtab", 3, 0, shstrtab, 0, 0, 1, 0),
+]
+
+body = b""
+headers = [b"\0" * 40]
+for name, kind, flags, contents, link, info, align, entsize in sections:
+    while (52 + len(body)) % 4:
+        body += b"\0"
+    headers.append(
+        struct.pack(
+            ">IIIIIIIIII",
+            names[name],
+            kind,
+            flags,
+            0,
+            52 + len(body),
+            len(contents),
+            link,
+            info,
+            align,
+            entsize,
+        )
+    )
+    body += contents
+while (52 + len(body)) % 4:
+    body += b"\0"
+
+ident = b"\x7fELF" + bytes([1, 2, 1]) + b"\0" * 9
+header = ident + struct.pack(
+    ">HHIIIIIHHHHHH",
+    1,  # ET_REL
+    42,  # EM_SH
+    1,
+    0,
+    0,
+    52 + len(body),
+    0,
+    52,
+    0,
+    0,
+    40,
+    len(headers),
+    len(headers) - 1,
+)
+
+with open("simple_sh.o", "wb") as f:
+    f.write(header + body + b"".join(headers))
@@ -0,0 +1,157 @@
+# simple.c for Win32, in the shape of MSVC's /O2 output (floats left out)
+	.intel_syntax noprefix
+	.text
+
+	.def	_test_1; .scl 2; .type 32; .endef
+	.globl	_test_1
+_test_1:
+	mov	eax, dword ptr [esp + 4]
+	test	eax, eax
+	jne	LBB0_1
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_1
+	add	esp, 8
+	ret
+LBB0_1:
+	cmp	eax, 1
+	jne	LBB0_2
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_2
+	add	esp, 8
+	ret
+LBB0_2:
+	cmp	eax, 2
+	jne	LBB0_3
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_some_external_function
+	add	esp, 8
+	ret
+LBB0_3:
+	sub	eax, 3
+	cmp	eax, 1
+	ja	LBB0_4
+	mov	eax, 5
+	ret
+LBB0_4:
+	or	eax, -1
+	ret
+
+	.def	_test_2; .scl 2; .type 32; .endef
+	.globl	_test_2
+_test_2:
+	mov	eax, dword ptr [esp + 4]
+	test	eax, eax
+	jne	LBB1_1
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_2
+	add	esp, 8
+	ret
+LBB1_1:
+	cmp	eax, 1
+	jne	LBB1_2
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_1
+	add	esp, 8
+	ret
+LBB1_2:
+	cmp	eax, 2
+	jne	LBB1_3
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_some_external_function
+	add	esp, 8
+	ret
+LBB1_3:
+	sub	eax, 3
+	cmp	eax, 1
+	ja	LBB1_4
+	mov	eax, 5
+	ret
+LBB1_4:
+	or	eax, -1
+	ret
+
+	.def	_test_3; .scl 2; .type 32; .endef
+	.globl	_test_3
+_test_3:
+	mov	eax, dword ptr [esp + 4]
+	test	eax, eax
+	jne	LBB2_1
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_1
+	add	esp, 8
+	ret
+LBB2_1:
+	cmp	eax, 1
+	jne	LBB2_2
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_math_op_1
+	add	esp, 8
+	ret
+LBB2_2:
+	cmp	eax, 2
+	jne	LBB2_3
+	push	dword ptr [_dog]
+	push	dword ptr [_cat]
+	call	_some_external_function
+	add	esp, 8
+	ret
+LBB2_3:
+	sub	eax, 3
+	cmp	eax, 1
+	ja	LBB2_4
+	mov	eax, 5
+	ret
+LBB2_4:
+	or	eax, -1
+	ret
+
+	.def	_math_op_1; .scl 2; .type 32; .endef
+	.globl	_math_op_1
+_math_op_1:
+	push	esi
+	mov	esi, dword ptr [esp + 8]
+	add	esi, dword ptr [esp + 12]
+	push	dword ptr [esp + 12]
+	push	dword ptr [esp + 12]
+	call	_some_external_function
+	add	esp, 8
+	add	eax, esi
+	pop	esi
+	ret
+
+	.def	_math_op_2; .scl 2; .type 32; .endef
+	.globl	_math_op_2
+_math_op_2:
+	mov	eax, dword ptr [esp + 4]
+	sub	eax, dword ptr [esp + 8]
+	ret
+
+	.def	_math_op_1_dup; .scl 2; .type 32; .endef
+	.globl	_math_op_1_dup
+_math_op_1_dup:
+	push	esi
+	mov	esi, dword ptr [esp + 8]
+	add	esi, dword ptr [esp + 12]
+	push	dword ptr [esp + 12]
+	push	dword ptr [esp + 12]
+	call	_some_external_function
+	add	esp, 8
+	add	eax, esi
+	pop	esi
+	ret
+
+	.data
+	.globl	_cat
+_cat:
+	.long	1
+	.globl	_dog
+_dog:
+	.long	5
@@ -0,0 +1,45 @@
+# Functions that refer to data by address in immediates as well as memory operands. Assembled as
+# is for the Win32 object, or with LINKED defined (as ELF, for llvm-objcopy) to stand in for the
+# same code once linked, with the data at the addresses simple_x86_relocs.map gives it. AT&T
+# syntax, as llvm-mc can't relocate `push offset x` in Intel syntax.
+
+.ifdef LINKED
+_cat = 0x405000
+_dog = 0x405004
+_callback = 0x405008
+.else
+	.def	_push_offsets; .scl 2; .type 32; .endef
+	.def	_mov_offsets; .scl 2; .type 32; .endef
+.endif
+
+	.text
+
+	.globl	_push_offsets
+_push_offsets:
+	pushl	$_dog
+	pushl	$_cat
+	calll	*_callback
+	addl	$8, %esp
+	retl
+
+	.globl	_mov_offsets
+_mov_offsets:
+	movl	$_cat, %eax
+	cmpl	$_dog, %ecx
+	jne	LBB1_1
+	movl	$5, %eax
+LBB1_1:
+	retl
+
+.ifndef LINKED
+	.data
+	.globl	_cat
+_cat:
+	.long	0
+	.globl	_dog
+_dog:
+	.long	0
+	.globl	_callback
+_callback:
+	.long	0
+.endif
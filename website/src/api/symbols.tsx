@@ -13,6 +13,12 @@ export type SymbolMetadata = {
   project_name: string;
   project_repo?: string;
   platform: number;
+  significance?: Significance | null;
+};
+
+export type Significance = {
+  bits: number;
+  evalue: number;
 };
 
 export const isDecompmeScratch = (symbol: SymbolMetadata): boolean => {
@@ -20,7 +26,7 @@ export const isDecompmeScratch = (symbol: SymbolMetadata): boolean => {
 };
 
 export type SymbolMatchResult = {
-  subtype: 'exact' | 'equivalent' | 'opcode';
+  subtype: 'exact' | 'semantic' | 'equivalent' | 'regalloc' | 'opcode';
   symbol: SymbolMetadata;
 };
 
@@ -29,6 +35,8 @@ export type SymbolSubmatchResult = {
   query_start: number;
   match_start: number;
   len: number;
+  match_len: number;
+  identity: number;
 };
 
 export type SymbolSubmatchResults = {
@@ -37,6 +37,11 @@ export function SymbolMatches({ slug }: { slug: string }) {
             <div className={styles.matchHeader}>
               <span
                 className={`${styles.matchBadge} ${styles[match.subtype] || styles.default}`}
+                title={
+                  match.symbol.significance
+                    ? `E-value ${match.symbol.significance.evalue.toExponential(2)}`
+                    : undefined
+                }
               >
                 {match.subtype}
               </span>